@@ -0,0 +1,37 @@
+#version 320 es
+
+in vec2 vert_position;
+
+in vec3 instance_corner;
+in vec3 instance_first_edge;
+in vec3 instance_second_edge;
+in int instance_texture_index;
+in float instance_damage;
+
+uniform mat4 uni_model_matrix;
+uniform mat4 uni_projection_view_matrix;
+
+out vec3 normal;
+out vec2 texture_coord;
+out flat int texture_index;
+out flat float damage;
+
+mat4 extract_rotation_matrix()
+{
+    // Note: won't work if uni_model_matrix contains scale.
+    mat4 rotation_matrix = uni_model_matrix;
+    rotation_matrix[3] = vec4(0.f, 0.f, 0.f, 1.f);
+    return rotation_matrix;
+}
+
+void main()
+{
+    // The unit square is stretched over the merged faces, the textures repeat once per voxel.
+    vec3 position = instance_corner + vert_position.x * instance_first_edge + vert_position.y * instance_second_edge;
+    gl_Position = uni_projection_view_matrix * uni_model_matrix * vec4(position, 1.0);
+    vec3 face_normal = normalize(cross(instance_first_edge, instance_second_edge));
+    normal = (extract_rotation_matrix() * vec4(face_normal, 1.0)).xyz;
+    texture_coord = vert_position * vec2(length(instance_first_edge), length(instance_second_edge));
+    texture_index = instance_texture_index;
+    damage = instance_damage;
+}
@@ -10,7 +10,6 @@ use crate::graphic::windowing::window::Window;
 use crate::physics::body::Body;
 use crate::voxels::structure::Structure;
 use crate::voxels::catalog::VoxelCatalog;
-use crate::voxels::voxel::Voxel;
 use crate::voxels::voxel::VoxelID;
 
 pub struct Editor {
@@ -28,15 +27,16 @@ const SAVE_FILENAME: &str = "save.vors";
 
 impl Editor {
     fn new() -> Self {
+        let voxel_catalog = VoxelCatalog::create();
         Self {
-            structure: Structure::new(0, 0, 0, 0, 0, 0, Voxel{id: VoxelID::ShipCore, life: 5.0}),
+            structure: Structure::new(&voxel_catalog, 0, 0, 0, 0, 0, 0, voxel_catalog.create_voxel(VoxelID::SHIP_CORE)),
             voxel_position: None,
-            voxel_id: VoxelID::LightHull,
+            voxel_id: VoxelID::LIGHT_HULL,
             symetry_x: false,
             symetry_y: false,
             symetry_z: false,
 
-            voxel_catalog: VoxelCatalog::create(),
+            voxel_catalog: voxel_catalog,
         }
     }
 
@@ -58,35 +58,26 @@ impl Editor {
             println!("Saved file '{SAVE_FILENAME}'");
         }
         if event_handler.is_key_just_pressed(Key::F9) {
-            let metadata = std::fs::metadata(SAVE_FILENAME);
-            if metadata.is_ok() {
-                self.structure = Structure::read_from_file(&self.voxel_catalog, SAVE_FILENAME);
-                println!("Loaded file '{SAVE_FILENAME}'");
-            } else {
-                let error = metadata.unwrap_err().to_string();
-                eprintln!("Cannot load file '{SAVE_FILENAME}': {error}");
+            match Structure::read_from_file(&self.voxel_catalog, SAVE_FILENAME) {
+                Ok(structure) => {
+                    self.structure = structure;
+                    println!("Loaded file '{SAVE_FILENAME}'");
+                },
+                Err(error) => eprintln!("Cannot load file '{SAVE_FILENAME}': {error}"),
             }
         }
-        if event_handler.scroll_status() < 0.0 {
-            self.voxel_id = match self.voxel_id as i32 {
-                0 => VoxelID::COUNT as i32 - 1,
-                other => other - 1,
-            }.into();
-        }
-        else if event_handler.scroll_status() > 0.0 {
-            const LAST_VOXEL_ID: i32 = VoxelID::COUNT as i32 - 1;
-            self.voxel_id = match self.voxel_id as i32 {
-                LAST_VOXEL_ID => 0,
-                other => other + 1,
-            }.into();
+        if event_handler.scroll_status() != 0.0 {
+            let ids = self.voxel_catalog.ids();
+            let index = ids.iter().position(|id| *id == self.voxel_id).unwrap_or(0);
+            let new_index = (if event_handler.scroll_status() < 0.0 { index + ids.len() - 1 } else { index + 1 }) % ids.len();
+            self.voxel_id = ids[new_index];
+            let descriptor = self.voxel_catalog.get_descriptor(self.voxel_id);
+            println!("Selected voxel type '{}' (life {}, density {} t/m3)", descriptor.name, descriptor.max_life, descriptor.density);
         }
 
         if event_handler.is_mouse_button_just_released(MouseButton::Left) {
             let segment = Segm3f::new(camera.position(), camera.position() + camera.forward() * 4.0);
-            let mut aimed_coords = None;
-            self.structure.for_first_voxel_in_segment(segment, |_voxel, coords| {
-                aimed_coords = Some(*coords);
-            });
+            let aimed_coords = self.structure.ray_cast(segment).map(|hit| hit.coords);
             if aimed_coords.is_some() {
                 let position = aimed_coords.unwrap();
                 self.structure.remove_voxel(position);
@@ -10,7 +10,7 @@ use crate::physics::body::Body;
 use crate::voxels::catalog::VoxelCatalog;
 
 pub struct BodyRenderer {
-    cube_mesh: Mesh,
+    voxel_face_mesh: Mesh,
     voxel_catalog: VoxelCatalog,
 
     debug_cube_mesh: Mesh,
@@ -56,7 +56,7 @@ fn positions_from_box(min: Vect3f, max: Vect3f) -> Vec<f32> {
 
 impl BodyRenderer {
     pub fn new() -> Self {
-        let cube_mesh = cube::cube_mesh(1.0);
+        let voxel_face_mesh = cube::voxel_face_mesh();
 
         let debug_cube = {
             let mut material = Material::create("shaders/position.vert", "shaders/hello_color.frag");
@@ -95,7 +95,7 @@ impl BodyRenderer {
         };
 
         Self {
-            cube_mesh: cube_mesh,
+            voxel_face_mesh: voxel_face_mesh,
             voxel_catalog: VoxelCatalog::create(),
 
             debug_cube_mesh: debug_cube,
@@ -126,23 +126,30 @@ impl BodyRenderer {
     pub fn render(&mut self, projection_view_matrix: &Mat4f, bodies: Vec<&Body>) {
         for body in bodies {
             if self.show_bodies {
-                let mut instance_positions: Vec<f32> = Default::default();
+                // Only the exposed faces are drawn, merged into rectangles, the mesh is rebuilt when voxels change.
+                let surface_mesh = body.structure().surface_mesh(&self.voxel_catalog);
+                let mut instance_corners: Vec<f32> = Default::default();
+                let mut instance_first_edges: Vec<f32> = Default::default();
+                let mut instance_second_edges: Vec<f32> = Default::default();
                 let mut instance_texture_indices: Vec<i32> = Default::default();
                 let mut instance_damages: Vec<f32> = Default::default();
-                body.structure().for_each_voxel(|coords, voxel| {
-                    let descriptor = self.voxel_catalog.get_descriptor(voxel.id);
-                    instance_positions.push(coords[0] as f32);
-                    instance_positions.push(coords[1] as f32);
-                    instance_positions.push(coords[2] as f32);
-                    instance_texture_indices.push(descriptor.texture_type as i32);
-                    instance_damages.push(1.0 - voxel.life / descriptor.max_life);
-                });
+                for quad in surface_mesh.quads() {
+                    let first_edge = quad.corners[1] - quad.corners[0];
+                    let second_edge = quad.corners[3] - quad.corners[0];
+                    instance_corners.extend_from_slice(&[quad.corners[0][0], quad.corners[0][1], quad.corners[0][2]]);
+                    instance_first_edges.extend_from_slice(&[first_edge[0], first_edge[1], first_edge[2]]);
+                    instance_second_edges.extend_from_slice(&[second_edge[0], second_edge[1], second_edge[2]]);
+                    instance_texture_indices.push(self.voxel_catalog.get_descriptor(quad.voxel_id).texture_type as i32);
+                    instance_damages.push(quad.damage);
+                }
                 let instance_count = instance_damages.len().try_into().unwrap();
-                self.cube_mesh.set_instanced_data(0, &instance_positions);
-                self.cube_mesh.set_instanced_data(1, &instance_texture_indices);
-                self.cube_mesh.set_instanced_data(2, &instance_damages);
-                self.cube_mesh.set_uniform_matrix("uni_model_matrix", body.repere());
-                self.cube_mesh.draw_instanced(instance_count, &projection_view_matrix);
+                self.voxel_face_mesh.set_instanced_data(0, &instance_corners);
+                self.voxel_face_mesh.set_instanced_data(1, &instance_first_edges);
+                self.voxel_face_mesh.set_instanced_data(2, &instance_second_edges);
+                self.voxel_face_mesh.set_instanced_data(3, &instance_texture_indices);
+                self.voxel_face_mesh.set_instanced_data(4, &instance_damages);
+                self.voxel_face_mesh.set_uniform_matrix("uni_model_matrix", body.repere());
+                self.voxel_face_mesh.draw_instanced(instance_count, &projection_view_matrix);
             }
 
             if self.show_boxes {
@@ -178,14 +185,14 @@ impl BodyRenderer {
                     let min = Vect3f::new([oct.min()[0] as f32, oct.min()[1] as f32, oct.min()[2] as f32]) + recenter;
                     let max = Vect3f::new([oct.max()[0] as f32, oct.max()[1] as f32, oct.max()[2] as f32]) + recenter;
                     let positions = positions_from_box(min, max);
+                    // The octree root grows with the structure, its biggest cells share the same color.
                     let color = match oct.extent()[0] {
-                        32 => Vect4f::new([0.0, 1.0, 1.0, 1.0]),
                         16 => Vect4f::new([0.0, 1.0, 0.0, 1.0]),
                         8 => Vect4f::new([1.0, 1.0, 0.0, 1.0]),
                         4 => Vect4f::new([1.0, 0.0, 1.0, 1.0]),
                         2 => Vect4f::new([1.0, 0.0, 1.0, 1.0]),
                         1 => Vect4f::new([1.0, 1.0, 1.0, 1.0]),
-                        _ => todo!(),
+                        _ => Vect4f::new([0.0, 1.0, 1.0, 1.0]),
                     };
                     self.debug_cube_mesh.set_positions_3d(&positions);
                     self.debug_cube_mesh.set_uniform_vector("uni_color", color);
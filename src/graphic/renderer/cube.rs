@@ -38,6 +38,39 @@ pub fn circle_texture(border_color: Color, fill_color: Color) -> Texture {
     Texture::create(8, 8, texture_pixels)
 }
 
+fn fill_color(texture_type: TextureType) -> Color {
+    let [red, green, blue] = texture_type.fill_color();
+    Color::new(red, green, blue, 0xFF)
+}
+
+fn add_voxel_textures(material: &mut Material) {
+    let hull_texture = cube_texture(Color::new(0x40, 0x40, 0x40, 0xFF), fill_color(TextureType::LightHull));
+    material.add_texture(&format!("voxel_texture[{}]", TextureType::LightHull as i32), hull_texture);
+    let hull_texture = cube_texture(Color::new(0x20, 0x20, 0x20, 0xFF), fill_color(TextureType::DarkHull));
+    material.add_texture(&format!("voxel_texture[{}]", TextureType::DarkHull as i32), hull_texture);
+    let core_texture = cube_texture(Color::new(0x80, 0x80, 0x80, 0xFF), fill_color(TextureType::Core));
+    material.add_texture(&format!("voxel_texture[{}]", TextureType::Core as i32), core_texture);
+    let canon_texture = circle_texture(Color::new(0x80, 0x80, 0x80, 0xFF), fill_color(TextureType::Canon));
+    material.add_texture(&format!("voxel_texture[{}]", TextureType::Canon as i32), canon_texture);
+
+    let damage_texture = {
+        let b = Color::black();
+        let t = Color::transparent();
+        let texture_pixels = [
+            t,  b,  t,  t,  t,  t,  t,  b,
+            t,  b,  t,  t,  t,  b,  b,  t,
+            t,  b,  t,  t,  b,  t,  t,  b,
+            t,  t,  b,  t,  b,  b,  b,  b,
+            t,  t,  b,  b,  b,  t,  t,  t,
+            t,  b,  t,  b,  t,  b,  t,  b,
+            b,  t,  t,  t,  t,  b,  t,  b,
+            t,  t,  t,  t,  t,  t,  b,  b,
+        ].to_vec();
+        Texture::create(8, 8, texture_pixels)
+    };
+    material.add_texture("damage_texture", damage_texture);
+}
+
 pub fn cube_mesh(alpha: f32) -> Mesh {
 
     let positions: Vec<f32> = [
@@ -180,31 +213,7 @@ pub fn cube_mesh(alpha: f32) -> Mesh {
         material.add_instance_data_buffer("instance_damage", 1);
         material.add_uniform_mat4("uni_model_matrix", Mat4f::identity());
 
-        let hull_texture = cube_texture(Color::new(0x40, 0x40, 0x40, 0xFF), Color::new(0x80, 0x80, 0x80, 0xFF));
-        material.add_texture(&format!("voxel_texture[{}]", TextureType::LightHull as i32), hull_texture);
-        let hull_texture = cube_texture(Color::new(0x20, 0x20, 0x20, 0xFF), Color::new(0x30, 0x30, 0x30, 0xFF));
-        material.add_texture(&format!("voxel_texture[{}]", TextureType::DarkHull as i32), hull_texture);
-        let core_texture = cube_texture(Color::new(0x80, 0x80, 0x80, 0xFF), Color::new(0x80, 0x80, 0xFF, 0xFF));
-        material.add_texture(&format!("voxel_texture[{}]", TextureType::Core as i32), core_texture);
-        let canon_texture = circle_texture(Color::new(0x80, 0x80, 0x80, 0xFF), Color::new(0x20, 0x20, 0x20, 0xFF));
-        material.add_texture(&format!("voxel_texture[{}]", TextureType::Canon as i32), canon_texture);
-
-        let damage_texture = {
-            let b = Color::black();
-            let t = Color::transparent();
-            let texture_pixels = [
-                t,  b,  t,  t,  t,  t,  t,  b,
-                t,  b,  t,  t,  t,  b,  b,  t,
-                t,  b,  t,  t,  b,  t,  t,  b,
-                t,  t,  b,  t,  b,  b,  b,  b,
-                t,  t,  b,  b,  b,  t,  t,  t,
-                t,  b,  t,  b,  t,  b,  t,  b,
-                b,  t,  t,  t,  t,  b,  t,  b,
-                t,  t,  t,  t,  t,  t,  b,  b,
-            ].to_vec();
-            Texture::create(8, 8, texture_pixels)
-        };
-        material.add_texture("damage_texture", damage_texture);
+        add_voxel_textures(&mut material);
 
         material.add_uniform_f32("uni_alpha", alpha);
         material
@@ -215,4 +224,34 @@ pub fn cube_mesh(alpha: f32) -> Mesh {
     mesh.set_normals(&normals);
     mesh.set_texture_coords(&tex_coords);
     mesh
-}
\ No newline at end of file
+}
+
+// Unit square stretched by each instance over a rectangle of voxel faces.
+pub fn voxel_face_mesh() -> Mesh {
+    let positions: Vec<f32> = [
+        0.0, 0.0,
+        1.0, 0.0,
+        1.0, 1.0,
+        1.0, 1.0,
+        0.0, 1.0,
+        0.0, 0.0,
+    ].to_vec();
+
+    let material = {
+        let mut material = Material::create("shaders/voxel_face.vert", "shaders/voxel.frag");
+
+        material.add_instance_data_buffer("instance_corner", 3);
+        material.add_instance_data_buffer("instance_first_edge", 3);
+        material.add_instance_data_buffer("instance_second_edge", 3);
+        material.add_instance_data_buffer("instance_texture_index", 1);
+        material.add_instance_data_buffer("instance_damage", 1);
+        material.add_uniform_mat4("uni_model_matrix", Mat4f::identity());
+        add_voxel_textures(&mut material);
+        material.add_uniform_f32("uni_alpha", 1.0);
+        material
+    };
+
+    let mut mesh = Mesh::create(Primitive::Triangles, material, false);
+    mesh.set_positions_2d(&positions);
+    mesh
+}
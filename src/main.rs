@@ -14,42 +14,91 @@ use graphic::windowing::window::Window;
 use graphic::windowing::event_handler::Key;
 use graphic::windowing::event_handler::MouseButton;
 use maths::matrix::Mat4f;
+use maths::random::Random;
 use maths::vector::Vect3f;
 use physics::body::Body;
 use voxels::structure::Structure;
 use voxels::catalog::VoxelCatalog;
+use voxels::generation::AsteroidParameters;
+use voxels::generation::DerelictParameters;
+use voxels::generation::generate_asteroid;
+use voxels::generation::generate_derelict;
+use voxels::mesh::SurfaceMesh;
+use voxels::vox::VoxPalette;
+use voxels::voxel::DamageType;
+use voxels::voxel::VoxelID;
 use warfare::battle::Battle;
 use warfare::ship::Ship;
+use warfare::ship::ShipControls;
 use warfare::weapon::Weapon;
 
-fn update_player_body_from_events(player_body: &mut Body, event_handler: &EventHandler) {
-    let forward = player_body.repere().forward();
-    let right = player_body.repere().right();
+// The ship modules turn the pilot throttles into thrust and torque.
+fn update_player_ship_from_events(player_ship: &mut Ship, voxel_catalog: &VoxelCatalog, event_handler: &EventHandler, elapsed_time: f32) {
+    let mut thrust = Vect3f::zero();
     if event_handler.is_key_pressed(Key::W) {
-        player_body.add_to_velocity(forward);
+        thrust[0] += 1.0;
     }
     if event_handler.is_key_pressed(Key::S) {
-        player_body.add_to_velocity(-forward);
+        thrust[0] -= 1.0;
     }
     if event_handler.is_key_pressed(Key::D) {
-        player_body.add_to_velocity(right);
+        thrust[1] -= 1.0;
     }
     if event_handler.is_key_pressed(Key::A) {
-        player_body.add_to_velocity(-right);
+        thrust[1] += 1.0;
     }
 
+    let mut rotation = Vect3f::zero();
     if event_handler.is_key_pressed(Key::E) {
-        player_body.add_roll_rotation(0.1);
+        rotation[0] += 0.5;
     }
     if event_handler.is_key_pressed(Key::Q) {
-        player_body.add_roll_rotation(-0.1);
+        rotation[0] -= 0.5;
     }
     let cursor_movement = event_handler.cursor_movement();
-    player_body.add_yaw_rotation(-cursor_movement.0 as f32 / 100.0);
-    player_body.add_pitch_rotation(cursor_movement.1 as f32 / 100.0);
+    rotation[1] = (cursor_movement.1 as f32 / 20.0).clamp(-1.0, 1.0);
+    rotation[2] = (-cursor_movement.0 as f32 / 20.0).clamp(-1.0, 1.0);
 
-    player_body.scale_rotation(0.9);
-    player_body.scale_velocity(0.95);
+    player_ship.steer(voxel_catalog, &ShipControls { thrust: thrust, rotation: rotation }, elapsed_time);
+}
+
+// Asteroids and derelicts scattered ahead of the player, the same ones for a given seed. None of them overlaps
+// another body, the player ship included.
+fn add_obstacles(battle: &mut Battle, voxel_catalog: &VoxelCatalog, derelict_parts: &[Structure], seed: u64) {
+    const ASTEROID_COUNT: usize = 6;
+    const DERELICT_COUNT: usize = 2;
+
+    let mut random = Random::new(seed);
+    let random_position = |random: &mut Random| Vect3f::new([random.range_f32(30.0, 90.0), random.range_f32(-50.0, 50.0), random.range_f32(-50.0, 50.0)]);
+    // Positions are drawn again until the obstacle box is clear of the bodies already in the battle.
+    let add_obstacle = |battle: &mut Battle, random: &mut Random, structure: Structure| {
+        let mut position = random_position(random);
+        let mut body = Body::new(structure, Mat4f::translation(position));
+        while battle.bodies().iter().any(|placed_body| placed_body.get_box().intersects(&body.get_box())) {
+            let new_position = random_position(random);
+            body.translate(new_position - position);
+            position = new_position;
+        }
+        battle.add_inert_body(body);
+    };
+    for _ in 0..ASTEROID_COUNT {
+        let parameters = AsteroidParameters {
+            radius: random.range_f32(3.0, 8.0),
+            roughness: random.range_f32(0.1, 0.4),
+            layers: vec![VoxelID::LIGHT_HULL, VoxelID::HEAVY_HULL],
+        };
+        let asteroid = generate_asteroid(voxel_catalog, &parameters, random.next_u64());
+        add_obstacle(battle, &mut random, asteroid);
+    }
+    for _ in 0..DERELICT_COUNT {
+        let parameters = DerelictParameters {
+            part_count: random.range_i32(2, 4) as usize,
+            missing_voxel_ratio: 0.1,
+            damaged_voxel_ratio: 0.3,
+        };
+        let derelict = generate_derelict(derelict_parts, &parameters, random.next_u64());
+        add_obstacle(battle, &mut random, derelict);
+    }
 }
 
 fn run_battle() {
@@ -62,28 +111,33 @@ fn run_battle() {
     let mut battle = Battle::new();
 
     let voxel_catalog = VoxelCatalog::create();
-    let tie_fighter_structure = Structure::read_from_file(&voxel_catalog, "structures/tie.vors");
-    battle.add_inert_body(Body::new(tie_fighter_structure, Mat4f::identity()));
+    let tie_fighter_structure = Structure::read_from_file(&voxel_catalog, "structures/tie.vors").expect("Unable to load tie fighter structure");
+    battle.add_inert_body(Body::new(tie_fighter_structure.clone(), Mat4f::identity()));
 
     let player_ship = {
         let player_repere = Mat4f::translation(Vect3f::new([-20.0, 0.0, 0.0]));
-        let player_structure = Structure::read_from_file(&voxel_catalog, "structures/x_wing.vors");
+        let player_structure = Structure::read_from_file(&voxel_catalog, "structures/x_wing.vors").expect("Unable to load x-wing structure");
         let player_body = Body::new(player_structure, player_repere);
         let mut ship = Ship::new(player_body);
-        ship.add_weapon(Vect3f::new([4.0, -4.0, -3.0]), Weapon::new(0.5, 1.0, 100.0, 1000.0));
-        ship.add_weapon(Vect3f::new([4.0, 4.0, -3.0]), Weapon::new(0.5, 1.0, 100.0, 1000.0));
-        ship.add_weapon(Vect3f::new([4.0, -4.0, 3.0]), Weapon::new(0.5, 1.0, 100.0, 1000.0));
-        ship.add_weapon(Vect3f::new([4.0, 4.0, 3.0]), Weapon::new(0.5, 1.0, 100.0, 1000.0));
+        ship.add_weapon(Vect3f::new([4.0, -4.0, -3.0]), Weapon::new(0.5, 1.0, DamageType::Energy, 100.0, 1000.0));
+        ship.add_weapon(Vect3f::new([4.0, 4.0, -3.0]), Weapon::new(0.5, 1.0, DamageType::Energy, 100.0, 1000.0));
+        // Slower heavy guns, going through a couple of light hull voxels but stopped by heavy hull.
+        ship.add_weapon(Vect3f::new([4.0, -4.0, 3.0]), Weapon::new(1.0, 3.0, DamageType::Kinetic, 100.0, 1000.0));
+        ship.add_weapon(Vect3f::new([4.0, 4.0, 3.0]), Weapon::new(1.0, 3.0, DamageType::Kinetic, 100.0, 1000.0));
         ship
     };
+    let derelict_parts = [tie_fighter_structure, player_ship.body().structure().clone()];
     battle.set_player_ship(player_ship);
 
+    const OBSTACLE_SEED: u64 = 1;
+    add_obstacles(&mut battle, &voxel_catalog, &derelict_parts, OBSTACLE_SEED);
+
     let mut pause = false;
 
     let tick_elapsed_time = 1.0 / 60.0; // Rather than real elapsed time in order to keep determinism.
     while !window.should_close() {
 
-        update_player_body_from_events(battle.player_ship_mut().unwrap().body_mut(), window.event_handler());
+        update_player_ship_from_events(battle.player_ship_mut().unwrap(), &voxel_catalog, window.event_handler(), tick_elapsed_time);
 
         if window.event_handler().is_mouse_button_pressed(MouseButton::Left) {
             let projectiles = battle.player_ship_mut().unwrap().shoot();
@@ -111,7 +165,7 @@ fn run_battle() {
         }
 
         if !pause {
-            battle.update(tick_elapsed_time);
+            battle.update(&voxel_catalog, tick_elapsed_time);
         }
 
         window.clear();
@@ -129,6 +183,34 @@ fn run_battle() {
 
 }
 
+// MagicaVoxel files are recognized by their extension, both ways. OBJ and PLY files are only written, as surface meshes.
+fn convert_structure(input_file_name: &str, output_file_name: &str) {
+    let voxel_catalog = VoxelCatalog::create();
+    let has_extension = |file_name: &str, expected_extension: &str| std::path::Path::new(file_name).extension().is_some_and(|extension| extension == expected_extension);
+    let is_vox_file = |file_name: &str| has_extension(file_name, Structure::VOX_EXTENSION);
+    let vox_palette = || VoxPalette::create(&voxel_catalog);
+    let loaded_structure = if is_vox_file(input_file_name) {
+        Structure::read_vox_file(&voxel_catalog, &vox_palette(), input_file_name)
+    } else {
+        Structure::read_from_file(&voxel_catalog, input_file_name)
+    };
+    match loaded_structure {
+        Ok(structure) => {
+            if is_vox_file(output_file_name) {
+                structure.write_vox_file(&vox_palette(), output_file_name).unwrap_or_else(|error| panic!("Unable to write '{output_file_name}': {error}"));
+            } else if has_extension(output_file_name, "obj") {
+                SurfaceMesh::from_structure(&structure, &voxel_catalog).write_obj_file(&voxel_catalog, output_file_name).unwrap_or_else(|error| panic!("Unable to write '{output_file_name}': {error}"));
+            } else if has_extension(output_file_name, "ply") {
+                SurfaceMesh::from_structure(&structure, &voxel_catalog).write_ply_file(&voxel_catalog, output_file_name).unwrap_or_else(|error| panic!("Unable to write '{output_file_name}': {error}"));
+            } else {
+                structure.write_to_file(output_file_name).unwrap_or_else(|error| panic!("Unable to write '{output_file_name}': {error}"));
+            }
+            println!("Converted '{input_file_name}' to '{output_file_name}'");
+        },
+        Err(error) => eprintln!("Cannot load file '{input_file_name}': {error}"),
+    }
+}
+
 fn main() {
     let first_arg = std::env::args().nth(1);
     if first_arg.is_some() && first_arg.as_ref().unwrap() == "demo" {
@@ -137,6 +219,10 @@ fn main() {
         editor::run_editor();
     } else if first_arg.is_some() && first_arg.as_ref().unwrap() == "profile" {
         profiler::run_profiler();
+    } else if first_arg.is_some() && first_arg.as_ref().unwrap() == "convert" {
+        let input_file_name = std::env::args().nth(2).expect("Missing input structure file");
+        let output_file_name = std::env::args().nth(3).expect("Missing output structure file");
+        convert_structure(&input_file_name, &output_file_name);
     } else {
         run_battle();
     }
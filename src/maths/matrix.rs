@@ -31,6 +31,16 @@ impl<const N: usize, T: MathsUsable> Mat<N, T> {
         result
     }
 
+    pub fn transpose(&self) -> Self {
+        let mut result = Self::zero();
+        for x in 0..N {
+            for y in 0..N {
+                result[y][x] = self[x][y];
+            }
+        }
+        result
+    }
+
     pub fn data_as_ptr(&self) -> *const T {
         assert!(std::mem::size_of_val(self) == std::mem::size_of::<T>() * 16);
         self.data.as_ptr().cast()
@@ -46,6 +56,23 @@ impl<T: MathsUsable> Mat<3, T> {
         self[0][1] * self[1][0] * self[2][2] -
         self[0][0] * self[1][2] * self[2][1]
     }
+
+    // Rows of the inverse are the cross products of the columns, divided by the determinant.
+    pub fn inverse(&self) -> Self {
+        let oo_det = T::from(1) / self.determinant();
+        let rows = [
+            Vect::cross(self[1], self[2]),
+            Vect::cross(self[2], self[0]),
+            Vect::cross(self[0], self[1]),
+        ];
+        let mut result = Self::zero();
+        for x in 0..3 {
+            for y in 0..3 {
+                result[x][y] = rows[y][x] * oo_det;
+            }
+        }
+        result
+    }
 }
 
 impl<T: MathsUsable> Mat<4, T> {
@@ -105,11 +132,6 @@ impl<T: MathsUsable> Mat<4, T> {
         Vect::<3, T>::new([inverse[0][0], inverse[1][0], inverse[2][0]])
     }
 
-    pub fn right(&self) -> Vect<3, T> {
-        let inverse = self.inverse();
-        -Vect::<3, T>::new([inverse[0][1], inverse[1][1], inverse[2][1]])
-    }
-
     pub fn up(&self) -> Vect<3, T> {
         let inverse = self.inverse();
         Vect::<3, T>::new([inverse[0][2], inverse[1][2], inverse[2][2]])
@@ -207,6 +229,26 @@ impl<const N: usize> Mat<N, f32> {
         result
     }
 
+    // Axis must be normalized.
+    pub fn rotation_around_axis(axis: Vect<3, f32>, angle: f32) -> Self {
+        assert!(N >= 3);
+        let cos = angle.cos();
+        let sin = angle.sin();
+        let cross_product = [
+            [0.0, axis[2], -axis[1]],
+            [-axis[2], 0.0, axis[0]],
+            [axis[1], -axis[0], 0.0],
+        ];
+        let mut result = Self::identity();
+        for x in 0..3 {
+            for y in 0..3 {
+                let identity = if x == y { cos } else { 0.0 };
+                result[x][y] = identity + sin * cross_product[x][y] + (1.0 - cos) * axis[x] * axis[y];
+            }
+        }
+        result
+    }
+
     pub fn look_at(eye: Vect<3, f32>, target: Vect<3, f32>, up: Vect<3, f32>) -> Self {
         assert!(eye != target);
         let zaxis = (target - eye).normalize();
@@ -267,10 +309,10 @@ impl<const N: usize, T: MathsUsable> std::ops::Mul<Self> for Mat<N, T> {
 
     fn mul(self, rhs: Self) -> Self::Output {
         let mut product = Self::identity();
-        for y in 0..4 {
-            for x in 0..4 {
+        for y in 0..N {
+            for x in 0..N {
                 let mut result = T::from(0);
-                for i in 0..4 {
+                for i in 0..N {
                     result += self[i][y] * rhs[x][i];
                 }
                 product[x][y] = result;
@@ -296,6 +338,6 @@ impl<const N: usize, const VN: usize, T: MathsUsable> std::ops::Mul<Vect<VN, T>>
     }
 }
 
-#[allow(dead_code)]
 pub type Mat3f = Mat<3, f32>;
+pub type Mat3d = Mat<3, f64>;
 pub type Mat4f = Mat<4, f32>;
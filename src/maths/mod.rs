@@ -1,6 +1,7 @@
 pub mod boxes;
 pub mod intersection;
 pub mod matrix;
+pub mod random;
 pub mod segment;
 pub mod vector;
 
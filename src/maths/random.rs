@@ -0,0 +1,93 @@
+use super::vector::Vect3f;
+
+// Seeded pseudo random numbers, using SplitMix64 by Sebastiano Vigna.
+// Same seed, same sequence on every platform, so that generated content can be replayed.
+#[derive(Clone)]
+pub struct Random {
+    state: u64,
+}
+
+impl Random {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            state: seed,
+        }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E3779B97F4A7C15);
+        mix(self.state)
+    }
+
+    // In [0, 1).
+    pub fn next_f32(&mut self) -> f32 {
+        // The 24 highest bits, as many as a f32 mantissa holds.
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // In [min, max).
+    pub fn range_f32(&mut self, min: f32, max: f32) -> f32 {
+        min + (max - min) * self.next_f32()
+    }
+
+    // In [min, max], bounds included.
+    pub fn range_i32(&mut self, min: i32, max: i32) -> i32 {
+        assert!(min <= max);
+        let count = (max as i64 - min as i64 + 1) as u64;
+        (min as i64 + (self.next_u64() % count) as i64) as i32
+    }
+
+    pub fn index(&mut self, len: usize) -> usize {
+        assert!(len > 0);
+        (self.next_u64() % len as u64) as usize
+    }
+
+    pub fn chance(&mut self, probability: f32) -> bool {
+        self.next_f32() < probability
+    }
+}
+
+fn mix(value: u64) -> u64 {
+    let mut result = value;
+    result = (result ^ (result >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    result = (result ^ (result >> 27)).wrapping_mul(0x94D049BB133111EB);
+    result ^ (result >> 31)
+}
+
+// Value noise: random values on integer coordinates, smoothly interpolated in between. In [0, 1].
+pub fn value_noise(seed: u64, position: Vect3f) -> f32 {
+    let cell = [position[0].floor(), position[1].floor(), position[2].floor()];
+    let weights: Vec<f32> = (0..3).map(|axis| {
+        let t = position[axis] - cell[axis];
+        t * t * (3.0 - 2.0 * t)
+    }).collect();
+    let lattice_value = |x: i64, y: i64, z: i64| {
+        let hash = mix(seed ^ mix((x as u64).wrapping_mul(0x8CB92BA72F3D8DD7) ^ (y as u64).wrapping_mul(0x9E3779B97F4A7C15) ^ (z as u64).wrapping_mul(0xD6E8FEB86659FD93)));
+        (hash >> 40) as f32 / ((1u64 << 24) - 1) as f32
+    };
+    let lerp = |a: f32, b: f32, t: f32| a + (b - a) * t;
+
+    let (x, y, z) = (cell[0] as i64, cell[1] as i64, cell[2] as i64);
+    let mut face_values = [0.0; 2];
+    for dz in 0..2 {
+        let bottom = lerp(lattice_value(x, y, z + dz), lattice_value(x + 1, y, z + dz), weights[0]);
+        let top = lerp(lattice_value(x, y + 1, z + dz), lattice_value(x + 1, y + 1, z + dz), weights[0]);
+        face_values[dz as usize] = lerp(bottom, top, weights[1]);
+    }
+    lerp(face_values[0], face_values[1], weights[2])
+}
+
+// Sum of value noises, each octave twice as detailed and half as strong as the previous one. In [0, 1].
+pub fn fractal_noise(seed: u64, position: Vect3f, octave_count: u32) -> f32 {
+    let mut result = 0.0;
+    let mut amplitude = 1.0;
+    let mut total_amplitude = 0.0;
+    let mut frequency = 1.0;
+    for octave in 0..octave_count {
+        result += value_noise(seed.wrapping_add(octave as u64), position * frequency) * amplitude;
+        total_amplitude += amplitude;
+        amplitude *= 0.5;
+        frequency *= 2.0;
+    }
+    result / total_amplitude
+}
@@ -1,4 +1,4 @@
-use super::matrix::Mat4f;
+use super::matrix::Mat;
 use super::vector::Vect;
 
 pub fn equals_with_delta(a: f32, b: f32, delta: f32) -> bool {
@@ -14,8 +14,8 @@ pub fn vec_equals_with_delta<const N: usize>(u: Vect<N, f32>, v: Vect<N, f32>, d
     true
 }
 
-pub fn mat_equals_with_delta(u: Mat4f, v: Mat4f, delta: f32) -> bool {
-    for y in 0..4 {
+pub fn mat_equals_with_delta<const N: usize>(u: Mat<N, f32>, v: Mat<N, f32>, delta: f32) -> bool {
+    for y in 0..N {
         if !vec_equals_with_delta(u[y], v[y], delta) {
             return false;
         }
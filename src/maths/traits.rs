@@ -13,6 +13,7 @@ macro_rules! limited_impl {
 }
 
 limited_impl!(f32, f32::MIN, f32::MAX);
+limited_impl!(f64, f64::MIN, f64::MAX);
 limited_impl!(i32, i32::MIN, i32::MAX);
 
 pub trait MathsUsable:
@@ -9,6 +9,8 @@ use super::vector::Vect;
 use super::vector::Vect3i;
 use super::vector::Vect3f;
 use super::intersection;
+use super::random::Random;
+use super::random;
 use super::testing;
 
 #[test]
@@ -167,6 +169,24 @@ fn matrix_inverse() {
     assert!(testing::mat_equals_with_delta(matrix.inverse(), expected, 0.0001));
 }
 
+#[test]
+fn matrix_inverse_and_transpose_3() {
+    let matrix = Mat3f::from_data([
+        1.0, 4.0, -4.2,
+        2.0, -1.0, 2.2,
+        -2.1, 4.3, 10.0,
+    ]);
+    assert!(testing::mat_equals_with_delta(matrix.clone() * matrix.inverse(), Mat3f::identity(), 0.0001));
+    assert!(testing::mat_equals_with_delta(matrix.inverse() * matrix.clone(), Mat3f::identity(), 0.0001));
+
+    let expected = Mat3f::from_data([
+        1.0, 2.0, -2.1,
+        4.0, -1.0, 4.3,
+        -4.2, 2.2, 10.0,
+    ]);
+    assert!(testing::mat_equals_with_delta(matrix.transpose(), expected, 0.0001));
+}
+
 #[test]
 fn matrix_translation() {
     let translation = Vect3f::new([3.0, -3.0, 1.0]);
@@ -215,6 +235,21 @@ fn matrix_rotation() {
     }
 }
 
+#[test]
+fn matrix_rotation_around_axis() {
+    let angle = 30.0_f32.to_radians();
+    let matrix = Mat4f::rotation_around_axis(Vect3f::new([0.0, 0.0, 1.0]), angle);
+    assert!(testing::mat_equals_with_delta(matrix, Mat4f::rotation_around_z(angle), 0.0001));
+    let matrix = Mat4f::rotation_around_axis(Vect3f::new([1.0, 0.0, 0.0]), angle);
+    assert!(testing::mat_equals_with_delta(matrix, Mat4f::rotation_around_x(angle), 0.0001));
+
+    // A third of a turn around the diagonal swaps the axes.
+    let diagonal = Vect3f::new([1.0, 1.0, 1.0]).normalize();
+    let matrix = Mat4f::rotation_around_axis(diagonal, 120.0_f32.to_radians());
+    assert!(testing::vec_equals_with_delta(matrix.clone() * Vect3f::new([1.0, 0.0, 0.0]), Vect3f::new([0.0, 1.0, 0.0]), 0.0001));
+    assert!(testing::vec_equals_with_delta(matrix.clone() * diagonal, diagonal, 0.0001));
+}
+
 #[test]
 fn matrix_op() {
     let mat1 = Mat4f::from_data([
@@ -344,3 +379,48 @@ fn intersection_oob() {
     assert!(!intersection::obb_intersect(box_a.clone(), &repere_a, box_b.clone(), &repere_b));
     assert!(!intersection::obb_intersect(box_b.clone(), &repere_b, box_a.clone(), &repere_a));
 }
+
+#[test]
+fn random_sequence() {
+    let mut a = Random::new(42);
+    let mut b = Random::new(42);
+    let mut c = Random::new(43);
+    let sequence_a: Vec<u64> = (0..100).map(|_| a.next_u64()).collect();
+    let sequence_b: Vec<u64> = (0..100).map(|_| b.next_u64()).collect();
+    let sequence_c: Vec<u64> = (0..100).map(|_| c.next_u64()).collect();
+    assert!(sequence_a == sequence_b);
+    assert!(sequence_a != sequence_c);
+    // SplitMix64 reference values.
+    assert!(Random::new(0).next_u64() == 0xE220A8397B1DCDAF);
+
+    let mut random = Random::new(7);
+    let mut seen_values = HashSet::new();
+    for _ in 0..1000 {
+        let value = random.range_i32(-2, 2);
+        assert!(value >= -2 && value <= 2);
+        seen_values.insert(value);
+        let value = random.range_f32(-1.0, 3.0);
+        assert!(value >= -1.0 && value < 3.0);
+        assert!(random.index(5) < 5);
+    }
+    assert!(seen_values.len() == 5);
+    let heads = (0..1000).filter(|_| random.chance(0.25)).count();
+    assert!(heads > 200 && heads < 300);
+}
+
+#[test]
+fn noise() {
+    for i in 0..200 {
+        let position = Vect3f::new([i as f32 * 0.37 - 20.0, i as f32 * 0.11, -(i as f32) * 0.23]);
+        let value = random::value_noise(5, position);
+        assert!(value >= 0.0 && value <= 1.0);
+        assert!(value == random::value_noise(5, position));
+        let value = random::fractal_noise(5, position, 4);
+        assert!(value >= 0.0 && value <= 1.0);
+    }
+
+    // Continuous, and random values on integer coordinates.
+    let position = Vect3f::new([3.0, -2.0, 7.0]);
+    assert!(testing::equals_with_delta(random::value_noise(5, position), random::value_noise(5, position + Vect3f::all(0.001)), 0.01));
+    assert!(random::value_noise(5, position) != random::value_noise(6, position));
+}
@@ -213,4 +213,5 @@ impl<const N: usize, T: MathsUsable> std::fmt::Display for Vect<N, T> {
 pub type Vect2f = Vect<2, f32>;
 pub type Vect3i = Vect<3, i32>;
 pub type Vect3f = Vect<3, f32>;
+pub type Vect3d = Vect<3, f64>;
 pub type Vect4f = Vect<4, f32>;
@@ -1,30 +1,34 @@
-use std::collections::HashSet;
-use std::collections::VecDeque;
 
 use crate::maths::boxes::Box3f;
 use crate::maths::matrix::Mat4f;
 use crate::maths::segment::Segm3f;
 use crate::maths::vector::Vect3f;
 use crate::maths::vector::Vect3i;
+use crate::voxels::catalog::VoxelCatalog;
+use crate::voxels::connectivity;
+use crate::voxels::connectivity::MainComponentRule;
+use crate::voxels::connectivity::Neighbourhood;
+use crate::voxels::damage::AreaDamage;
+use crate::voxels::structure::RayHit;
 use crate::voxels::structure::Structure;
-use crate::voxels::voxel::Voxel;
 
 pub struct Body {
     repere: Mat4f,
     structure: Structure,
     velocity: Vect3f,
-    rotation: Vect3f,
+    angular_momentum: Vect3f, // In world axes, around the center of mass.
 }
 
-fn close_coords(coords: Vect3i) -> [Vect3i; 6] {
-    [
-        coords + Vect3i::new([ 1, 0, 0]),
-        coords + Vect3i::new([-1, 0, 0]),
-        coords + Vect3i::new([0,  1, 0]),
-        coords + Vect3i::new([0, -1, 0]),
-        coords + Vect3i::new([0, 0,  1]),
-        coords + Vect3i::new([0, 0, -1]),
-    ]
+struct RigidMotion {
+    center_of_mass: Vect3f,
+    velocity: Vect3f,
+    angular_velocity: Vect3f,
+}
+
+impl RigidMotion {
+    fn velocity_at(&self, point: Vect3f) -> Vect3f {
+        self.velocity + Vect3f::cross(self.angular_velocity, point - self.center_of_mass)
+    }
 }
 
 impl Body {
@@ -33,42 +37,20 @@ impl Body {
             repere: repere,
             structure: structure,
             velocity: Vect3f::zero(),
-            rotation: Vect3f::zero(),
+            angular_momentum: Vect3f::zero(),
         }
     }
 
-    pub fn new_from_other(structure: Structure, distance: Vect3f, other: &Body) -> Self {
-        if distance == Vect3f::zero() {
-            return Self {
-                repere: other.repere.clone(),
-                structure: structure,
-                velocity: other.velocity,
-                rotation: other.rotation,
-            };
-        }
-
-        let new_repere = other.repere().clone() * Mat4f::translation(distance);
-        let radius_sq = distance.length_sq();
-        let local_distance = other.repere().without_translation() * distance.normalize();
-        let angular_speed_x_sq = other.roll() * other.roll();
-        let angular_speed_y_sq = other.pitch() * other.pitch();
-        let angular_speed_z_sq = other.yaw() * other.yaw();
-        let velocity_from_roll_centrifugal = Vect3f::cross(local_distance, Vect3f::new([-1.0, 0.0, 0.0])) * f32::sqrt(radius_sq * angular_speed_x_sq);
-        let velocity_from_yaw_centrifugal = Vect3f::cross(local_distance, Vect3f::new([0.0, -1.0, 0.0])) * f32::sqrt(radius_sq * angular_speed_y_sq);
-        let velocity_from_pitch_centrifugal = Vect3f::cross(local_distance, Vect3f::new([0.0, 0.0, -1.0])) * f32::sqrt(radius_sq * angular_speed_z_sq);
-        let velocity = other.velocity + velocity_from_roll_centrifugal + velocity_from_yaw_centrifugal + velocity_from_pitch_centrifugal;
-
-        let distance_normalized = distance.normalize();
-        let roll = other.roll() * Vect3f::dot(distance_normalized, Vect3f::new([1.0, 0.0, 0.0])).abs();
-        let pitch = other.pitch() * Vect3f::dot(distance_normalized, Vect3f::new([0.0, 1.0, 0.0])).abs();
-        let yaw = other.yaw() * Vect3f::dot(distance_normalized, Vect3f::new([0.0, 0.0, 1.0])).abs();
-
-        Self {
-            repere: new_repere,
+    // A fragment moves as it did when it was part of the other body, so that splitting conserves momentum.
+    fn new_fragment(structure: Structure, distance: Vect3f, other_repere: &Mat4f, motion: &RigidMotion) -> Self {
+        let mut result = Self {
+            repere: other_repere.clone() * Mat4f::translation(distance),
             structure: structure,
-            velocity,
-            rotation: Vect3f::new([roll, pitch, yaw]),
-        }
+            velocity: Vect3f::zero(),
+            angular_momentum: Vect3f::zero(),
+        };
+        result.set_rigid_motion(motion);
+        result
     }
 
     pub fn repere(&self) -> &Mat4f {
@@ -79,19 +61,11 @@ impl Body {
         &self.structure
     }
 
+    #[allow(dead_code)]
     pub fn velocity(&self) -> Vect3f {
         self.velocity
     }
 
-    pub fn momentum(&self, coords: Vect3i) -> Vect3f {
-        let rotation = self.repere.without_translation();
-        let coords_f = rotation.clone() * Vect3f::new([coords[0] as f32, coords[1] as f32, coords[2] as f32]);
-        let velocity_from_yaw = Vect3f::cross(rotation.clone() * Vect3f::new([0.0, 0.0, 1.0]), coords_f) * self.yaw();
-        let velocity_from_pitch = Vect3f::cross(rotation.clone() * Vect3f::new([0.0, 1.0, 0.0]), coords_f) * self.pitch();
-        let velocity_from_roll = Vect3f::cross(rotation.clone() * Vect3f::new([1.0, 0.0, 0.0]), coords_f) * self.roll();
-        (self.velocity + velocity_from_yaw + velocity_from_pitch + velocity_from_roll) * self.structure.mass()
-    }
-
     pub fn get_box(&self) -> Box3f {
         let voxel_box = self.structure.get_box();
         let mut result = Box3f::new();
@@ -101,118 +75,183 @@ impl Body {
         result
     }
 
+    #[cfg(test)]
     pub fn yaw(&self) -> f32 {
-        self.rotation[2]
+        self.local_angular_velocity()[2]
     }
 
+    #[cfg(test)]
     pub fn pitch(&self) -> f32 {
-        self.rotation[1]
+        self.local_angular_velocity()[1]
     }
 
+    #[cfg(test)]
     pub fn roll(&self) -> f32 {
-        self.rotation[0]
+        self.local_angular_velocity()[0]
+    }
+
+    #[allow(dead_code)]
+    pub fn angular_momentum(&self) -> Vect3f {
+        self.angular_momentum
+    }
+
+    // In world axes.
+    pub fn angular_velocity(&self) -> Vect3f {
+        self.angular_velocity_from_momentum(self.angular_momentum)
+    }
+
+    // Angular velocity this body would get from the given angular momentum, both in world axes.
+    pub fn angular_velocity_from_momentum(&self, angular_momentum: Vect3f) -> Vect3f {
+        if self.structure.mass() <= 0.0 {
+            return Vect3f::zero();
+        }
+        let rotation = self.repere.without_translation();
+        let local_angular_momentum = rotation.transpose() * angular_momentum;
+        let local_angular_velocity: Vect3f = self.structure.inertia_tensor().inverse() * local_angular_momentum;
+        rotation * local_angular_velocity
+    }
+
+    // Velocity of a world point moving along with the body.
+    pub fn velocity_at(&self, point: Vect3f) -> Vect3f {
+        self.rigid_motion().velocity_at(point)
     }
 
-    pub fn for_first_voxel_in_segment<F: FnMut(&mut Voxel, &Vect3i)>(&mut self, segment: Segm3f, f: F) -> bool {
+    // Angular velocity in structure axes, as [roll, pitch, yaw].
+    fn local_angular_velocity(&self) -> Vect3f {
+        self.repere.without_translation().transpose() * self.angular_velocity()
+    }
+
+    fn rigid_motion(&self) -> RigidMotion {
+        RigidMotion {
+            center_of_mass: self.world_center_of_mass(),
+            velocity: self.velocity,
+            angular_velocity: self.angular_velocity(),
+        }
+    }
+
+    // Takes the velocity and angular velocity this body has if rigidly attached to the given motion.
+    fn set_rigid_motion(&mut self, motion: &RigidMotion) {
+        if self.structure.is_empty() {
+            return;
+        }
+        self.velocity = motion.velocity_at(self.world_center_of_mass());
+        let local_angular_velocity = self.repere.without_translation().transpose() * motion.angular_velocity;
+        self.angular_momentum = self.local_to_world_angular_momentum(local_angular_velocity);
+    }
+
+    fn local_to_world_angular_momentum(&self, local_angular_velocity: Vect3f) -> Vect3f {
+        let local_angular_momentum: Vect3f = self.structure.inertia_tensor() * local_angular_velocity;
+        self.repere.without_translation() * local_angular_momentum
+    }
+
+    pub fn world_center_of_mass(&self) -> Vect3f {
+        self.repere.clone() * self.structure.center_of_mass()
+    }
+
+    // Same as Structure::ray_cast, but with a segment and a hit point in world coordinates.
+    #[allow(dead_code)]
+    pub fn ray_cast(&self, segment: Segm3f) -> Option<RayHit> {
         let segment_in_repere = segment.transform(&self.repere.inverse());
-        self.structure.for_first_voxel_in_segment(segment_in_repere, f)
+        self.structure.ray_cast(segment_in_repere).map(|hit| RayHit {
+            point: self.repere.clone() * hit.point,
+            ..hit
+        })
     }
 
-    pub fn set_velocity(&mut self, velocity: Vect3f) {
-        self.velocity = velocity;
+    // Same as Structure::ray_cast_all, in world coordinates.
+    pub fn ray_cast_all(&self, segment: Segm3f) -> Vec<RayHit> {
+        let segment_in_repere = segment.transform(&self.repere.inverse());
+        self.structure.ray_cast_all(segment_in_repere).into_iter().map(|hit| RayHit {
+            point: self.repere.clone() * hit.point,
+            ..hit
+        }).collect()
     }
 
-    pub fn add_to_velocity(&mut self, velocity: Vect3f) {
-        self.velocity += velocity;
+    pub fn damage_voxel(&mut self, coords: Vect3i, damage: f32) {
+        self.structure.damage_voxel(coords, damage);
     }
 
-    pub fn add_yaw_rotation(&mut self, yaw: f32) {
-        self.rotation[2] += yaw;
+    // Same as Structure::apply_area_damage, with the center in world coordinates.
+    pub fn apply_area_damage(&mut self, catalog: &VoxelCatalog, area_damage: &AreaDamage) -> Vec<Vect3i> {
+        let area_damage_in_repere = AreaDamage {
+            center: self.repere.inverse() * area_damage.center,
+            ..*area_damage
+        };
+        self.structure.apply_area_damage(catalog, &area_damage_in_repere)
     }
 
-    pub fn add_pitch_rotation(&mut self, pitch: f32) {
-        self.rotation[1] += pitch;
+    pub fn set_velocity(&mut self, velocity: Vect3f) {
+        self.velocity = velocity;
     }
 
-    pub fn add_roll_rotation(&mut self, roll: f32) {
-        self.rotation[0] += roll;
+    pub fn translate(&mut self, translation: Vect3f) {
+        self.repere = Mat4f::translation(translation) * self.repere.clone();
     }
 
-    pub fn scale_velocity(&mut self, scale: f32) {
-        self.velocity *= scale;
+    // Torque in world axes, around the center of mass.
+    pub fn apply_torque(&mut self, torque: Vect3f, elapsed_time: f32) {
+        self.angular_momentum += torque * elapsed_time;
     }
 
-    pub fn scale_rotation(&mut self, scale: f32) {
-        self.rotation *= scale;
+    // Impulse and point in world coordinates. An impulse away from the center of mass also makes the body spin.
+    pub fn apply_impulse(&mut self, impulse: Vect3f, point: Vect3f) {
+        if self.structure.mass() <= 0.0 {
+            return;
+        }
+        self.velocity += impulse / self.structure.mass();
+        self.angular_momentum += Vect3f::cross(point - self.world_center_of_mass(), impulse);
     }
 
+    // Angular momentum is kept, so angular velocity changes as the inertia tensor turns with the body.
     pub fn apply_velocity_and_rotation(&mut self, elapsed_time: f32) {
+        let center_of_mass = self.structure.center_of_mass();
+        let angular_velocity = self.local_angular_velocity();
+        let rotation = if angular_velocity == Vect3f::zero() {
+            Mat4f::identity()
+        } else {
+            Mat4f::rotation_around_axis(angular_velocity.normalize(), angular_velocity.length() * elapsed_time)
+        };
         self.repere = Mat4f::translation(self.velocity * elapsed_time)
         * self.repere.clone()
-        * Mat4f::rotation_around_z(self.rotation[2] * elapsed_time)
-        * Mat4f::rotation_around_y(self.rotation[1] * elapsed_time)
-        * Mat4f::rotation_around_x(self.rotation[0] * elapsed_time)
+        * Mat4f::translation(center_of_mass)
+        * rotation
+        * Mat4f::translation(-center_of_mass)
     }
 
+    // Destroyed voxels take their share of momentum with them, the rest of the body and its fragments keep moving together.
     pub fn update_dead_voxels(&mut self) -> Vec<Body> {
         let mut new_bodies: Vec<Body> = vec![];
+        let motion = self.rigid_motion();
         let destroyed_coords = self.structure.erase_dead_voxels();
+        if destroyed_coords.is_empty() {
+            return new_bodies;
+        }
 
-        let coords_to_check = {
-            let mut result = vec![];
-            for coord in destroyed_coords {
-                for coord_to_check in close_coords(coord) {
-                    if self.structure.has_voxel_on_coords(coord_to_check) {
-                        result.push(coord_to_check);
-                    }
-                }
-            }
-            result
-        };
+        let coords_to_check: Vec<Vect3i> = destroyed_coords.iter()
+            .flat_map(|coords| Neighbourhood::Faces.offsets().into_iter().map(move |offset| *coords + offset))
+            .collect();
 
         // Cut in separate bodies disjoincted structures.
-        let mut coords_to_reach: HashSet<Vect3i> = coords_to_check.clone().into_iter().collect();
-        let mut jointed_coords: Vec<HashSet<Vect3i>> = vec![];
-        while !coords_to_reach.is_empty() {
-            let first_coords = coords_to_reach.iter().next().unwrap().clone();
-            let mut coords_to_explore: VecDeque<Vect3i> = Default::default();
-            let mut explored_coords: HashSet<Vect3i> = Default::default();
-            coords_to_explore.push_back(first_coords);
-            coords_to_reach.remove(&first_coords);
-            explored_coords.insert(first_coords);
-            while !coords_to_explore.is_empty() && (!coords_to_reach.is_empty() || !jointed_coords.is_empty())  {
-                let coords = coords_to_explore.pop_front().unwrap();
-                for close_coords in close_coords(coords) {
-                    if self.structure.has_voxel_on_coords(close_coords) && !explored_coords.contains(&close_coords) {
-                        coords_to_reach.remove(&close_coords);
-                        coords_to_explore.push_back(close_coords);
-                        explored_coords.insert(close_coords);
-                    }
-                }
-            }
-            jointed_coords.push(explored_coords);
-        }
-        if jointed_coords.len() > 1 {
-            for join in jointed_coords {
-                if !join.contains(&Vect3i::zero()) {
-                    let mut new_structure = Structure::new_empty();
-                    for coords in join {
+        if let Some(components) = self.structure.split_components(&coords_to_check, Neighbourhood::Faces) {
+            let main_index = connectivity::main_component(&components, MainComponentRule::Origin);
+            for (index, component) in components.into_iter().enumerate() {
+                if Some(index) != main_index {
+                    let mut new_structure = Structure::new_empty_like(&self.structure);
+                    for coords in component.coords {
                         let voxel = self.structure.remove_voxel(coords);
                         new_structure.add_voxel(coords, voxel);
                     }
                     let new_center = new_structure.recenter();
                     let translation = Vect3f::new([new_center[0] as f32, new_center[1] as f32, new_center[2] as f32]);
-                    let mut new_body = Body::new_from_other(new_structure, translation, self);
-
-                    // Debug to see result
-                    new_body.add_to_velocity(if translation == Vect3f::zero() { Vect3f::new([0.0, 0.0, 1.0]) } else { translation.normalize() });
-                    new_bodies.push(new_body);
+                    new_bodies.push(Body::new_fragment(new_structure, translation, &self.repere, &motion));
                 }
             }
         }
         if !new_bodies.is_empty() {
             self.structure.recalculate_box();
         }
+        // Center of mass and inertia moved with the lost voxels.
+        self.set_rigid_motion(&motion);
         new_bodies
     }
 }
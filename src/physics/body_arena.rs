@@ -0,0 +1,121 @@
+// Bodies are referred by handles rather than indices or references, so they can be followed across ticks
+// while other bodies are split, created or destroyed. A handle to a removed body never matches a new one.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct BodyHandle {
+    index: u32,
+    generation: u32,
+}
+
+struct Slot<T> {
+    generation: u32,
+    value: Option<T>,
+    // Body this one was cut from, kept with the slot so it goes away along with the body.
+    parent: Option<BodyHandle>,
+}
+
+pub struct BodyArena<T> {
+    slots: Vec<Slot<T>>,
+    free_indices: Vec<u32>,
+}
+
+impl<T> BodyArena<T> {
+    pub fn new() -> Self {
+        Self {
+            slots: vec![],
+            free_indices: vec![],
+        }
+    }
+
+    pub fn insert(&mut self, value: T) -> BodyHandle {
+        match self.free_indices.pop() {
+            Some(index) => {
+                let slot = &mut self.slots[index as usize];
+                slot.value = Some(value);
+                slot.parent = None;
+                BodyHandle { index, generation: slot.generation }
+            },
+            None => {
+                self.slots.push(Slot { generation: 0, value: Some(value), parent: None });
+                BodyHandle { index: self.slots.len() as u32 - 1, generation: 0 }
+            },
+        }
+    }
+
+    // Inserts a fragment cut from the parent body.
+    pub fn insert_split(&mut self, parent: BodyHandle, value: T) -> BodyHandle {
+        let child = self.insert(value);
+        self.slots[child.index as usize].parent = Some(parent);
+        child
+    }
+
+    pub fn remove(&mut self, handle: BodyHandle) -> Option<T> {
+        if !self.contains(handle) {
+            return None;
+        }
+        let slot = &mut self.slots[handle.index as usize];
+        slot.generation += 1;
+        slot.parent = None;
+        self.free_indices.push(handle.index);
+        slot.value.take()
+    }
+
+    pub fn contains(&self, handle: BodyHandle) -> bool {
+        self.get(handle).is_some()
+    }
+
+    pub fn get(&self, handle: BodyHandle) -> Option<&T> {
+        match self.slots.get(handle.index as usize) {
+            Some(slot) if slot.generation == handle.generation => slot.value.as_ref(),
+            _ => None,
+        }
+    }
+
+    pub fn get_mut(&mut self, handle: BodyHandle) -> Option<&mut T> {
+        match self.slots.get_mut(handle.index as usize) {
+            Some(slot) if slot.generation == handle.generation => slot.value.as_mut(),
+            _ => None,
+        }
+    }
+
+    #[cfg(test)]
+    pub fn handles(&self) -> Vec<BodyHandle> {
+        self.iter().map(|(handle, _)| handle).collect()
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = (BodyHandle, &T)> {
+        self.slots.iter().enumerate().filter_map(|(index, slot)| {
+            slot.value.as_ref().map(|value| (BodyHandle { index: index as u32, generation: slot.generation }, value))
+        })
+    }
+
+    pub fn iter_mut(&mut self) -> impl Iterator<Item = (BodyHandle, &mut T)> {
+        self.slots.iter_mut().enumerate().filter_map(|(index, slot)| {
+            let generation = slot.generation;
+            slot.value.as_mut().map(|value| (BodyHandle { index: index as u32, generation }, value))
+        })
+    }
+
+    pub fn retain<F: FnMut(&T) -> bool>(&mut self, mut f: F) {
+        let handles_to_remove: Vec<BodyHandle> = self.iter().filter(|(_, value)| !f(value)).map(|(handle, _)| handle).collect();
+        for handle in handles_to_remove {
+            self.remove(handle);
+        }
+    }
+
+    #[cfg(test)]
+    pub fn parent(&self, handle: BodyHandle) -> Option<BodyHandle> {
+        match self.slots.get(handle.index as usize) {
+            Some(slot) if slot.generation == handle.generation => slot.parent,
+            _ => None,
+        }
+    }
+
+    // The children are the fragments still alive, their parent may already be destroyed.
+    #[cfg(test)]
+    pub fn children(&self, handle: BodyHandle) -> Vec<BodyHandle> {
+        self.slots.iter().enumerate()
+            .filter(|(_, slot)| slot.value.is_some() && slot.parent == Some(handle))
+            .map(|(index, slot)| BodyHandle { index: index as u32, generation: slot.generation })
+            .collect()
+    }
+}
@@ -0,0 +1,23 @@
+use crate::maths::boxes::Box3f;
+
+// Sweep and prune: boxes are sorted along x, and a box is only tested against the boxes whose x range is still open.
+// Returned pairs are indices into the given boxes, lowest index first, for boxes intersecting on all axes.
+pub fn candidate_pairs(boxes: &[Box3f]) -> Vec<(usize, usize)> {
+    let mut sorted_indices: Vec<usize> = (0..boxes.len()).filter(|index| !boxes[*index].is_empty()).collect();
+    sorted_indices.sort_by(|a, b| boxes[*a].min()[0].total_cmp(&boxes[*b].min()[0]));
+
+    let mut result = vec![];
+    let mut active_indices: Vec<usize> = vec![];
+    for index in sorted_indices {
+        let current_box = &boxes[index];
+        active_indices.retain(|active_index| boxes[*active_index].max()[0] >= current_box.min()[0]);
+        for active_index in &active_indices {
+            if boxes[*active_index].intersects(current_box) {
+                result.push((index.min(*active_index), index.max(*active_index)));
+            }
+        }
+        active_indices.push(index);
+    }
+    result.sort();
+    result
+}
@@ -7,6 +7,7 @@ use crate::maths::vector::Vect3f;
 use crate::maths::vector::Vect3i;
 use crate::maths::intersection;
 use crate::maths::intersection::OBBAxis;
+use crate::voxels::mesh::FACE_NORMALS;
 
 fn taking_box(body: &Body) -> Box3f {
     let mut result = Box3f::from_min_max(Vect3f::all(-1.0), Vect3f::all(1.0));
@@ -76,23 +77,112 @@ pub fn intersection_high_precision(body_a: &Body, body_b: &Body) -> Vec<(Vect3i,
     voxels_intersection(body_a, englobing_box_a, body_b, englobing_box_b, &axis)
 }
 
+fn world_coords(body: &Body, coords: Vect3i) -> Vect3f {
+    body.repere().clone() * Vect3f::new([coords[0] as f32, coords[1] as f32, coords[2] as f32])
+}
+
+// Sum of the normals of the voxel faces that are not covered by another voxel, in world axes.
+fn surface_normal(body: &Body, coords: Vect3i) -> Vect3f {
+    let mut result = Vect3f::zero();
+    for face_normal in FACE_NORMALS {
+        let face_normal = Vect3i::new(face_normal);
+        if !body.structure().has_voxel_on_coords(coords + face_normal) {
+            result += Vect3f::new([face_normal[0] as f32, face_normal[1] as f32, face_normal[2] as f32]);
+        }
+    }
+    body.repere().without_translation() * result
+}
+
+// Contact point and normal, from body A towards body B, in world coordinates.
+pub fn contact_from_intersections(body_a: &Body, body_b: &Body, intersections: &[(Vect3i, Vect3i)]) -> Option<(Vect3f, Vect3f)> {
+    if intersections.is_empty() {
+        return None;
+    }
+    let mut contact_point = Vect3f::zero();
+    let mut normal = Vect3f::zero();
+    for (coords_a, coords_b) in intersections {
+        contact_point += (world_coords(body_a, *coords_a) + world_coords(body_b, *coords_b)) / 2.0;
+        normal += surface_normal(body_a, *coords_a) - surface_normal(body_b, *coords_b);
+    }
+    contact_point = contact_point / intersections.len() as f32;
+
+    // Surfaces can cancel out when bodies are deeply interlocked, centers of mass are the only hint left.
+    if normal == Vect3f::zero() {
+        normal = body_b.world_center_of_mass() - body_a.world_center_of_mass();
+    }
+    if normal == Vect3f::zero() {
+        return None;
+    }
+    Some((contact_point, normal.normalize()))
+}
+
+// Half size of a voxel of the body once projected on a world axis.
+fn voxel_half_extent(body: &Body, axis: Vect3f) -> f32 {
+    let rotation = body.repere().without_translation();
+    let mut result = 0.0;
+    // Opposite normals follow each other, one of each pair is enough.
+    for face_normal in FACE_NORMALS.iter().step_by(2) {
+        let body_axis = rotation.clone() * Vect3f::new([face_normal[0] as f32, face_normal[1] as f32, face_normal[2] as f32]);
+        result += Vect3f::dot(body_axis, axis).abs() * 0.5;
+    }
+    result
+}
+
+// Distance to move body B along the normal, relative to body A, so that intersecting voxels do not overlap anymore.
+pub fn penetration_depth(body_a: &Body, body_b: &Body, intersections: &[(Vect3i, Vect3i)], normal: Vect3f) -> f32 {
+    let half_extent_a = voxel_half_extent(body_a, normal);
+    let half_extent_b = voxel_half_extent(body_b, normal);
+    let mut max_a = f32::MIN;
+    let mut min_b = f32::MAX;
+    for (coords_a, coords_b) in intersections {
+        max_a = max_a.max(Vect3f::dot(world_coords(body_a, *coords_a), normal) + half_extent_a);
+        min_b = min_b.min(Vect3f::dot(world_coords(body_b, *coords_b), normal) - half_extent_b);
+    }
+    (max_a - min_b).max(0.0)
+}
+
+// Moves bodies apart along the normal, the lightest one moving the most.
+pub fn separate_bodies(body_a: &mut Body, body_b: &mut Body, normal: Vect3f, depth: f32) {
+    const SEPARATION_MARGIN: f32 = 0.01;
+    let mass_a = body_a.structure().mass();
+    let mass_b = body_b.structure().mass();
+    if depth <= 0.0 || mass_a + mass_b <= 0.0 {
+        return;
+    }
+    let distance = depth + SEPARATION_MARGIN;
+    body_a.translate(normal * (-distance * mass_b / (mass_a + mass_b)));
+    body_b.translate(normal * (distance * mass_a / (mass_a + mass_b)));
+}
+
 pub fn apply_collision_if_any(body_a: &mut Body, body_b: &mut Body, restitution: f32) {
     let intersections = intersection_low_precision(body_a, body_b);
-    if !intersections.is_empty() {
-        let (intersections_a, intersections_b): (Vec<Vect3i>, Vec<Vect3i>) = intersections.into_iter().unzip();
-        let average_intersection_a = intersections_a.iter().sum::<Vect3i>() / intersections_a.len() as i32;
-        let average_intersection_b = intersections_b.iter().sum::<Vect3i>() / intersections_b.len() as i32;
-        let momentum_a = body_a.momentum(average_intersection_a);
-        let momentum_b = body_b.momentum(average_intersection_b);
-        let mass_a = body_a.structure().mass();
-        let mass_b = body_b.structure().mass();
-        let center_velocity = (momentum_a + momentum_b) / (mass_a + mass_b);
-        let new_velocity_a = center_velocity * (1.0 + restitution) - body_a.velocity() * restitution;
-        let new_velocity_b = center_velocity * (1.0 + restitution) - body_b.velocity() * restitution;
-
-        body_a.set_velocity(new_velocity_a);
-        body_b.set_velocity(new_velocity_b);
+    let (contact_point, normal) = match contact_from_intersections(body_a, body_b, &intersections) {
+        Some(contact) => contact,
+        None => return,
+    };
+    let depth = penetration_depth(body_a, body_b, &intersections, normal);
+    apply_contact_impulse(body_a, body_b, contact_point, normal, restitution);
+    separate_bodies(body_a, body_b, normal, depth);
+}
+
+fn apply_contact_impulse(body_a: &mut Body, body_b: &mut Body, contact_point: Vect3f, normal: Vect3f, restitution: f32) {
+
+    let relative_velocity = body_b.velocity_at(contact_point) - body_a.velocity_at(contact_point);
+    let normal_velocity = Vect3f::dot(relative_velocity, normal);
+    if normal_velocity >= 0.0 {
+        // Already moving apart.
+        return;
     }
+
+    let lever_arm_a = contact_point - body_a.world_center_of_mass();
+    let lever_arm_b = contact_point - body_b.world_center_of_mass();
+    let angular_factor_a = Vect3f::cross(body_a.angular_velocity_from_momentum(Vect3f::cross(lever_arm_a, normal)), lever_arm_a);
+    let angular_factor_b = Vect3f::cross(body_b.angular_velocity_from_momentum(Vect3f::cross(lever_arm_b, normal)), lever_arm_b);
+    let inverse_mass_sum = 1.0 / body_a.structure().mass() + 1.0 / body_b.structure().mass() + Vect3f::dot(angular_factor_a + angular_factor_b, normal);
+    let impulse = normal * (-(1.0 + restitution) * normal_velocity / inverse_mass_sum);
+
+    body_a.apply_impulse(-impulse, contact_point);
+    body_b.apply_impulse(impulse, contact_point);
 }
 
 fn compare_coords(a: Vect3i, b: Vect3i) -> Ordering {
@@ -1,4 +1,6 @@
 pub mod body;
+pub mod body_arena;
+pub mod broadphase;
 pub mod collision;
 
 #[cfg(test)]
@@ -1,10 +1,16 @@
 use std::f32::consts::PI;
 
 use super::body::Body;
+use super::body_arena::BodyArena;
+use super::broadphase;
 use super::collision;
+use crate::maths::boxes::Box3f;
+use crate::maths::matrix::Mat3f;
 use crate::maths::matrix::Mat4f;
+use crate::maths::segment::Segm3f;
 use crate::maths::vector::Vect3f;
 use crate::maths::vector::Vect3i;
+use crate::voxels::catalog::VoxelCatalog;
 use crate::voxels::structure::Structure;
 use crate::voxels::voxel::Voxel;
 use crate::voxels::voxel::VoxelID;
@@ -12,19 +18,32 @@ use crate::maths::testing;
 
 const TEST_VOXEL: Voxel = Voxel{
     life: 2.0,
-    id: VoxelID::LightHull,
+    id: VoxelID::LIGHT_HULL,
+};
+
+const TEST_VOXEL_HEAVY: Voxel = Voxel{
+    life: 2.0,
+    id: VoxelID::HEAVY_HULL,
 };
 
 const TEST_DEAD_VOXEL: Voxel = Voxel{
     life: 0.0,
-    id: VoxelID::ShipCore,
+    id: VoxelID::SHIP_CORE,
 };
 
 
+// Angular velocity in structure axes, given as a torque over one second.
+fn add_local_rotation(body: &mut Body, roll: f32, pitch: f32, yaw: f32) {
+    let local_angular_momentum: Vect3f = body.structure().inertia_tensor() * Vect3f::new([roll, pitch, yaw]);
+    let angular_momentum = body.repere().without_translation() * local_angular_momentum;
+    body.apply_torque(angular_momentum, 1.0);
+}
+
 #[test]
 fn body_cut_in_half() {
+    let catalog = VoxelCatalog::create();
     let mut structure = {
-        let mut structure = Structure::new(-2, 2, 0, 0, 0, 0, TEST_VOXEL);
+        let mut structure = Structure::new(&catalog, -2, 2, 0, 0, 0, 0, TEST_VOXEL);
         structure.add_voxel(Vect3i::new([2, 1, 0]), TEST_VOXEL);
         structure.add_voxel(Vect3i::new([2, 1, 1]), TEST_VOXEL);
         structure.add_voxel(Vect3i::new([2, 0, 1]), TEST_VOXEL);
@@ -50,9 +69,9 @@ fn body_cut_in_half() {
         structure.recalculate_box();
         structure
     };
-    let expected_new_structure = Structure::new(0, 0, -1, 1, -1, 1, TEST_VOXEL);
+    let expected_new_structure = Structure::new(&catalog, 0, 0, -1, 1, -1, 1, TEST_VOXEL);
 
-    structure.set_voxel(1, 0, 0, Some(Voxel{life: 0.0, id: VoxelID::LightHull}));
+    structure.set_voxel(1, 0, 0, Some(Voxel{life: 0.0, id: VoxelID::LIGHT_HULL}));
     let mut body = Body::new(structure, Mat4f::identity());
     let new_bodies = body.update_dead_voxels();
 
@@ -63,7 +82,8 @@ fn body_cut_in_half() {
 
 #[test]
 fn body_cut_in_half_with_same_center() {
-    let mut structure = Structure::new(-2, 2, -2, 2, 0, 0, TEST_VOXEL);
+    let catalog = VoxelCatalog::create();
+    let mut structure = Structure::new(&catalog, -2, 2, -2, 2, 0, 0, TEST_VOXEL);
     structure.set_voxel(-1, -1, 0, Some(TEST_DEAD_VOXEL));
     structure.set_voxel(-1, 0, 0, Some(TEST_DEAD_VOXEL));
     structure.set_voxel(-1, 1, 0, Some(TEST_DEAD_VOXEL));
@@ -90,21 +110,22 @@ fn body_cut_in_half_with_same_center() {
     let mut body = Body::new(structure, Mat4f::identity());
     let new_bodies = body.update_dead_voxels();
 
-    assert!(body.structure().clone() == Structure::new(0, 0, 0, 0, 0, 0, TEST_VOXEL));
+    assert!(body.structure().clone() == Structure::new(&catalog, 0, 0, 0, 0, 0, 0, TEST_VOXEL));
     assert!(new_bodies.len() == 1);
     assert!(new_bodies[0].structure().clone() == expected_new_structure);
 }
 
 #[test]
 fn body_cut_in_half_with_father_body_becoming_empty() {
-    let mut structure = Structure::new(-1, 1, 0, 0, 0, 0, TEST_VOXEL);
+    let catalog = VoxelCatalog::create();
+    let mut structure = Structure::new(&catalog, -1, 1, 0, 0, 0, 0, TEST_VOXEL);
     structure.set_voxel(0, 0, 0, Some(TEST_DEAD_VOXEL));
 
     let mut body = Body::new(structure, Mat4f::identity());
     let new_bodies = body.update_dead_voxels();
 
     let expected_structure = {
-        let mut structure = Structure::new(0, 0, 0, 0, 0, 0, TEST_VOXEL);
+        let mut structure = Structure::new(&catalog, 0, 0, 0, 0, 0, 0, TEST_VOXEL);
         structure.set_voxel(0, 0, 0, None);
         structure
     };
@@ -114,68 +135,118 @@ fn body_cut_in_half_with_father_body_becoming_empty() {
 }
 
 #[test]
-fn body_momentum_identity() {
-    let structure_square = Structure::new(-1, 1, -1, 1, -1, 1, TEST_VOXEL);
-
-    let mut body = Body::new(structure_square.clone(), Mat4f::identity());
-    body.add_yaw_rotation(PI / 2.0);
-    let momentum = body.momentum(Vect3i::new([1, 1, 1])) / body.structure().mass();
-    assert!(testing::vec_equals_with_delta(momentum, Vect3f::new([-PI / 2.0, PI / 2.0, 0.0]), 0.0001));
-
-    let mut body = Body::new(structure_square.clone(), Mat4f::identity());
-    body.add_pitch_rotation(PI / 2.0);
-    let momentum = body.momentum(Vect3i::new([1, 1, 1])) / body.structure().mass();
-    assert!(testing::vec_equals_with_delta(momentum, Vect3f::new([PI / 2.0, 0.0, -PI / 2.0]), 0.0001));
-
-    let mut body = Body::new(structure_square.clone(), Mat4f::identity());
-    body.add_roll_rotation(PI / 2.0);
-    let momentum = body.momentum(Vect3i::new([1, 1, 1])) / body.structure().mass();
-    assert!(testing::vec_equals_with_delta(momentum, Vect3f::new([0.0, -PI / 2.0, PI / 2.0]), 0.0001));
+fn body_rotation_around_center_of_mass() {
+    let catalog = VoxelCatalog::create();
+    let structure = Structure::new(&catalog, 1, 3, 0, 0, 0, 0, TEST_VOXEL);
+    let mut body = Body::new(structure, Mat4f::identity());
+    add_local_rotation(&mut body, 0.0, PI / 3.0, PI / 2.0);
+    body.apply_velocity_and_rotation(1.0);
+    let center_of_mass = body.repere().clone() * body.structure().center_of_mass();
+    assert!(testing::vec_equals_with_delta(center_of_mass, Vect3f::new([2.0, 0.0, 0.0]), 0.0001));
+}
+
+#[test]
+fn structure_inertia_tensor() {
+    let catalog = VoxelCatalog::create();
+    // A single voxel is a cube of side 1: m / 6 on each axis.
+    let structure = Structure::new(&catalog, 4, 4, -2, -2, 1, 1, TEST_VOXEL);
+    let voxel_mass = structure.voxel_mass(&TEST_VOXEL);
+    let expected = Mat3f::from_data([
+        voxel_mass / 6.0, 0.0, 0.0,
+        0.0, voxel_mass / 6.0, 0.0,
+        0.0, 0.0, voxel_mass / 6.0,
+    ]);
+    assert!(testing::mat_equals_with_delta(structure.inertia_tensor(), expected, 0.0001));
+
+    // A cube of side 3: m * (3² + 3²) / 12 on each axis, wherever the cube is.
+    let structure = Structure::new(&catalog, 0, 2, 0, 2, 0, 2, TEST_VOXEL);
+    let diagonal = structure.mass() * 1.5;
+    let expected = Mat3f::from_data([
+        diagonal, 0.0, 0.0,
+        0.0, diagonal, 0.0,
+        0.0, 0.0, diagonal,
+    ]);
+    assert!(testing::mat_equals_with_delta(structure.inertia_tensor(), expected.clone(), 0.001));
+    let far_structure = Structure::new(&catalog, 10000, 10002, -20000, -19998, 5000, 5002, TEST_VOXEL);
+    assert!(testing::mat_equals_with_delta(far_structure.inertia_tensor(), expected, 0.001));
+
+    // A stick along x is easy to spin around x, hard around y and z.
+    let structure = Structure::new(&catalog, -3, 3, 0, 0, 0, 0, TEST_VOXEL);
+    let inertia = structure.inertia_tensor();
+    assert!(testing::equals_with_delta(inertia[0][0], 7.0 * voxel_mass / 6.0, 0.001));
+    assert!(testing::equals_with_delta(inertia[1][1], voxel_mass * 28.0 + 7.0 * voxel_mass / 6.0, 0.001));
+    assert!(testing::equals_with_delta(inertia[2][2], inertia[1][1], 0.001));
+    assert!(testing::equals_with_delta(inertia[0][1], 0.0, 0.001));
+
+    // Removing voxels keeps the tensor up to date.
+    let mut structure = Structure::new(&catalog, -3, 3, 0, 0, 0, 0, TEST_VOXEL);
+    for x in 1..4 {
+        structure.set_voxel(x, 0, 0, None);
+    }
+    let expected = Structure::new(&catalog, -3, 0, 0, 0, 0, 0, TEST_VOXEL).inertia_tensor();
+    assert!(testing::mat_equals_with_delta(structure.inertia_tensor(), expected, 0.001));
 }
 
 #[test]
-fn body_momentum_rotated() {
-    let structure_square = Structure::new(-1, 1, -1, 1, -1, 1, TEST_VOXEL);
-
-    let repere = Mat4f::rotation_around_z(PI / 2.0);
-    let mut body = Body::new(structure_square.clone(), repere);
-    body.add_yaw_rotation(PI / 2.0);
-    let momentum = body.momentum(Vect3i::new([1, 1, 1])) / body.structure().mass();
-    assert!(testing::vec_equals_with_delta(momentum, Vect3f::new([-PI / 2.0, -PI / 2.0, 0.0]), 0.0001));
-
-    let repere = Mat4f::rotation_around_z(PI / 2.0) * Mat4f::rotation_around_y(PI / 4.0);
-    let mut body = Body::new(structure_square.clone(), repere);
-    body.add_pitch_rotation(PI / 2.0);
-    let momentum = body.momentum(Vect3i::new([1, 1, 1])) / body.structure().mass();
-    assert!(testing::vec_equals_with_delta(momentum, Vect3f::new([0.0, 0.0, -f32::sqrt(2.0) * PI / 2.0]), 0.0001));
-
-    let repere = Mat4f::rotation_around_z(PI / 2.0) * Mat4f::rotation_around_y(PI / 4.0) * Mat4f::rotation_around_x(-PI / 4.0);
-    let mut body = Body::new(structure_square.clone(), repere);
-    body.add_roll_rotation(PI / 2.0);
-    let momentum = body.momentum(Vect3i::new([1, 1, 1])) / body.structure().mass();
-    assert!(testing::vec_equals_with_delta(momentum, Vect3f::new([0.0, PI / 2.0, PI / 2.0]), 0.0001));
+fn body_torque_depends_on_inertia() {
+    let catalog = VoxelCatalog::create();
+    let stick = Structure::new(&catalog, -3, 3, 0, 0, 0, 0, TEST_VOXEL);
+    let mut body_roll = Body::new(stick.clone(), Mat4f::identity());
+    body_roll.apply_torque(Vect3f::new([1.0, 0.0, 0.0]), 1.0);
+    let mut body_pitch = Body::new(stick.clone(), Mat4f::identity());
+    body_pitch.apply_torque(Vect3f::new([0.0, 1.0, 0.0]), 1.0);
+    assert!(testing::equals_with_delta(body_roll.roll(), 1.0 / stick.inertia_tensor()[0][0], 0.0001));
+    assert!(testing::equals_with_delta(body_pitch.pitch(), 1.0 / stick.inertia_tensor()[1][1], 0.0001));
+    assert!(body_roll.roll() > body_pitch.pitch() * 20.0);
+
+    // Torque is given in world axes: a stick turned along y rolls around world y.
+    let mut body = Body::new(stick.clone(), Mat4f::rotation_around_z(PI / 2.0));
+    body.apply_torque(Vect3f::new([0.0, 1.0, 0.0]), 1.0);
+    assert!(testing::equals_with_delta(body.roll(), 1.0 / stick.inertia_tensor()[0][0], 0.0001));
+    assert!(testing::vec_equals_with_delta(body.angular_velocity(), Vect3f::new([0.0, body.roll(), 0.0]), 0.0001));
 }
 
 #[test]
-fn body_momentum_multiple_rotation() {
-    let structure_square = Structure::new(-1, 1, -1, 1, -1, 1, TEST_VOXEL);
-
-    let mut body = Body::new(structure_square.clone(), Mat4f::identity());
-    body.add_yaw_rotation(PI / 2.0);
-    body.add_roll_rotation(PI / 2.0);
-    let momentum = body.momentum(Vect3i::new([1, 1, 1])) / body.structure().mass();
-    assert!(testing::vec_equals_with_delta(momentum, Vect3f::new([-PI / 2.0, 0.0, PI / 2.0]), 0.0001));
-
-    let repere = Mat4f::rotation_around_z(PI / 2.0);
-    let mut body = Body::new(structure_square.clone(), repere);
-    body.add_yaw_rotation(PI / 2.0);
-    body.add_roll_rotation(PI / 2.0);
-    let momentum = body.momentum(Vect3i::new([1, 1, 1])) / body.structure().mass();
-    assert!(testing::vec_equals_with_delta(momentum, Vect3f::new([0.0, -PI / 2.0, PI / 2.0]), 0.0001));
+fn body_off_center_impulse() {
+    let catalog = VoxelCatalog::create();
+    let structure = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    let mass = structure.mass();
+    let mut body = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([5.0, 0.0, 0.0])));
+    body.apply_impulse(Vect3f::new([0.0, mass, 0.0]), Vect3f::new([6.0, 0.0, 0.0]));
+    assert!(testing::vec_equals_with_delta(body.velocity(), Vect3f::new([0.0, 1.0, 0.0]), 0.0001));
+    assert!(testing::vec_equals_with_delta(body.angular_momentum(), Vect3f::new([0.0, 0.0, mass]), 0.0001));
+    assert!(testing::equals_with_delta(body.yaw(), mass / structure.inertia_tensor()[2][2], 0.0001));
+
+    // Through the center of mass, no spin.
+    let mut body = Body::new(structure.clone(), Mat4f::identity());
+    body.apply_impulse(Vect3f::new([0.0, mass, 0.0]), Vect3f::new([-4.0, 0.0, 0.0]));
+    body.apply_impulse(Vect3f::new([0.0, mass, 0.0]), Vect3f::new([4.0, 0.0, 0.0]));
+    assert!(testing::vec_equals_with_delta(body.angular_momentum(), Vect3f::zero(), 0.0001));
+    assert!(testing::vec_equals_with_delta(body.velocity(), Vect3f::new([0.0, 2.0, 0.0]), 0.0001));
+}
+
+#[test]
+fn body_free_rotation_keeps_angular_momentum() {
+    let catalog = VoxelCatalog::create();
+    let mut structure = Structure::new(&catalog, 0, 4, 0, 1, 0, 0, TEST_VOXEL);
+    structure.set_voxel(4, 1, 0, None);
+    let mut body = Body::new(structure, Mat4f::identity());
+    add_local_rotation(&mut body, 0.7, 0.0, 1.3);
+    let angular_momentum = body.angular_momentum();
+    let center_of_mass = body.repere().clone() * body.structure().center_of_mass();
+    for _ in 0..100 {
+        body.apply_velocity_and_rotation(1.0 / 60.0);
+    }
+    assert!(testing::vec_equals_with_delta(body.angular_momentum(), angular_momentum, 0.0001));
+    let new_center_of_mass = body.repere().clone() * body.structure().center_of_mass();
+    assert!(testing::vec_equals_with_delta(new_center_of_mass, center_of_mass, 0.001));
+    // Body axes moved, so angular velocity changed, as inertia is not the same around all axes.
+    assert!(!testing::vec_equals_with_delta(body.angular_velocity(), Vect3f::new([0.7, 0.0, 1.3]), 0.01));
 }
 
 #[test]
 fn body_cube_intersection_high_precision() {
+    let catalog = VoxelCatalog::create();
     // Cubes with same axis, with a corner voxel half-mingled.
     //        +-------+
     //        |       |
@@ -186,7 +257,7 @@ fn body_cube_intersection_high_precision() {
     // |       |
     // |       |
     // +-------+
-    let structure_square = Structure::new(-1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    let structure_square = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
     let body_a = Body::new(structure_square.clone(), Mat4f::identity());
     let body_b = Body::new(structure_square.clone(), Mat4f::translation(Vect3f::new([2.5, 2.5, 2.5])));
     let result = collision::intersection_high_precision(&body_a, &body_b);
@@ -221,6 +292,7 @@ fn body_cube_intersection_high_precision() {
 
 #[test]
 fn body_cube_intersection_low_precision() {
+    let catalog = VoxelCatalog::create();
     // Cubes with same axis, with a corner voxel half-mingled.
     //        +-------+
     //        |       |
@@ -231,7 +303,7 @@ fn body_cube_intersection_low_precision() {
     // |       |
     // |       |
     // +-------+
-    let structure_square = Structure::new(-1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    let structure_square = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
     let body_a = Body::new(structure_square.clone(), Mat4f::identity());
     let body_b = Body::new(structure_square.clone(), Mat4f::translation(Vect3f::new([2.4, 2.4, 2.4])));
     let result = collision::intersection_low_precision(&body_a, &body_b);
@@ -266,8 +338,9 @@ fn body_cube_intersection_low_precision() {
 
 #[test]
 fn body_heavy_intersection() {
+    let catalog = VoxelCatalog::create();
     let structure_h = {
-        let mut structure = Structure::new(0, 0, 0, 0, 0, 0, TEST_VOXEL);
+        let mut structure = Structure::new(&catalog, 0, 0, 0, 0, 0, 0, TEST_VOXEL);
         structure.add_voxel(Vect3i::new([1, 0, 0]), TEST_VOXEL);
         structure.add_voxel(Vect3i::new([2, 0, 0]), TEST_VOXEL);
         structure.add_voxel(Vect3i::new([3, 0, 0]), TEST_VOXEL);
@@ -332,8 +405,9 @@ fn body_heavy_intersection() {
 
 #[test]
 fn elastic_collision_same_mass() {
+    let catalog = VoxelCatalog::create();
     let restitution = 1.0;
-    let structure = Structure::new(-1, 1, 0, 0, 0, 0, TEST_VOXEL);
+    let structure = Structure::new(&catalog, -1, 1, 0, 0, 0, 0, TEST_VOXEL);
     let mut body_a = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([-1.2, 0.0, 0.0])));
     let mut body_b = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([1.2, 0.0, 0.0])));
 
@@ -343,6 +417,9 @@ fn elastic_collision_same_mass() {
     assert!(testing::vec_equals_with_delta(body_a.velocity(), Vect3f::new([0.0, 0.0, 0.0]), 0.0001));
     assert!(testing::vec_equals_with_delta(body_b.velocity(), Vect3f::new([1.0, 0.0, 0.0]), 0.0001));
 
+    // Bodies were separated by the previous collision.
+    body_a = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([-1.2, 0.0, 0.0])));
+    body_b = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([1.2, 0.0, 0.0])));
     body_a.set_velocity(Vect3f::new([1.0, 0.0, 0.0]));
     body_b.set_velocity(Vect3f::new([-1.0, 0.0, 0.0]));
     collision::apply_collision_if_any(&mut body_a, &mut body_b, restitution);
@@ -352,8 +429,9 @@ fn elastic_collision_same_mass() {
 
 #[test]
 fn perfectly_inelastic_collision_same_mass() {
+    let catalog = VoxelCatalog::create();
     let restitution = 0.0;
-    let structure = Structure::new(-1, 1, 0, 0, 0, 0, TEST_VOXEL);
+    let structure = Structure::new(&catalog, -1, 1, 0, 0, 0, 0, TEST_VOXEL);
     let mut body_a = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([-1.2, 0.0, 0.0])));
     let mut body_b = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([1.2, 0.0, 0.0])));
 
@@ -363,6 +441,9 @@ fn perfectly_inelastic_collision_same_mass() {
     assert!(testing::vec_equals_with_delta(body_a.velocity(), Vect3f::new([0.5, 0.0, 0.0]), 0.0001));
     assert!(testing::vec_equals_with_delta(body_b.velocity(), Vect3f::new([0.5, 0.0, 0.0]), 0.0001));
 
+    // Bodies were separated by the previous collision.
+    body_a = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([-1.2, 0.0, 0.0])));
+    body_b = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([1.2, 0.0, 0.0])));
     body_a.set_velocity(Vect3f::new([1.0, 0.0, 0.0]));
     body_b.set_velocity(Vect3f::new([-1.0, 0.0, 0.0]));
     collision::apply_collision_if_any(&mut body_a, &mut body_b, restitution);
@@ -373,11 +454,12 @@ fn perfectly_inelastic_collision_same_mass() {
 
 #[test]
 fn elastic_collision_different_mass() {
+    let catalog = VoxelCatalog::create();
     let restitution = 1.0;
-    let structure_a = Structure::new(-4, 1, 0, 0, 0, 0, TEST_VOXEL);
-    let structure_b = Structure::new(-1, 1, 0, 0, 0, 0, TEST_VOXEL);
-    let mut body_a = Body::new(structure_a, Mat4f::translation(Vect3f::new([-1.2, 0.0, 0.0])));
-    let mut body_b = Body::new(structure_b, Mat4f::translation(Vect3f::new([1.2, 0.0, 0.0])));
+    let structure_a = Structure::new(&catalog, -4, 1, 0, 0, 0, 0, TEST_VOXEL);
+    let structure_b = Structure::new(&catalog, -1, 1, 0, 0, 0, 0, TEST_VOXEL);
+    let mut body_a = Body::new(structure_a.clone(), Mat4f::translation(Vect3f::new([-1.2, 0.0, 0.0])));
+    let mut body_b = Body::new(structure_b.clone(), Mat4f::translation(Vect3f::new([1.2, 0.0, 0.0])));
 
     body_a.set_velocity(Vect3f::new([1.0, 0.0, 0.0]));
     body_b.set_velocity(Vect3f::new([0.0, 0.0, 0.0]));
@@ -385,6 +467,9 @@ fn elastic_collision_different_mass() {
     assert!(testing::vec_equals_with_delta(body_a.velocity(), Vect3f::new([1.0 / 3.0, 0.0, 0.0]), 0.0001));
     assert!(testing::vec_equals_with_delta(body_b.velocity(), Vect3f::new([4.0 / 3.0, 0.0, 0.0]), 0.0001));
 
+    // Bodies were separated by the previous collision.
+    body_a = Body::new(structure_a, Mat4f::translation(Vect3f::new([-1.2, 0.0, 0.0])));
+    body_b = Body::new(structure_b, Mat4f::translation(Vect3f::new([1.2, 0.0, 0.0])));
     body_a.set_velocity(Vect3f::new([1.0, 0.0, 0.0]));
     body_b.set_velocity(Vect3f::new([-1.0, 0.0, 0.0]));
     collision::apply_collision_if_any(&mut body_a, &mut body_b, restitution);
@@ -394,11 +479,12 @@ fn elastic_collision_different_mass() {
 
 #[test]
 fn perfectly_inelastic_collision_different_mass() {
+    let catalog = VoxelCatalog::create();
     let restitution = 0.0;
-    let structure_a = Structure::new(-4, 1, 0, 0, 0, 0, TEST_VOXEL);
-    let structure_b = Structure::new(-1, 1, 0, 0, 0, 0, TEST_VOXEL);
-    let mut body_a = Body::new(structure_a, Mat4f::translation(Vect3f::new([-1.2, 0.0, 0.0])));
-    let mut body_b = Body::new(structure_b, Mat4f::translation(Vect3f::new([1.2, 0.0, 0.0])));
+    let structure_a = Structure::new(&catalog, -4, 1, 0, 0, 0, 0, TEST_VOXEL);
+    let structure_b = Structure::new(&catalog, -1, 1, 0, 0, 0, 0, TEST_VOXEL);
+    let mut body_a = Body::new(structure_a.clone(), Mat4f::translation(Vect3f::new([-1.2, 0.0, 0.0])));
+    let mut body_b = Body::new(structure_b.clone(), Mat4f::translation(Vect3f::new([1.2, 0.0, 0.0])));
 
     body_a.set_velocity(Vect3f::new([1.0, 0.0, 0.0]));
     body_b.set_velocity(Vect3f::new([0.0, 0.0, 0.0]));
@@ -406,9 +492,289 @@ fn perfectly_inelastic_collision_different_mass() {
     assert!(testing::vec_equals_with_delta(body_a.velocity(), Vect3f::new([2.0 / 3.0, 0.0, 0.0]), 0.0001));
     assert!(testing::vec_equals_with_delta(body_b.velocity(), Vect3f::new([2.0 / 3.0, 0.0, 0.0]), 0.0001));
 
+    // Bodies were separated by the previous collision.
+    body_a = Body::new(structure_a, Mat4f::translation(Vect3f::new([-1.2, 0.0, 0.0])));
+    body_b = Body::new(structure_b, Mat4f::translation(Vect3f::new([1.2, 0.0, 0.0])));
     body_a.set_velocity(Vect3f::new([1.0, 0.0, 0.0]));
     body_b.set_velocity(Vect3f::new([-1.0, 0.0, 0.0]));
     collision::apply_collision_if_any(&mut body_a, &mut body_b, restitution);
     assert!(testing::vec_equals_with_delta(body_a.velocity(), Vect3f::new([1.0 / 3.0, 0.0, 0.0]), 0.0001));
     assert!(testing::vec_equals_with_delta(body_b.velocity(), Vect3f::new([1.0 / 3.0, 0.0, 0.0]), 0.0001));
 }
+
+#[test]
+fn collision_contact_normal() {
+    let catalog = VoxelCatalog::create();
+    let structure = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    let body_a = Body::new(structure.clone(), Mat4f::identity());
+    let body_b = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([0.0, 0.0, 2.2])));
+    let intersections = collision::intersection_low_precision(&body_a, &body_b);
+    let (contact_point, normal) = collision::contact_from_intersections(&body_a, &body_b, &intersections).unwrap();
+    assert!(testing::vec_equals_with_delta(normal, Vect3f::new([0.0, 0.0, 1.0]), 0.0001));
+    assert!(testing::equals_with_delta(contact_point[2], 1.1, 0.0001));
+
+    // Rotated bodies give world normals.
+    let body_a = Body::new(structure.clone(), Mat4f::rotation_around_x(PI / 2.0));
+    let body_b = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([-2.2, 0.0, 0.0])) * Mat4f::rotation_around_z(PI / 2.0));
+    let intersections = collision::intersection_low_precision(&body_a, &body_b);
+    let (_, normal) = collision::contact_from_intersections(&body_a, &body_b, &intersections).unwrap();
+    assert!(testing::vec_equals_with_delta(normal, Vect3f::new([-1.0, 0.0, 0.0]), 0.0001));
+
+    assert!(collision::contact_from_intersections(&body_a, &body_b, &[]).is_none());
+}
+
+fn total_angular_momentum(bodies: &[&Body]) -> Vect3f {
+    let mut result = Vect3f::zero();
+    for body in bodies {
+        result += body.angular_momentum() + Vect3f::cross(body.world_center_of_mass(), body.velocity() * body.structure().mass());
+    }
+    result
+}
+
+#[test]
+fn glancing_collision_spins_body() {
+    let catalog = VoxelCatalog::create();
+    // A wing along y, hit near its tip by a single voxel coming along -x.
+    let wing = Structure::new(&catalog, -1, 0, -5, 5, -2, 2, TEST_VOXEL);
+    let mut body_a = Body::new(wing, Mat4f::identity());
+    let mut body_b = Body::new(Structure::new(&catalog, 0, 0, 0, 0, 0, 0, TEST_VOXEL), Mat4f::translation(Vect3f::new([0.4, 4.0, 0.0])));
+    body_b.set_velocity(Vect3f::new([-1.0, 0.0, 0.0]));
+
+    let linear_momentum = body_a.velocity() * body_a.structure().mass() + body_b.velocity() * body_b.structure().mass();
+    let angular_momentum = total_angular_momentum(&[&body_a, &body_b]);
+    collision::apply_collision_if_any(&mut body_a, &mut body_b, 1.0);
+
+    assert!(body_a.velocity()[0] < 0.0);
+    assert!(body_a.yaw() > 0.0);
+    assert!(testing::equals_with_delta(body_a.roll(), 0.0, 0.0001));
+    assert!(testing::equals_with_delta(body_a.pitch(), 0.0, 0.0001));
+    assert!(body_b.velocity()[0] > 0.0);
+
+    let new_linear_momentum = body_a.velocity() * body_a.structure().mass() + body_b.velocity() * body_b.structure().mass();
+    assert!(testing::vec_equals_with_delta(new_linear_momentum, linear_momentum, 0.0001));
+    assert!(testing::vec_equals_with_delta(total_angular_momentum(&[&body_a, &body_b]), angular_momentum, 0.001));
+
+    // Elastic: relative velocity along the normal is reversed at the contact point.
+    let contact_point = Vect3f::new([0.2, 4.0, 0.0]);
+    let relative_velocity = body_b.velocity_at(contact_point) - body_a.velocity_at(contact_point);
+    assert!(testing::equals_with_delta(relative_velocity[0], 1.0, 0.0001));
+}
+
+#[test]
+fn separating_bodies_do_not_collide() {
+    let catalog = VoxelCatalog::create();
+    let structure = Structure::new(&catalog, -1, 1, 0, 0, 0, 0, TEST_VOXEL);
+    let mut body_a = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([-1.2, 0.0, 0.0])));
+    let mut body_b = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([1.2, 0.0, 0.0])));
+    body_a.set_velocity(Vect3f::new([-1.0, 0.0, 0.0]));
+    body_b.set_velocity(Vect3f::new([0.5, 0.0, 0.0]));
+    collision::apply_collision_if_any(&mut body_a, &mut body_b, 1.0);
+    assert!(testing::vec_equals_with_delta(body_a.velocity(), Vect3f::new([-1.0, 0.0, 0.0]), 0.0001));
+    assert!(testing::vec_equals_with_delta(body_b.velocity(), Vect3f::new([0.5, 0.0, 0.0]), 0.0001));
+}
+
+#[test]
+fn interpenetrating_bodies_are_separated() {
+    let catalog = VoxelCatalog::create();
+    let structure = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    let mut body_a = Body::new(structure.clone(), Mat4f::identity());
+    let mut body_b = Body::new(structure.clone(), Mat4f::translation(Vect3f::new([1.5, 0.3, 0.0])));
+    assert!(!collision::intersection_low_precision(&body_a, &body_b).is_empty());
+
+    // Even without any velocity.
+    collision::apply_collision_if_any(&mut body_a, &mut body_b, 1.0);
+    assert!(collision::intersection_low_precision(&body_a, &body_b).is_empty());
+    assert!(collision::intersection_high_precision(&body_a, &body_b).is_empty());
+    // Same mass, same move, along the contact normal.
+    assert!(testing::vec_equals_with_delta(body_a.repere().position() + body_b.repere().position(), Vect3f::new([1.5, 0.3, 0.0]), 0.0001));
+    assert!(testing::equals_with_delta(body_b.repere().position()[1], 0.3, 0.0001));
+    assert!(body_b.repere().position()[0] - body_a.repere().position()[0] >= 3.0);
+    assert!(testing::vec_equals_with_delta(body_a.velocity(), Vect3f::zero(), 0.0001));
+}
+
+#[test]
+fn interpenetrating_bodies_are_separated_by_mass_ratio() {
+    let catalog = VoxelCatalog::create();
+    let structure_a = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    let structure_b = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL_HEAVY);
+    let mass_ratio = structure_b.mass() / structure_a.mass();
+    let mut body_a = Body::new(structure_a, Mat4f::identity());
+    let mut body_b = Body::new(structure_b, Mat4f::translation(Vect3f::new([0.0, 0.0, -2.0])));
+    let intersections = collision::intersection_low_precision(&body_a, &body_b);
+    let (_, normal) = collision::contact_from_intersections(&body_a, &body_b, &intersections).unwrap();
+    assert!(testing::vec_equals_with_delta(normal, Vect3f::new([0.0, 0.0, -1.0]), 0.0001));
+    let depth = collision::penetration_depth(&body_a, &body_b, &intersections, normal);
+    assert!(testing::equals_with_delta(depth, 1.0, 0.0001));
+
+    collision::separate_bodies(&mut body_a, &mut body_b, normal, depth);
+    assert!(collision::intersection_low_precision(&body_a, &body_b).is_empty());
+    // Body B is heavier, so it moves less, by the mass ratio.
+    assert!(mass_ratio > 2.0);
+    let move_a = body_a.repere().position()[2];
+    let move_b = body_b.repere().position()[2] + 2.0;
+    assert!(testing::equals_with_delta(move_a, -mass_ratio * move_b, 0.0001));
+    assert!(move_a > 0.5);
+}
+
+#[test]
+fn broadphase_candidate_pairs() {
+    let boxes = vec![
+        Box3f::from_min_max(Vect3f::new([0.0, 0.0, 0.0]), Vect3f::new([2.0, 2.0, 2.0])),
+        // Overlaps the first one along x only.
+        Box3f::from_min_max(Vect3f::new([1.0, 5.0, 0.0]), Vect3f::new([3.0, 6.0, 2.0])),
+        // Intersects the first one, contains the second one along x.
+        Box3f::from_min_max(Vect3f::new([-1.0, 1.0, 1.0]), Vect3f::new([4.0, 3.0, 3.0])),
+        Box3f::from_min_max(Vect3f::new([10.0, 0.0, 0.0]), Vect3f::new([11.0, 1.0, 1.0])),
+        Box3f::new(),
+        // Touching the fourth one.
+        Box3f::from_min_max(Vect3f::new([11.0, 1.0, 1.0]), Vect3f::new([12.0, 2.0, 2.0])),
+    ];
+    assert!(broadphase::candidate_pairs(&boxes) == vec![(0, 2), (3, 5)]);
+    assert!(broadphase::candidate_pairs(&[]).is_empty());
+
+    // Same as testing every pair.
+    let mut boxes = vec![];
+    for i in 0..40 {
+        let min = Vect3f::new([(i * 7 % 13) as f32, (i * 5 % 11) as f32, (i * 3 % 7) as f32]);
+        boxes.push(Box3f::from_min_max(min, min + Vect3f::all(1.0 + (i % 3) as f32)));
+    }
+    let mut expected = vec![];
+    for a in 0..boxes.len() {
+        for b in a + 1..boxes.len() {
+            if boxes[a].intersects(&boxes[b]) {
+                expected.push((a, b));
+            }
+        }
+    }
+    assert!(!expected.is_empty());
+    assert!(broadphase::candidate_pairs(&boxes) == expected);
+}
+
+#[test]
+fn body_arena_generational_handles() {
+    let mut arena = BodyArena::new();
+    let handle_a = arena.insert("a");
+    let handle_b = arena.insert("b");
+    assert!(arena.get(handle_a) == Some(&"a"));
+    assert!(arena.remove(handle_a) == Some("a"));
+    assert!(arena.get(handle_a).is_none());
+    assert!(arena.remove(handle_a).is_none());
+
+    // The slot is reused, but the old handle does not match the new value.
+    let handle_c = arena.insert("c");
+    assert!(handle_c != handle_a);
+    assert!(arena.get(handle_a).is_none());
+    assert!(arena.get(handle_c) == Some(&"c"));
+    assert!(arena.get(handle_b) == Some(&"b"));
+
+    *arena.get_mut(handle_b).unwrap() = "bb";
+    arena.retain(|value| *value != "c");
+    assert!(!arena.contains(handle_c));
+    assert!(arena.handles() == vec![handle_b]);
+    assert!(arena.iter().map(|(_, value)| *value).collect::<Vec<_>>() == vec!["bb"]);
+}
+
+#[test]
+fn body_arena_split_records() {
+    let mut arena = BodyArena::new();
+    let parent = arena.insert(0);
+    let child_a = arena.insert_split(parent, 1);
+    let child_b = arena.insert_split(parent, 2);
+    let grand_child = arena.insert_split(child_a, 3);
+    assert!(arena.children(parent) == vec![child_a, child_b]);
+    assert!(arena.parent(grand_child) == Some(child_a));
+    assert!(arena.parent(parent).is_none());
+
+    // Lineage is kept after the parent is destroyed, and dropped along with the child.
+    arena.remove(child_a);
+    assert!(arena.parent(grand_child) == Some(child_a));
+    assert!(arena.children(parent) == vec![child_b]);
+    arena.remove(grand_child);
+    assert!(arena.parent(grand_child).is_none());
+    assert!(arena.children(child_a).is_empty());
+
+    // A reused slot does not inherit the lineage of the removed body.
+    let reused = arena.insert(4);
+    assert!(arena.parent(reused).is_none());
+}
+
+// Momentum of the voxels still alive, as if they were moving along with the body.
+fn alive_voxels_momentum(body: &Body) -> (Vect3f, Vect3f) {
+    let mut linear_momentum = Vect3f::zero();
+    let mut angular_momentum = Vect3f::zero();
+    let angular_velocity = body.angular_velocity();
+    body.structure().for_each_voxel(|coords, voxel| {
+        if voxel.life > 0.0 {
+            let position = body.repere().clone() * Vect3f::new([coords[0] as f32, coords[1] as f32, coords[2] as f32]);
+            let mass = body.structure().voxel_mass(voxel);
+            let momentum = body.velocity_at(position) * mass;
+            linear_momentum += momentum;
+            angular_momentum += Vect3f::cross(position, momentum) + angular_velocity * (mass / 6.0);
+        }
+    });
+    (linear_momentum, angular_momentum)
+}
+
+#[test]
+fn spinning_bar_cut_in_half_conserves_momentum() {
+    let catalog = VoxelCatalog::create();
+    let mut structure = Structure::new(&catalog, -3, 3, 0, 0, 0, 0, TEST_VOXEL);
+    structure.set_voxel(0, 0, 0, Some(TEST_DEAD_VOXEL));
+    let mut body = Body::new(structure, Mat4f::translation(Vect3f::new([1.0, 2.0, 3.0])) * Mat4f::rotation_around_z(0.3));
+    body.set_velocity(Vect3f::new([1.0, 0.0, -0.5]));
+    add_local_rotation(&mut body, 0.5, 0.0, 2.0);
+    let angular_velocity = body.angular_velocity();
+    let (linear_momentum, angular_momentum) = alive_voxels_momentum(&body);
+
+    let new_bodies = body.update_dead_voxels();
+    assert!(body.structure().is_empty());
+    assert!(new_bodies.len() == 2);
+
+    let new_linear_momentum = new_bodies.iter().map(|new_body| new_body.velocity() * new_body.structure().mass()).sum::<Vect3f>();
+    assert!(testing::vec_equals_with_delta(new_linear_momentum, linear_momentum, 0.001));
+    let new_bodies_ref: Vec<&Body> = new_bodies.iter().collect();
+    assert!(testing::vec_equals_with_delta(total_angular_momentum(&new_bodies_ref), angular_momentum, 0.01));
+
+    // Both halves keep spinning the same way, and fly apart from the spin.
+    for new_body in &new_bodies {
+        assert!(testing::vec_equals_with_delta(new_body.angular_velocity(), angular_velocity, 0.0001));
+    }
+    let separation_velocity = new_bodies[1].velocity() - new_bodies[0].velocity();
+    let separation = new_bodies[1].world_center_of_mass() - new_bodies[0].world_center_of_mass();
+    assert!(testing::equals_with_delta(Vect3f::dot(separation_velocity, separation), 0.0, 0.001));
+    assert!(separation_velocity.length() > 1.0);
+}
+
+#[test]
+fn spinning_bar_losing_its_end_conserves_momentum() {
+    let catalog = VoxelCatalog::create();
+    let mut structure = Structure::new(&catalog, -1, 3, 0, 0, 0, 0, TEST_VOXEL);
+    structure.set_voxel(1, 0, 0, Some(TEST_DEAD_VOXEL));
+    let mut body = Body::new(structure, Mat4f::rotation_around_x(0.7));
+    body.set_velocity(Vect3f::new([0.0, 2.0, 0.0]));
+    add_local_rotation(&mut body, 0.0, 0.0, 1.5);
+    let (linear_momentum, angular_momentum) = alive_voxels_momentum(&body);
+
+    let new_bodies = body.update_dead_voxels();
+    assert!(new_bodies.len() == 1);
+    let bodies = vec![&body, &new_bodies[0]];
+    let new_linear_momentum = body.velocity() * body.structure().mass() + new_bodies[0].velocity() * new_bodies[0].structure().mass();
+    assert!(testing::vec_equals_with_delta(new_linear_momentum, linear_momentum, 0.001));
+    assert!(testing::vec_equals_with_delta(total_angular_momentum(&bodies), angular_momentum, 0.01));
+    // The remaining part has a new center of mass, so it moves differently than before.
+    assert!(!testing::vec_equals_with_delta(body.velocity(), Vect3f::new([0.0, 2.0, 0.0]), 0.1));
+}
+
+#[test]
+fn body_ray_cast_in_world() {
+    let catalog = VoxelCatalog::create();
+    let structure = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    let body = Body::new(structure, Mat4f::translation(Vect3f::new([10.0, 0.0, 0.0])) * Mat4f::rotation_around_z(PI / 2.0));
+    let hit = body.ray_cast(Segm3f::new(Vect3f::new([0.0, 1.0, 0.0]), Vect3f::new([20.0, 1.0, 0.0]))).unwrap();
+    // World x is structure -y, so the segment enters by the structure +y face.
+    assert!(hit.coords == Vect3i::new([1, 1, 0]));
+    assert!(hit.face == Vect3i::new([0, 1, 0]));
+    assert!(testing::vec_equals_with_delta(hit.point, Vect3f::new([8.5, 1.0, 0.0]), 0.0001));
+    assert!(testing::equals_with_delta(hit.distance, 8.5, 0.0001));
+    assert!(body.ray_cast(Segm3f::new(Vect3f::new([0.0, 5.0, 0.0]), Vect3f::new([20.0, 5.0, 0.0]))).is_none());
+}
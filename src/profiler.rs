@@ -3,12 +3,19 @@ use std::time::Duration;
 use std::time::Instant;
 
 use crate::maths::boxes::Box3f;
+use crate::maths::boxes::Box3i;
 use crate::maths::matrix::Mat4f;
+use crate::maths::segment::Segm3f;
 use crate::maths::vector::Vect3f;
+use crate::maths::vector::Vect3i;
 use crate::physics::body::Body;
+use crate::physics::broadphase;
 use crate::physics::collision;
+use crate::voxels::mesh::SurfaceMesh;
+use crate::voxels::storage::StorageLayout;
 use crate::voxels::structure::Structure;
 use crate::voxels::catalog::VoxelCatalog;
+use crate::voxels::voxel::VoxelID;
 
 fn profile<F: Fn()>(f: F, name: &str) -> Duration {
     const COUNT: u32 = 10;
@@ -24,7 +31,7 @@ fn profile<F: Fn()>(f: F, name: &str) -> Duration {
 
 pub fn run_profiler() {
     let voxel_catalog = VoxelCatalog::create();
-    let tie_fighter_structure = Structure::read_from_file(&voxel_catalog, "structures/tie.vors");
+    let tie_fighter_structure = Structure::read_from_file(&voxel_catalog, "structures/tie.vors").expect("Unable to load tie fighter structure");
 
     assert!(tie_fighter_structure.get_box() == Box3f::from_min_max(Vect3f::new([-6.5, -8.5, -9.5]), Vect3f::new([6.5, 8.5, 9.5])));
     let fighter_a = Body::new(tie_fighter_structure.clone(), Mat4f::identity());
@@ -49,4 +56,97 @@ pub fn run_profiler() {
     total_time += profile(|| { collision::intersection_low_precision(&fighter_a, &fighter_a); }, "fighters exactly at the same position");
     total_time += profile(|| { collision::intersection_low_precision(&fighter_a, &fighter_d); }, "fighters slightly colliding");
     println!("Total time: {} us.", total_time.as_micros());
+
+    println!("");
+    println!("Many fragments collision:");
+    // Small debris scattered around, a few of them touching each other, as after a long fight.
+    const FRAGMENT_COUNT: i32 = 500;
+    let fragments: Vec<Body> = (0..FRAGMENT_COUNT).map(|i| {
+        let position = Vect3f::new([(i % 10) as f32 * 3.5, (i / 10 % 10) as f32 * 3.5, (i / 100) as f32 * 3.5]);
+        Body::new(Structure::new(&voxel_catalog, 0, 1, 0, 1, 0, i % 3, voxel_catalog.create_voxel(VoxelID::LIGHT_HULL)), Mat4f::translation(position))
+    }).collect();
+    let mut total_time = Duration::ZERO;
+    total_time += profile(|| {
+        for a in 0..fragments.len() {
+            for b in a + 1..fragments.len() {
+                collision::intersection_low_precision(&fragments[a], &fragments[b]);
+            }
+        }
+    }, "every pair of fragments");
+    total_time += profile(|| {
+        let boxes: Vec<Box3f> = fragments.iter().map(|fragment| fragment.get_box()).collect();
+        for (a, b) in broadphase::candidate_pairs(&boxes) {
+            collision::intersection_low_precision(&fragments[a], &fragments[b]);
+        }
+    }, "broadphase candidate pairs only");
+    println!("Total time: {} us.", total_time.as_micros());
+
+    println!("");
+    println!("Ray casts through sparse structure:");
+    // A thin frame along the box edges: rays go through a lot of empty space.
+    let mut sparse_structure = Structure::empty(&voxel_catalog, -15, 15, -15, 15, -15, 15);
+    for i in -15..16 {
+        for (a, b) in [(-15, -15), (-15, 15), (15, -15), (15, 15)] {
+            sparse_structure.add_voxel(Vect3i::new([i, a, b]), voxel_catalog.create_voxel(VoxelID::LIGHT_HULL));
+            sparse_structure.add_voxel(Vect3i::new([a, i, b]), voxel_catalog.create_voxel(VoxelID::LIGHT_HULL));
+            sparse_structure.add_voxel(Vect3i::new([a, b, i]), voxel_catalog.create_voxel(VoxelID::LIGHT_HULL));
+        }
+    }
+    const RAY_COUNT: i32 = 5000;
+    let segments: Vec<Segm3f> = (0..RAY_COUNT).map(|i| {
+        let angle = i as f32 * 0.7;
+        let start = Vect3f::new([40.0 * angle.cos(), 40.0 * angle.sin(), (i % 61 - 30) as f32]);
+        let end = Vect3f::new([-40.0 * (angle * 1.3).cos(), -40.0 * (angle * 1.3).sin(), (i % 37 - 18) as f32]);
+        Segm3f::new(start, end)
+    }).collect();
+    let mut total_time = Duration::ZERO;
+    total_time += profile(|| {
+        for segment in &segments {
+            sparse_structure.ray_cast_all_cell_by_cell(*segment);
+        }
+    }, "plain Amanatides and Woo walk");
+    total_time += profile(|| {
+        for segment in &segments {
+            sparse_structure.ray_cast_all(*segment);
+        }
+    }, "skipping empty octree cells");
+    println!("Total time: {} us.", total_time.as_micros());
+
+    println!("");
+    println!("Sparse station storage:");
+    // Small modules far apart, added one after the other as in the editor.
+    let station_voxels: Vec<Vect3i> = (0..64).flat_map(|module| {
+        let module_position = Vect3i::new([module % 4 * 60 - 90, module / 4 % 4 * 60 - 90, module / 16 * 60 - 90]);
+        (0..27).map(move |i| module_position + Vect3i::new([i % 3, i / 3 % 3, i / 9]))
+    }).collect();
+    let build_station = |layout: StorageLayout| {
+        let mut station = Structure::with_layout(&voxel_catalog, Box3i::zero(), layout);
+        for coords in &station_voxels {
+            station.add_voxel(*coords, voxel_catalog.create_voxel(VoxelID::LIGHT_HULL));
+        }
+        station
+    };
+    let mut total_time = Duration::ZERO;
+    for layout in [StorageLayout::Dense, StorageLayout::Chunked] {
+        total_time += profile(|| { build_station(layout); }, &format!("{:?} layout, building the station", layout));
+        let station = build_station(layout);
+        total_time += profile(|| {
+            let mut station = station.clone();
+            for coords in &station_voxels {
+                station.remove_voxel(*coords);
+            }
+        }, &format!("{:?} layout, removing every voxel", layout));
+        println!("{:?} layout memory: {} kB.", layout, station.storage_memory_size() / 1024);
+        assert!(station.to_layout(StorageLayout::Chunked) == station);
+    }
+    println!("Total time: {} us.", total_time.as_micros());
+
+    println!("");
+    println!("Surface meshing:");
+    let mut voxel_count = 0;
+    tie_fighter_structure.for_each_voxel(|_, _| voxel_count += 1);
+    let total_time = profile(|| { SurfaceMesh::greedy_from_structure(&tie_fighter_structure, &voxel_catalog); }, "greedy mesh of the fighter");
+    let quad_count = SurfaceMesh::greedy_from_structure(&tie_fighter_structure, &voxel_catalog).quads().len();
+    println!("{} voxels drawn with {} quads.", voxel_count, quad_count);
+    println!("Total time: {} us.", total_time.as_micros());
 }
@@ -1,41 +1,128 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+
+use super::error::LoadError;
+use super::voxel::DamageType;
+use super::voxel::ModuleType;
 use super::voxel::TextureType;
 use super::voxel::Voxel;
 use super::voxel::VoxelDescriptor;
 use super::voxel::VoxelID;
+use super::voxel::VOXEL_VOLUME;
 
 pub struct VoxelCatalog {
-    descriptors: Vec<VoxelDescriptor>,
+    descriptors: HashMap<VoxelID, VoxelDescriptor>,
+    ids: Vec<VoxelID>,
+    voxel_masses: VoxelMasses,
+}
+
+// Mass of one voxel of each type, in tonnes. Shared by the structures built with the catalog,
+// so that they keep their mass up to date without holding the catalog.
+#[derive(Clone)]
+pub struct VoxelMasses(Rc<HashMap<VoxelID, f32>>);
+
+impl VoxelMasses {
+    pub fn get(&self, id: VoxelID) -> f32 {
+        self.0[&id]
+    }
 }
 
 impl VoxelCatalog {
+    pub const DEFAULT_FILE_NAME: &'static str = "catalog/voxels.def";
+    const SEPARATOR: &'static str = ";";
+    const COMMENT: &'static str = "#";
+    const NO_MODULE: &'static str = "None";
+
     pub fn create() -> Self {
-        let mut descriptors: Vec<VoxelDescriptor> = vec![];
-        let dummy_voxel_descriptor = VoxelDescriptor { max_life: 0.0, texture_type: TextureType::Core };
-        descriptors.resize(VoxelID::COUNT as usize, dummy_voxel_descriptor);
+        Self::read_from_file(Self::DEFAULT_FILE_NAME).unwrap_or_else(|error| panic!("Unable to load voxel catalog: {error}"))
+    }
 
-        descriptors[VoxelID::ShipCore as usize] = VoxelDescriptor {
-            max_life: 5.0,
-            texture_type: TextureType::Core,
-        };
-        descriptors[VoxelID::LightHull as usize] = VoxelDescriptor {
-            max_life: 2.0,
-            texture_type: TextureType::LightHull,
-        };
-        descriptors[VoxelID::HeavyHull as usize] = VoxelDescriptor {
-            max_life: 5.0,
-            texture_type: TextureType::DarkHull,
-        };
-        descriptors[VoxelID::Canon as usize] = VoxelDescriptor {
-            max_life: 5.0,
-            texture_type: TextureType::Canon,
+    pub fn read_from_file(file_name: &str) -> Result<Self, LoadError> {
+        let catalog_file_content = std::fs::read_to_string(file_name).map_err(|error| LoadError::Io { file_name: file_name.to_string(), error: error })?;
+        Self::deserialize(&catalog_file_content)
+    }
+
+    // One voxel type per line: id;name;max_life;texture_type;density;penetration_resistance;kinetic_resistance;energy_resistance;explosive_resistance;module;module_output;power_draw
+    // Module is None for voxel types which are only hull.
+    // Empty lines and lines starting with '#' are ignored.
+    pub fn deserialize(str: &str) -> Result<Self, LoadError> {
+        fn parse_number<T: std::str::FromStr>(line: usize, value: &str) -> Result<T, LoadError> {
+            value.trim().parse().map_err(|_| LoadError::InvalidNumber { line: line, value: value.to_string() })
+        }
+
+        let mut result = Self {
+            descriptors: Default::default(),
+            ids: vec![],
+            voxel_masses: VoxelMasses(Default::default()),
         };
-        Self {
-            descriptors: descriptors,
+        for (index, line) in str.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(Self::COMMENT) {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(Self::SEPARATOR).map(|field| field.trim()).collect();
+            // Names are used as material names in exported meshes, which cannot hold whitespaces.
+            if fields.len() != 12 || fields[1].is_empty() || fields[1].contains(char::is_whitespace) {
+                return Err(LoadError::InvalidDefinition { line: line_number, content: line.to_string() });
+            }
+            let id = VoxelID(parse_number(line_number, fields[0])?);
+            let max_life: f32 = parse_number(line_number, fields[2])?;
+            let texture_type = TextureType::from_name(fields[3]).ok_or_else(|| LoadError::UnknownTextureType { line: line_number, name: fields[3].to_string() })?;
+            let density: f32 = parse_number(line_number, fields[4])?;
+            let penetration_resistance: f32 = parse_number(line_number, fields[5])?;
+            let mut damage_resistances = [0.0; 3];
+            for damage_type in [DamageType::Kinetic, DamageType::Energy, DamageType::Explosive] {
+                damage_resistances[damage_type.index()] = parse_number(line_number, fields[6 + damage_type.index()])?;
+            }
+            let module = match fields[9] {
+                Self::NO_MODULE => None,
+                name => Some(ModuleType::from_name(name).ok_or_else(|| LoadError::UnknownModuleType { line: line_number, name: name.to_string() })?),
+            };
+            let module_output: f32 = parse_number(line_number, fields[10])?;
+            let power_draw: f32 = parse_number(line_number, fields[11])?;
+            let is_finite = [max_life, density, penetration_resistance, module_output, power_draw].iter().chain(damage_resistances.iter()).all(|value| value.is_finite());
+            if !is_finite || max_life <= 0.0 || density <= 0.0 || penetration_resistance < 0.0 || damage_resistances.iter().any(|resistance| *resistance > 1.0) || module_output < 0.0 || power_draw < 0.0 {
+                return Err(LoadError::InvalidDefinition { line: line_number, content: line.to_string() });
+            }
+            if result.contains(id) {
+                return Err(LoadError::DuplicateVoxelID { line: line_number, id: id.0 });
+            }
+
+            result.descriptors.insert(id, VoxelDescriptor {
+                name: fields[1].to_string(),
+                max_life: max_life,
+                texture_type: texture_type,
+                density: density,
+                penetration_resistance: penetration_resistance,
+                damage_resistances: damage_resistances,
+                module: module,
+                module_output: module_output,
+                power_draw: power_draw,
+            });
+            result.ids.push(id);
         }
+        result.ids.sort();
+        result.voxel_masses = VoxelMasses(Rc::new(result.descriptors.iter().map(|(id, descriptor)| (*id, descriptor.density * VOXEL_VOLUME)).collect()));
+        Ok(result)
+    }
+
+    pub fn contains(&self, id: VoxelID) -> bool {
+        self.descriptors.contains_key(&id)
+    }
+
+    // Sorted by increasing ID.
+    pub fn ids(&self) -> &Vec<VoxelID> {
+        &self.ids
     }
 
     pub fn get_descriptor(&self, id: VoxelID) -> &VoxelDescriptor {
-        &self.descriptors[id as usize]
+        &self.descriptors[&id]
+    }
+
+    pub fn voxel_masses(&self) -> VoxelMasses {
+        self.voxel_masses.clone()
     }
 
     pub fn create_voxel(&self, id: VoxelID) -> Voxel {
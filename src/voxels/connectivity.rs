@@ -0,0 +1,208 @@
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::collections::VecDeque;
+
+use crate::maths::vector::Vect3i;
+use super::structure::Structure;
+use super::voxel::VoxelID;
+
+// Which voxels around a voxel hold to it.
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Neighbourhood {
+    Faces, // 6 neighbours
+    FacesAndEdges, // 18 neighbours
+    FacesEdgesAndCorners, // 26 neighbours
+}
+
+impl Neighbourhood {
+    pub fn offsets(&self) -> Vec<Vect3i> {
+        let max_non_zero = match self {
+            Self::Faces => 1,
+            Self::FacesAndEdges => 2,
+            Self::FacesEdgesAndCorners => 3,
+        };
+        let mut result = vec![];
+        for z in -1..2 {
+            for y in -1..2 {
+                for x in -1..2 {
+                    let non_zero = [x, y, z].iter().filter(|value| **value != 0).count();
+                    if non_zero > 0 && non_zero <= max_non_zero {
+                        result.push(Vect3i::new([x, y, z]));
+                    }
+                }
+            }
+        }
+        result
+    }
+}
+
+// How to choose the piece which stays the original body when a structure is cut.
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MainComponentRule {
+    Origin, // The piece holding the voxel on coordinates zero, if still there.
+    CoreVoxel, // The heaviest piece holding a ship core.
+    Mass, // The heaviest piece.
+}
+
+pub struct Component {
+    pub coords: Vec<Vect3i>, // In no particular order.
+    pub mass: f32,
+    pub has_core: bool,
+}
+
+impl Component {
+    pub fn contains(&self, coords: Vect3i) -> bool {
+        self.coords.contains(&coords)
+    }
+}
+
+// None when no component matches the rule.
+pub fn main_component(components: &[Component], rule: MainComponentRule) -> Option<usize> {
+    let heaviest = |candidates: &mut dyn Iterator<Item = (usize, &Component)>| {
+        candidates.max_by(|a, b| a.1.mass.total_cmp(&b.1.mass)).map(|(index, _)| index)
+    };
+    match rule {
+        MainComponentRule::Origin => components.iter().position(|component| component.contains(Vect3i::zero())),
+        MainComponentRule::CoreVoxel => heaviest(&mut components.iter().enumerate().filter(|(_, component)| component.has_core)),
+        MainComponentRule::Mass => heaviest(&mut components.iter().enumerate()),
+    }
+}
+
+impl Structure {
+    // Every set of voxels holding together.
+    pub fn components(&self, neighbourhood: Neighbourhood) -> Vec<Component> {
+        let offsets = neighbourhood.offsets();
+        let mut explored_coords: HashSet<Vect3i> = HashSet::new();
+        let mut result = vec![];
+        self.for_each_voxel(|coords, _voxel| {
+            if explored_coords.contains(&coords) {
+                return;
+            }
+            let mut component_coords = vec![coords];
+            let mut coords_to_explore: VecDeque<Vect3i> = VecDeque::from([coords]);
+            explored_coords.insert(coords);
+            while let Some(coords) = coords_to_explore.pop_front() {
+                for offset in &offsets {
+                    let close_coords = coords + *offset;
+                    if self.has_voxel_on_coords(close_coords) && explored_coords.insert(close_coords) {
+                        component_coords.push(close_coords);
+                        coords_to_explore.push_back(close_coords);
+                    }
+                }
+            }
+            result.push(self.component(component_coords));
+        });
+        result
+    }
+
+    // Components holding the given voxels, typically the neighbours of destroyed voxels.
+    // None when they all hold together, which is found without exploring the whole structure.
+    pub fn split_components(&self, coords_to_check: &[Vect3i], neighbourhood: Neighbourhood) -> Option<Vec<Component>> {
+        let offsets = neighbourhood.offsets();
+        let mut coords_to_reach: HashSet<Vect3i> = coords_to_check.iter().copied().filter(|coords| self.has_voxel_on_coords(*coords)).collect();
+        let mut jointed_coords: Vec<HashSet<Vect3i>> = vec![];
+        while let Some(first_coords) = coords_to_reach.iter().next().copied() {
+            let mut coords_to_explore: VecDeque<Vect3i> = Default::default();
+            let mut explored_coords: HashSet<Vect3i> = Default::default();
+            coords_to_explore.push_back(first_coords);
+            coords_to_reach.remove(&first_coords);
+            explored_coords.insert(first_coords);
+            // The first component is only explored until it reaches every voxel to check.
+            while !coords_to_explore.is_empty() && (!coords_to_reach.is_empty() || !jointed_coords.is_empty()) {
+                let coords = coords_to_explore.pop_front().unwrap();
+                for offset in &offsets {
+                    let close_coords = coords + *offset;
+                    if self.has_voxel_on_coords(close_coords) && !explored_coords.contains(&close_coords) {
+                        coords_to_reach.remove(&close_coords);
+                        coords_to_explore.push_back(close_coords);
+                        explored_coords.insert(close_coords);
+                    }
+                }
+            }
+            jointed_coords.push(explored_coords);
+        }
+        if jointed_coords.len() <= 1 {
+            return None;
+        }
+        Some(jointed_coords.into_iter().map(|join| self.component(join.into_iter().collect())).collect())
+    }
+
+    // Voxels whose loss would cut their component in several pieces, in the for_each_voxel order.
+    // Using Hopcroft and Tarjan depth first search, without recursion so that big structures do not overflow the stack.
+    #[allow(dead_code)]
+    pub fn articulation_voxels(&self, neighbourhood: Neighbourhood) -> Vec<Vect3i> {
+        let offsets = neighbourhood.offsets();
+        let mut discovery_time: HashMap<Vect3i, usize> = HashMap::new();
+        let mut lowest_time: HashMap<Vect3i, usize> = HashMap::new();
+        let mut articulations: HashSet<Vect3i> = HashSet::new();
+        self.for_each_voxel(|root, _voxel| {
+            if discovery_time.contains_key(&root) {
+                return;
+            }
+            discovery_time.insert(root, discovery_time.len());
+            lowest_time.insert(root, discovery_time[&root]);
+            let mut root_children = 0;
+            // Each voxel being explored, with the index of its next neighbour to look at.
+            let mut stack: Vec<(Vect3i, usize)> = vec![(root, 0)];
+            while let Some((coords, offset_index)) = stack.last().copied() {
+                if offset_index < offsets.len() {
+                    stack.last_mut().unwrap().1 += 1;
+                    let close_coords = coords + offsets[offset_index];
+                    if !self.has_voxel_on_coords(close_coords) {
+                        continue;
+                    }
+                    match discovery_time.get(&close_coords).copied() {
+                        Some(time) => {
+                            let lowest = lowest_time[&coords].min(time);
+                            lowest_time.insert(coords, lowest);
+                        },
+                        None => {
+                            let time = discovery_time.len();
+                            discovery_time.insert(close_coords, time);
+                            lowest_time.insert(close_coords, time);
+                            stack.push((close_coords, 0));
+                        },
+                    }
+                } else {
+                    stack.pop();
+                    if let Some((parent, _)) = stack.last().copied() {
+                        let lowest = lowest_time[&parent].min(lowest_time[&coords]);
+                        lowest_time.insert(parent, lowest);
+                        if parent == root {
+                            root_children += 1;
+                        } else if lowest_time[&coords] >= discovery_time[&parent] {
+                            articulations.insert(parent);
+                        }
+                    }
+                }
+            }
+            if root_children > 1 {
+                articulations.insert(root);
+            }
+        });
+
+        let mut result = vec![];
+        self.for_each_voxel(|coords, _voxel| {
+            if articulations.contains(&coords) {
+                result.push(coords);
+            }
+        });
+        result
+    }
+
+    fn component(&self, coords: Vec<Vect3i>) -> Component {
+        let mut mass = 0.0;
+        let mut has_core = false;
+        for voxel in coords.iter().map(|coords| self.get_voxel(*coords).unwrap()) {
+            mass += self.voxel_mass(&voxel);
+            has_core |= voxel.id == VoxelID::SHIP_CORE;
+        }
+        Component {
+            coords,
+            mass,
+            has_core,
+        }
+    }
+}
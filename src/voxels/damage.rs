@@ -0,0 +1,90 @@
+use crate::maths::boxes::Box3i;
+use crate::maths::segment::Segm3f;
+use crate::maths::vector::Vect3f;
+use crate::maths::vector::Vect3i;
+use super::catalog::VoxelCatalog;
+use super::structure::Structure;
+use super::voxel::DamageType;
+
+// Damage to every voxel in a sphere, as explosions do.
+#[derive(Clone, Copy)]
+pub struct AreaDamage {
+    pub center: Vect3f,
+    pub radius: f32,
+    pub damage: f32, // At the center, going down linearly to zero on the sphere.
+    pub damage_type: DamageType,
+    pub transmission: f32, // Share of the damage going through each voxel in the way, zero when they fully shield.
+}
+
+impl AreaDamage {
+    // Before occlusion and resistances.
+    pub fn damage_at(&self, point: Vect3f) -> f32 {
+        let distance = (point - self.center).length();
+        if distance >= self.radius {
+            return 0.0;
+        }
+        self.damage * (1.0 - distance / self.radius)
+    }
+}
+
+impl Structure {
+    // The damage reaching each voxel is decided before any voxel is damaged, so that the order voxels are visited
+    // in does not matter. Voxels in the way are the ones crossed by the segment from the center to the voxel center,
+    // the one holding the center does not count. Each voxel then takes the damage left after its type resistance.
+    // Returns the damaged voxels, in the for_each_voxel order.
+    pub fn apply_area_damage(&mut self, catalog: &VoxelCatalog, area_damage: &AreaDamage) -> Vec<Vect3i> {
+        let corner_coords = |sign: f32| {
+            let corner = area_damage.center + Vect3f::all(sign * area_damage.radius);
+            Vect3i::new([corner[0].round() as i32, corner[1].round() as i32, corner[2].round() as i32])
+        };
+        let sphere_box = Box3i::from_min_max(corner_coords(-1.0), corner_coords(1.0));
+        let voxel_box = self.voxel_box();
+        if !sphere_box.intersects(voxel_box) {
+            return vec![];
+        }
+        let mut min = Vect3i::zero();
+        let mut max = Vect3i::zero();
+        for axis in 0..3 {
+            min[axis] = sphere_box.min()[axis].max(voxel_box.min()[axis]);
+            max[axis] = sphere_box.max()[axis].min(voxel_box.max()[axis]);
+        }
+
+        let mut damages = vec![];
+        for z in min[2]..max[2] + 1 {
+            for y in min[1]..max[1] + 1 {
+                // Whole empty octree cells are skipped along x.
+                let mut x = min[0];
+                while x <= max[0] {
+                    let coords = Vect3i::new([x, y, z]);
+                    if let Some(empty_box) = self.octtree().empty_box(coords) {
+                        x = empty_box.max()[0] + 1;
+                        continue;
+                    }
+                    x += 1;
+                    if !self.has_voxel_on_coords(coords) {
+                        continue;
+                    }
+                    let voxel_center = Vect3f::new([coords[0] as f32, coords[1] as f32, coords[2] as f32]);
+                    let mut damage = area_damage.damage_at(voxel_center);
+                    if damage <= 0.0 {
+                        continue;
+                    }
+                    for hit in self.ray_cast_all(Segm3f::new(area_damage.center, voxel_center)) {
+                        if hit.coords != coords && hit.face != Vect3i::zero() {
+                            damage *= area_damage.transmission;
+                        }
+                    }
+                    if damage > 0.0 {
+                        damages.push((coords, damage));
+                    }
+                }
+            }
+        }
+
+        for (coords, damage) in &damages {
+            let descriptor = catalog.get_descriptor(self.get_voxel(*coords).unwrap().id);
+            self.damage_voxel(*coords, descriptor.effective_damage(*damage, area_damage.damage_type));
+        }
+        damages.into_iter().map(|(coords, _)| coords).collect()
+    }
+}
@@ -0,0 +1,65 @@
+#[derive(Debug)]
+pub enum LoadError {
+    Io { file_name: String, error: std::io::Error },
+    UnsupportedVersion { version: u32 },
+    InvalidHeader { line: usize, content: String },
+    InvalidNumber { line: usize, value: String },
+    InvalidExtent { line: usize },
+    InvalidVoxelID { line: usize, id: u16 },
+    ExtentMismatch { line: usize, axis: char, expected: usize, found: usize },
+    TruncatedSlice { line: usize, z: i32 },
+    InvalidMagic,
+    CorruptedBinary { offset: usize, reason: &'static str },
+    InvalidDefinition { line: usize, content: String },
+    UnknownTextureType { line: usize, name: String },
+    UnknownModuleType { line: usize, name: String },
+    DuplicateVoxelID { line: usize, id: u16 },
+    DuplicatePaletteIndex { line: usize, index: u8 },
+    UnmappedPaletteIndex { index: u8 },
+}
+
+impl std::fmt::Display for LoadError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { file_name, error } => write!(f, "unable to read '{file_name}': {error}"),
+            Self::UnsupportedVersion { version } => write!(f, "unsupported format version {version}"),
+            Self::InvalidHeader { line, content } => write!(f, "line {line}: invalid header '{content}'"),
+            Self::InvalidNumber { line, value } => write!(f, "line {line}: '{value}' is not a valid number"),
+            Self::InvalidExtent { line } => write!(f, "line {line}: extent minimum is greater than its maximum"),
+            Self::InvalidVoxelID { line, id } => write!(f, "line {line}: unknown voxel ID {id}"),
+            Self::ExtentMismatch { line, axis, expected, found } => write!(f, "line {line}: expected {expected} voxels along {axis}, found {found}"),
+            Self::TruncatedSlice { line, z } => write!(f, "line {line}: missing slice for z = {z}"),
+            Self::InvalidMagic => write!(f, "not a binary structure file"),
+            Self::CorruptedBinary { offset, reason } => write!(f, "byte {offset}: {reason}"),
+            Self::InvalidDefinition { line, content } => write!(f, "line {line}: invalid voxel definition '{content}'"),
+            Self::UnknownTextureType { line, name } => write!(f, "line {line}: unknown texture type '{name}'"),
+            Self::UnknownModuleType { line, name } => write!(f, "line {line}: unknown module type '{name}'"),
+            Self::DuplicateVoxelID { line, id } => write!(f, "line {line}: voxel ID {id} is already defined"),
+            Self::DuplicatePaletteIndex { line, index } => write!(f, "line {line}: palette index {index} is already mapped"),
+            Self::UnmappedPaletteIndex { index } => write!(f, "palette index {index} is not mapped to any voxel type"),
+        }
+    }
+}
+
+impl std::error::Error for LoadError {}
+
+#[derive(Debug)]
+pub enum SaveError {
+    Io { file_name: String, error: std::io::Error },
+    UnmappedVoxelID { id: u16 },
+    TooBig { axis: char, size: i32, max_size: i32 },
+    TooManyCells { max_cell_count: usize },
+}
+
+impl std::fmt::Display for SaveError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Io { file_name, error } => write!(f, "unable to write '{file_name}': {error}"),
+            Self::UnmappedVoxelID { id } => write!(f, "voxel ID {id} is not mapped to any palette index"),
+            Self::TooBig { axis, size, max_size } => write!(f, "size {size} along {axis} is over the maximum of {max_size}"),
+            Self::TooManyCells { max_cell_count } => write!(f, "bounding box holds more than {max_cell_count} cells"),
+        }
+    }
+}
+
+impl std::error::Error for SaveError {}
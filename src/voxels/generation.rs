@@ -0,0 +1,137 @@
+use std::collections::HashSet;
+
+use crate::maths::random::fractal_noise;
+use crate::maths::random::Random;
+use crate::maths::vector::Vect3f;
+use crate::maths::vector::Vect3i;
+use super::catalog::VoxelCatalog;
+use super::connectivity::main_component;
+use super::connectivity::MainComponentRule;
+use super::connectivity::Neighbourhood;
+use super::structure::Structure;
+use super::transform::VoxelTransform;
+use super::voxel::VoxelID;
+
+// Everything here only depends on the seed: the same seed always gives the same structure.
+
+pub struct AsteroidParameters {
+    pub radius: f32,
+    pub roughness: f32, // Height of the bumps relative to the radius, zero gives a ball.
+    pub layers: Vec<VoxelID>, // Voxel types from the surface to the center, with noisy boundaries.
+}
+
+pub struct DerelictParameters {
+    pub part_count: usize,
+    pub missing_voxel_ratio: f32, // Share of the voxels blown away.
+    pub damaged_voxel_ratio: f32, // Share of the remaining voxels which lost some life.
+}
+
+// The surface height only depends on the direction from the center, so the asteroid has no overhang.
+pub fn generate_asteroid(catalog: &VoxelCatalog, parameters: &AsteroidParameters, seed: u64) -> Structure {
+    const SHAPE_OCTAVE_COUNT: u32 = 3;
+    const LAYER_OCTAVE_COUNT: u32 = 2;
+    const LAYER_TURBULENCE: f32 = 0.3;
+    const BUMP_SCALE: f32 = 3.0; // Around three bumps across the asteroid, whatever its size.
+    assert!(!parameters.layers.is_empty());
+
+    let mut random = Random::new(seed);
+    let shape_seed = random.next_u64();
+    let layer_seed = random.next_u64();
+    let layer_scale = 3.0 / parameters.radius.max(1.0);
+
+    let max_radius = (parameters.radius * (1.0 + parameters.roughness)).ceil() as i32;
+    let mut result = Structure::new_empty(catalog);
+    for z in -max_radius..max_radius + 1 {
+        for y in -max_radius..max_radius + 1 {
+            for x in -max_radius..max_radius + 1 {
+                let position = Vect3f::new([x as f32, y as f32, z as f32]);
+                let distance = position.length();
+                let direction = if distance > 0.0 { position / distance } else { Vect3f::zero() };
+                let bump = fractal_noise(shape_seed, direction * BUMP_SCALE, SHAPE_OCTAVE_COUNT) * 2.0 - 1.0;
+                let surface_distance = parameters.radius * (1.0 + parameters.roughness * bump);
+                if distance > surface_distance {
+                    continue;
+                }
+
+                let turbulence = (fractal_noise(layer_seed, position * layer_scale, LAYER_OCTAVE_COUNT) * 2.0 - 1.0) * LAYER_TURBULENCE;
+                let depth = (1.0 - distance / surface_distance + turbulence).clamp(0.0, 0.999);
+                let layer = (depth * parameters.layers.len() as f32) as usize;
+                result.add_voxel(Vect3i::new([x, y, z]), catalog.create_voxel(parameters.layers[layer]));
+            }
+        }
+    }
+    keep_main_component(&mut result);
+    result.recalculate_box();
+    result
+}
+
+// Parts are randomly turned, then stuck one after the other next to the voxels already there.
+// The hulk is then damaged, and only its biggest piece is kept.
+pub fn generate_derelict(parts: &[Structure], parameters: &DerelictParameters, seed: u64) -> Structure {
+    const ATTACH_ATTEMPT_COUNT: usize = 32;
+    assert!(!parts.is_empty());
+
+    let face_offsets = Neighbourhood::Faces.offsets();
+    let mut random = Random::new(seed);
+    let mut result = random_orientation(&parts[random.index(parts.len())], &mut random);
+    for _ in 1..parameters.part_count {
+        let part = random_orientation(&parts[random.index(parts.len())], &mut random);
+        if part.is_empty() || result.is_empty() {
+            continue;
+        }
+        let part_coords = voxel_coords(&part);
+        let result_coords = voxel_coords(&result);
+        for _ in 0..ATTACH_ATTEMPT_COUNT {
+            let anchor = result_coords[random.index(result_coords.len())] + face_offsets[random.index(face_offsets.len())];
+            let offset = anchor - part_coords[random.index(part_coords.len())];
+            if result.merge(&part, &VoxelTransform::translation(offset)).is_ok() {
+                break;
+            }
+        }
+    }
+
+    for coords in voxel_coords(&result) {
+        if random.chance(parameters.missing_voxel_ratio) {
+            result.remove_voxel(coords);
+        } else if random.chance(parameters.damaged_voxel_ratio) {
+            let mut voxel = result.get_voxel(coords).unwrap();
+            voxel.life *= random.range_f32(0.1, 1.0);
+            result.add_voxel(coords, voxel);
+        }
+    }
+    keep_main_component(&mut result);
+    result.recalculate_box();
+    result
+}
+
+fn random_orientation(structure: &Structure, random: &mut Random) -> Structure {
+    let mut transform = VoxelTransform::rotation(random.index(3), random.range_i32(0, 3))
+        .then(&VoxelTransform::rotation(random.index(3), random.range_i32(0, 3)));
+    if random.chance(0.5) {
+        transform = transform.then(&VoxelTransform::mirror(random.index(3)));
+    }
+    structure.transformed(&transform)
+}
+
+fn voxel_coords(structure: &Structure) -> Vec<Vect3i> {
+    let mut result = vec![];
+    structure.for_each_voxel(|coords, _voxel| result.push(coords));
+    result
+}
+
+fn keep_main_component(structure: &mut Structure) {
+    let components = structure.components(Neighbourhood::Faces);
+    let Some(main_index) = main_component(&components, MainComponentRule::Mass) else {
+        return;
+    };
+    let lost_coords: HashSet<Vect3i> = components.iter()
+        .enumerate()
+        .filter(|(index, _)| *index != main_index)
+        .flat_map(|(_, component)| component.coords.iter().copied())
+        .collect();
+    for coords in voxel_coords(structure) {
+        if lost_coords.contains(&coords) {
+            structure.remove_voxel(coords);
+        }
+    }
+}
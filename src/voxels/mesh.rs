@@ -0,0 +1,253 @@
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+
+use crate::maths::vector::Vect3f;
+use crate::maths::vector::Vect3i;
+use super::catalog::VoxelCatalog;
+use super::structure::Structure;
+use super::voxel::Voxel;
+use super::voxel::VoxelID;
+
+// In the cube mesh order.
+pub const FACE_NORMALS: [[i32; 3]; 6] = [[0, 0, -1], [0, 0, 1], [-1, 0, 0], [1, 0, 0], [0, -1, 0], [0, 1, 0]];
+
+#[derive(Clone, Copy)]
+pub struct Quad {
+    pub corners: [Vect3f; 4], // Counterclockwise seen from outside, the first one has the lowest coordinates.
+    pub normal: Vect3i,
+    pub voxel_id: VoxelID,
+    pub damage: f32, // From 0 for intact voxels to 1, by steps so that faces of slightly damaged voxels still merge.
+}
+
+// Corners of the faces of a rectangle of voxels, going from coords along the two other axes, in the axes order.
+// The first corner is the one with the lowest coordinates, whatever the normal.
+fn face_corners(coords: Vect3i, normal: Vect3i, first_size: i32, second_size: i32) -> [Vect3f; 4] {
+    let axis = (0..3).find(|axis| normal[*axis] != 0).unwrap();
+    let mut u = Vect3f::zero();
+    u[(axis + 1) % 3] = 1.0;
+    let mut v = Vect3f::zero();
+    v[(axis + 2) % 3] = 1.0;
+    let start = as_f32(coords) + as_f32(normal) * 0.5 - (u + v) * 0.5;
+    let u = u * first_size as f32;
+    let v = v * second_size as f32;
+    // u then v is counterclockwise seen from the positive side of the axis.
+    let mut corners = [start, start + u, start + u + v, start + v];
+    if normal[axis] < 0 {
+        corners.reverse();
+        corners.rotate_right(1);
+    }
+    corners
+}
+
+fn as_f32(coords: Vect3i) -> Vect3f {
+    Vect3f::new([coords[0] as f32, coords[1] as f32, coords[2] as f32])
+}
+
+// Surface of a structure: only faces between a voxel and empty space, without any GL resource.
+// Quads cover either single voxel faces or merged rectangles of them, both are exported the same way.
+#[derive(Clone)]
+pub struct SurfaceMesh {
+    quads: Vec<Quad>,
+}
+
+impl SurfaceMesh {
+    const DAMAGE_STEPS: f32 = 8.0;
+
+    // One quad per exposed voxel face.
+    pub fn from_structure(structure: &Structure, catalog: &VoxelCatalog) -> Self {
+        let mut quads = vec![];
+        structure.for_each_voxel(|coords, voxel| {
+            for normal in FACE_NORMALS {
+                let normal = Vect3i::new(normal);
+                if !structure.has_voxel_on_coords(coords + normal) {
+                    quads.push(Quad {
+                        corners: face_corners(coords, normal, 1, 1),
+                        normal,
+                        voxel_id: voxel.id,
+                        damage: Self::damage_step(catalog, voxel) as f32 / Self::DAMAGE_STEPS,
+                    });
+                }
+            }
+        });
+        Self { quads }
+    }
+
+    // Exposed faces merged into as few rectangles as possible, each one covering voxels with the same type and the
+    // same damage step.
+    // Each layer of voxels is meshed on its own for each face direction: faces are merged along the first of the
+    // two other axes as long as they match, then the row is extended along the second axis.
+    // Faces are gathered from the voxels first, so only the layers holding faces are meshed, each over the
+    // rectangle its faces cover.
+    pub fn greedy_from_structure(structure: &Structure, catalog: &VoxelCatalog) -> Self {
+        let face_key = |voxel: &Voxel| (voxel.id, Self::damage_step(catalog, voxel));
+        let layer_axes = |normal: Vect3i| {
+            let axis = (0..3).find(|axis| normal[*axis] != 0).unwrap();
+            (axis, (axis + 1) % 3, (axis + 2) % 3)
+        };
+
+        // By normal index and layer, the faces with their coordinates along the first and second axes.
+        let mut layers = BTreeMap::<(usize, i32), Vec<_>>::new();
+        structure.for_each_voxel(|coords, voxel| {
+            for (normal_index, normal) in FACE_NORMALS.iter().enumerate() {
+                let normal = Vect3i::new(*normal);
+                if !structure.has_voxel_on_coords(coords + normal) {
+                    let (axis, first_axis, second_axis) = layer_axes(normal);
+                    layers.entry((normal_index, coords[axis])).or_default().push((coords[first_axis], coords[second_axis], face_key(voxel)));
+                }
+            }
+        });
+
+        let mut quads = vec![];
+        for ((normal_index, layer), layer_faces) in layers {
+            let normal = Vect3i::new(FACE_NORMALS[normal_index]);
+            let (axis, first_axis, second_axis) = layer_axes(normal);
+            let first_min = layer_faces.iter().map(|face| face.0).min().unwrap();
+            let second_min = layer_faces.iter().map(|face| face.1).min().unwrap();
+            let first_size = (layer_faces.iter().map(|face| face.0).max().unwrap() - first_min + 1) as usize;
+            let second_size = (layer_faces.iter().map(|face| face.1).max().unwrap() - second_min + 1) as usize;
+            let coords_at = |first: usize, second: usize| {
+                let mut coords = Vect3i::zero();
+                coords[axis] = layer;
+                coords[first_axis] = first_min + first as i32;
+                coords[second_axis] = second_min + second as i32;
+                coords
+            };
+
+            let mut faces = vec![None; first_size * second_size];
+            for (first, second, key) in layer_faces {
+                faces[(second - second_min) as usize * first_size + (first - first_min) as usize] = Some(key);
+            }
+
+            for second in 0..second_size {
+                let mut first = 0;
+                while first < first_size {
+                    let Some(key) = faces[second * first_size + first] else {
+                        first += 1;
+                        continue;
+                    };
+                    let mut width = 1;
+                    while first + width < first_size && faces[second * first_size + first + width] == Some(key) {
+                        width += 1;
+                    }
+                    let mut height = 1;
+                    while second + height < second_size && (first..first + width).all(|f| faces[(second + height) * first_size + f] == Some(key)) {
+                        height += 1;
+                    }
+                    for merged_second in second..second + height {
+                        faces[merged_second * first_size + first..merged_second * first_size + first + width].fill(None);
+                    }
+                    quads.push(Quad {
+                        corners: face_corners(coords_at(first, second), normal, width as i32, height as i32),
+                        normal,
+                        voxel_id: key.0,
+                        damage: key.1 as f32 / Self::DAMAGE_STEPS,
+                    });
+                    first += width;
+                }
+            }
+        }
+        Self { quads }
+    }
+
+    pub fn quads(&self) -> &Vec<Quad> {
+        &self.quads
+    }
+
+    fn damage_step(catalog: &VoxelCatalog, voxel: &Voxel) -> u8 {
+        let damage = 1.0 - voxel.life / catalog.get_descriptor(voxel.id).max_life;
+        (damage * Self::DAMAGE_STEPS).ceil().clamp(0.0, Self::DAMAGE_STEPS) as u8
+    }
+
+    fn color(catalog: &VoxelCatalog, voxel_id: VoxelID) -> [u8; 3] {
+        catalog.get_descriptor(voxel_id).texture_type.fill_color()
+    }
+
+    // Quads grouped by voxel type, each group being a material of the MTL file.
+    pub fn serialize_obj(&self, catalog: &VoxelCatalog, mtl_file_name: &str) -> String {
+        let mut result = format!("# {} faces\nmtllib {}\n", self.quads.len(), mtl_file_name);
+        // Corners are on half units, doubled they can be compared exactly.
+        let mut position_indices: HashMap<Vect3i, usize> = HashMap::new();
+        let mut positions = String::new();
+        let mut faces = String::new();
+        let mut quads: Vec<&Quad> = self.quads.iter().collect();
+        quads.sort_by_key(|quad| quad.voxel_id);
+        let mut current_voxel_id = None;
+        for quad in quads {
+            if current_voxel_id != Some(quad.voxel_id) {
+                current_voxel_id = Some(quad.voxel_id);
+                faces += &format!("usemtl {}\n", catalog.get_descriptor(quad.voxel_id).name);
+            }
+            let normal_index = FACE_NORMALS.iter().position(|normal| Vect3i::new(*normal) == quad.normal).unwrap() + 1;
+            faces += "f";
+            for corner in quad.corners {
+                let key = Vect3i::new([(corner[0] * 2.0) as i32, (corner[1] * 2.0) as i32, (corner[2] * 2.0) as i32]);
+                let position_count = position_indices.len();
+                let position_index = *position_indices.entry(key).or_insert_with(|| {
+                    positions += &format!("v {} {} {}\n", corner[0], corner[1], corner[2]);
+                    position_count + 1
+                });
+                faces += &format!(" {}//{}", position_index, normal_index);
+            }
+            faces += "\n";
+        }
+        result += &positions;
+        for normal in FACE_NORMALS {
+            result += &format!("vn {} {} {}\n", normal[0], normal[1], normal[2]);
+        }
+        result + &faces
+    }
+
+    pub fn serialize_mtl(&self, catalog: &VoxelCatalog) -> String {
+        let mut voxel_ids: Vec<VoxelID> = self.quads.iter().map(|quad| quad.voxel_id).collect();
+        voxel_ids.sort();
+        voxel_ids.dedup();
+        let mut result = String::new();
+        for voxel_id in voxel_ids {
+            let color = Self::color(catalog, voxel_id);
+            result += &format!("newmtl {}\n", catalog.get_descriptor(voxel_id).name);
+            result += &format!("Kd {} {} {}\n\n", color[0] as f32 / 255.0, color[1] as f32 / 255.0, color[2] as f32 / 255.0);
+        }
+        result
+    }
+
+    // Binary little-endian, each quad with its own four vertices carrying its normal and its voxel type color.
+    pub fn serialize_ply(&self, catalog: &VoxelCatalog) -> Vec<u8> {
+        let header = format!("ply\nformat binary_little_endian 1.0\n\
+            element vertex {}\n\
+            property float x\nproperty float y\nproperty float z\n\
+            property float nx\nproperty float ny\nproperty float nz\n\
+            property uchar red\nproperty uchar green\nproperty uchar blue\n\
+            element face {}\n\
+            property list uchar uint vertex_indices\n\
+            end_header\n", self.quads.len() * 4, self.quads.len());
+        let mut result = header.into_bytes();
+        for quad in &self.quads {
+            let color = Self::color(catalog, quad.voxel_id);
+            for corner in quad.corners {
+                for value in [corner[0], corner[1], corner[2], quad.normal[0] as f32, quad.normal[1] as f32, quad.normal[2] as f32] {
+                    result.extend_from_slice(&value.to_le_bytes());
+                }
+                result.extend_from_slice(&color);
+            }
+        }
+        for index in 0..self.quads.len() as u32 {
+            result.push(4);
+            for corner_index in 0..4 {
+                result.extend_from_slice(&(index * 4 + corner_index).to_le_bytes());
+            }
+        }
+        result
+    }
+
+    // The MTL file is written next to the OBJ file, with the same name.
+    pub fn write_obj_file(&self, catalog: &VoxelCatalog, file_name: &str) -> std::io::Result<()> {
+        let mtl_path = std::path::Path::new(file_name).with_extension("mtl");
+        let mtl_file_name = mtl_path.file_name().unwrap().to_string_lossy();
+        std::fs::write(file_name, self.serialize_obj(catalog, &mtl_file_name))?;
+        std::fs::write(&mtl_path, self.serialize_mtl(catalog))
+    }
+
+    pub fn write_ply_file(&self, catalog: &VoxelCatalog, file_name: &str) -> std::io::Result<()> {
+        std::fs::write(file_name, self.serialize_ply(catalog))
+    }
+}
@@ -1,5 +1,13 @@
 pub mod catalog;
+pub mod connectivity;
+pub mod damage;
+pub mod error;
+pub mod generation;
+pub mod mesh;
+pub mod storage;
 pub mod structure;
+pub mod transform;
+pub mod vox;
 pub mod voxel;
 
 mod octtree;
@@ -88,6 +88,15 @@ impl Octcell {
         }
     }
 
+    // A cell of box [min, max] holds the voxels from min + 1 to max.
+    pub fn empty_box(&self, coord: Vect3i) -> Option<Box3i> {
+        match self.state {
+            CellState::Empty => Some(Box3i::from_min_max(self.oct.min() + Vect3i::all(1), self.oct.max())),
+            CellState::Full => None,
+            CellState::Partitioned => self.get_child(coord).empty_box(coord),
+        }
+    }
+
     fn partition(&mut self) {
         assert!(self.size() / 2 * 2 == self.size());
         let subdivisions = self.oct.subdivide();
@@ -160,21 +169,40 @@ impl Octcell {
     }
 }
 
+// The root grows when a voxel is added out of it, and shrinks back (never under its initial size)
+// when its outer parts are empty.
 #[derive(Clone)]
 pub struct Octtree {
-    root: Octcell
+    root: Octcell,
+    min_size: i32,
 }
 
 #[allow(dead_code)]
 impl Octtree {
     pub fn new(size: i32) -> Self {
         Self {
-            root: Octcell::new(Box3i::from_min_max(Vect3i::new([-size, -size, -size]), Vect3i::new([size, size, size])), CellState::Empty)
+            root: Self::root_cell(size, CellState::Empty),
+            min_size: size,
         }
     }
 
+    fn root_cell(size: i32, state: CellState) -> Octcell {
+        Octcell::new(Box3i::from_min_max(Vect3i::new([-size, -size, -size]), Vect3i::new([size, size, size])), state)
+    }
+
+    // Half the root width.
+    pub fn size(&self) -> i32 {
+        self.root.size() / 2
+    }
+
+    // As each cell, root of box [min, max] holds the voxels from min + 1 to max.
+    fn holds(&self, coord: Vect3i) -> bool {
+        let root_box = self.root.get_box();
+        Box3i::from_min_max(root_box.min() + Vect3i::all(1), root_box.max()).contains(coord)
+    }
+
     pub fn has_voxel(&self, coord: Vect3i) -> bool {
-        self.root.has_voxel(coord)
+        self.holds(coord) && self.root.has_voxel(coord)
     }
 
     pub fn has_box(&self, voxel_box: Box3i) -> bool {
@@ -186,12 +214,93 @@ impl Octtree {
         self.root.walk(f)
     }
 
+    // Voxel coordinates of the biggest empty cell around the coordinate, if it is empty.
+    pub fn empty_box(&self, coord: Vect3i) -> Option<Box3i> {
+        if !self.holds(coord) {
+            return None;
+        }
+        self.root.empty_box(coord)
+    }
+
     pub fn add_voxel(&mut self, coord: Vect3i) {
-        assert!(self.root.get_box().contains(coord));
+        while !self.holds(coord) {
+            self.grow();
+        }
         self.root.add_voxel(coord);
     }
 
     pub fn remove_voxel(&mut self, coord: Vect3i) {
+        if !self.holds(coord) {
+            return;
+        }
         self.root.remove_voxel(coord);
+        self.shrink();
+    }
+
+    // Doubles the root size: each octant of the old root becomes the inner part of the matching new octant.
+    fn grow(&mut self) {
+        let size = self.size();
+        let mut old_root = std::mem::replace(&mut self.root, Self::root_cell(size * 2, CellState::Empty));
+        if old_root.state == CellState::Empty {
+            return;
+        }
+        if old_root.state == CellState::Full {
+            old_root.partition();
+        }
+        self.root.partition();
+        let old_children = old_root.children.take().unwrap();
+        let new_children = self.root.children.as_mut().unwrap();
+        for (index, old_child) in old_children.into_iter().enumerate() {
+            if old_child.state != CellState::Empty {
+                new_children[index].partition();
+                new_children[index].children.as_mut().unwrap()[Self::inner_index(index)] = old_child;
+            }
+        }
+    }
+
+    // Halves the root size as long as only the inner part of each octant has voxels.
+    fn shrink(&mut self) {
+        while self.size() > self.min_size {
+            let size = self.size();
+            match self.root.state {
+                CellState::Empty => {
+                    self.root = Self::root_cell(self.min_size, CellState::Empty);
+                    return;
+                },
+                CellState::Full => return,
+                CellState::Partitioned => (),
+            }
+            let can_shrink = self.root.children.as_ref().unwrap().iter().enumerate().all(|(index, child)| {
+                match child.state {
+                    CellState::Empty => true,
+                    CellState::Full => false,
+                    CellState::Partitioned => child.children.as_ref().unwrap().iter().enumerate().all(|(sub_index, sub_child)| {
+                        sub_index == Self::inner_index(index) || sub_child.state == CellState::Empty
+                    }),
+                }
+            });
+            if !can_shrink {
+                return;
+            }
+
+            let old_root = std::mem::replace(&mut self.root, Self::root_cell(size / 2, CellState::Empty));
+            self.root.partition();
+            let new_children = self.root.children.as_mut().unwrap();
+            for (index, old_child) in old_root.children.unwrap().into_iter().enumerate() {
+                if let Some(old_sub_children) = old_child.children {
+                    new_children[index] = old_sub_children.into_iter().nth(Self::inner_index(index)).unwrap();
+                }
+            }
+            if new_children.iter().all(|child| child.state == CellState::Empty) {
+                self.root = Self::root_cell(size / 2, CellState::Empty);
+            } else if new_children.iter().all(|child| child.state == CellState::Full) {
+                self.root = Self::root_cell(size / 2, CellState::Full);
+            }
+        }
+    }
+
+    // Index of the child of an octant which is next to the center of its parent.
+    fn inner_index(octant_index: usize) -> usize {
+        octant_index ^ 7
     }
 }
@@ -0,0 +1,266 @@
+use std::collections::HashMap;
+
+use crate::maths::boxes::Box3i;
+use crate::maths::vector::Vect3i;
+use super::voxel::Voxel;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum StorageLayout {
+    // One cell per coordinates of the bounding box, reallocated when the box grows.
+    Dense,
+    // Fixed-size chunks, only allocated where there are voxels.
+    Chunked,
+}
+
+// Voxels of a structure by coordinates. The structure keeps its own bounding box, mass and octree.
+#[derive(Clone)]
+pub enum VoxelStorage {
+    Dense(DenseStorage),
+    Chunked(ChunkedStorage),
+}
+
+impl VoxelStorage {
+    // The box is only a hint of where voxels will be.
+    pub fn new(layout: StorageLayout, voxel_box: Box3i) -> Self {
+        match layout {
+            StorageLayout::Dense => Self::Dense(DenseStorage::new(voxel_box)),
+            StorageLayout::Chunked => Self::Chunked(ChunkedStorage::new()),
+        }
+    }
+
+    pub fn layout(&self) -> StorageLayout {
+        match self {
+            Self::Dense(_) => StorageLayout::Dense,
+            Self::Chunked(_) => StorageLayout::Chunked,
+        }
+    }
+
+    pub fn get(&self, coords: Vect3i) -> Option<Voxel> {
+        match self {
+            Self::Dense(storage) => storage.get(coords),
+            Self::Chunked(storage) => storage.get(coords),
+        }
+    }
+
+    pub fn get_mut(&mut self, coords: Vect3i) -> Option<&mut Voxel> {
+        match self {
+            Self::Dense(storage) => storage.get_mut(coords),
+            Self::Chunked(storage) => storage.get_mut(coords),
+        }
+    }
+
+    // Returns the replaced voxel.
+    pub fn set(&mut self, coords: Vect3i, voxel: Option<Voxel>) -> Option<Voxel> {
+        match self {
+            Self::Dense(storage) => storage.set(coords, voxel),
+            Self::Chunked(storage) => storage.set(coords, voxel),
+        }
+    }
+
+    pub fn voxel_count(&self) -> usize {
+        match self {
+            Self::Dense(storage) => storage.voxel_count,
+            Self::Chunked(storage) => storage.voxel_count,
+        }
+    }
+
+    // Only visits the stored voxels, in z, y, x order like a walk through the box.
+    pub fn for_each<F: FnMut(Vect3i, &Voxel)>(&self, mut f: F) {
+        match self {
+            Self::Dense(storage) => {
+                for (index, voxel) in storage.data.iter().enumerate() {
+                    if let Some(voxel) = voxel {
+                        f(storage.coords(index), voxel);
+                    }
+                }
+            },
+            Self::Chunked(storage) => {
+                for (coords, voxel) in storage.sorted_voxels() {
+                    f(coords, &voxel);
+                }
+            },
+        }
+    }
+
+    // Gives back memory once every voxel is in the box.
+    pub fn fit(&mut self, voxel_box: Box3i) {
+        match self {
+            Self::Dense(storage) => storage.resize(voxel_box),
+            Self::Chunked(storage) => storage.chunks.shrink_to_fit(),
+        }
+    }
+
+    // Approximation in bytes, for profiling.
+    pub fn memory_size(&self) -> usize {
+        match self {
+            Self::Dense(storage) => storage.data.capacity() * std::mem::size_of::<Option<Voxel>>(),
+            Self::Chunked(storage) => {
+                let entry_size = std::mem::size_of::<Vect3i>() + std::mem::size_of::<Box<Chunk>>();
+                storage.chunks.capacity() * entry_size + storage.chunks.len() * std::mem::size_of::<Chunk>()
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct DenseStorage {
+    voxel_box: Box3i,
+    data: Vec<Option<Voxel>>,
+    voxel_count: usize,
+}
+
+impl DenseStorage {
+    fn new(voxel_box: Box3i) -> Self {
+        let voxel_box = if voxel_box.is_empty() { Box3i::zero() } else { voxel_box };
+        Self {
+            data: vec![None; Self::cell_count(&voxel_box)],
+            voxel_box,
+            voxel_count: 0,
+        }
+    }
+
+    fn cell_count(voxel_box: &Box3i) -> usize {
+        let extent = voxel_box.extent() + Vect3i::all(1);
+        (extent[0] * extent[1] * extent[2]).try_into().unwrap()
+    }
+
+    fn index(voxel_box: &Box3i, coords: Vect3i) -> usize {
+        let extent = voxel_box.extent() + Vect3i::all(1);
+        let coords_in_data = coords - voxel_box.min();
+        (coords_in_data[2] * (extent[0] * extent[1]) + coords_in_data[1] * extent[0] + coords_in_data[0]).try_into().unwrap()
+    }
+
+    fn coords(&self, index: usize) -> Vect3i {
+        let extent = self.voxel_box.extent() + Vect3i::all(1);
+        let index = index as i32;
+        Vect3i::new([index % extent[0], index / extent[0] % extent[1], index / (extent[0] * extent[1])]) + self.voxel_box.min()
+    }
+
+    fn get(&self, coords: Vect3i) -> Option<Voxel> {
+        if !self.voxel_box.contains(coords) {
+            return None;
+        }
+        self.data[Self::index(&self.voxel_box, coords)]
+    }
+
+    fn get_mut(&mut self, coords: Vect3i) -> Option<&mut Voxel> {
+        if !self.voxel_box.contains(coords) {
+            return None;
+        }
+        self.data[Self::index(&self.voxel_box, coords)].as_mut()
+    }
+
+    fn set(&mut self, coords: Vect3i, voxel: Option<Voxel>) -> Option<Voxel> {
+        if !self.voxel_box.contains(coords) {
+            if voxel.is_none() {
+                return None;
+            }
+            let mut new_box = self.voxel_box.clone();
+            new_box.add(coords);
+            self.resize(new_box);
+        }
+        let index = Self::index(&self.voxel_box, coords);
+        let old_voxel = std::mem::replace(&mut self.data[index], voxel);
+        self.voxel_count = self.voxel_count.wrapping_add_signed(voxel.is_some() as isize - old_voxel.is_some() as isize);
+        old_voxel
+    }
+
+    // Voxels out of the new box are lost.
+    fn resize(&mut self, new_box: Box3i) {
+        let new_box = if new_box.is_empty() { Box3i::zero() } else { new_box };
+        let mut new_data: Vec<Option<Voxel>> = vec![None; Self::cell_count(&new_box)];
+        let mut voxel_count = 0;
+        for z in self.voxel_box.min()[2]..self.voxel_box.max()[2] + 1 {
+            for y in self.voxel_box.min()[1]..self.voxel_box.max()[1] + 1 {
+                for x in self.voxel_box.min()[0]..self.voxel_box.max()[0] + 1 {
+                    let coords = Vect3i::new([x, y, z]);
+                    let voxel = self.data[Self::index(&self.voxel_box, coords)];
+                    if voxel.is_some() && new_box.contains(coords) {
+                        new_data[Self::index(&new_box, coords)] = voxel;
+                        voxel_count += 1;
+                    }
+                }
+            }
+        }
+        self.voxel_box = new_box;
+        self.data = new_data;
+        self.voxel_count = voxel_count;
+    }
+}
+
+const CHUNK_SIZE: i32 = 8;
+const CHUNK_CELL_COUNT: usize = (CHUNK_SIZE * CHUNK_SIZE * CHUNK_SIZE) as usize;
+
+#[derive(Clone)]
+struct Chunk {
+    data: [Option<Voxel>; CHUNK_CELL_COUNT],
+    voxel_count: usize,
+}
+
+#[derive(Clone)]
+pub struct ChunkedStorage {
+    // By chunk coordinates: chunk c holds the voxels from c * CHUNK_SIZE to (c + 1) * CHUNK_SIZE - 1.
+    chunks: HashMap<Vect3i, Box<Chunk>>,
+    voxel_count: usize,
+}
+
+impl ChunkedStorage {
+    fn new() -> Self {
+        Self {
+            chunks: HashMap::new(),
+            voxel_count: 0,
+        }
+    }
+
+    fn chunk_coords_and_index(coords: Vect3i) -> (Vect3i, usize) {
+        let mut chunk_coords = Vect3i::zero();
+        let mut index = 0;
+        for axis in (0..3).rev() {
+            chunk_coords[axis] = coords[axis].div_euclid(CHUNK_SIZE);
+            index = index * CHUNK_SIZE + coords[axis].rem_euclid(CHUNK_SIZE);
+        }
+        (chunk_coords, index as usize)
+    }
+
+    // Chunks are not ordered in the map, so their voxels are sorted afterwards.
+    fn sorted_voxels(&self) -> Vec<(Vect3i, Voxel)> {
+        let mut result = Vec::with_capacity(self.voxel_count);
+        for (chunk_coords, chunk) in &self.chunks {
+            for (index, voxel) in chunk.data.iter().enumerate() {
+                if let Some(voxel) = voxel {
+                    let index = index as i32;
+                    let coords_in_chunk = Vect3i::new([index % CHUNK_SIZE, index / CHUNK_SIZE % CHUNK_SIZE, index / (CHUNK_SIZE * CHUNK_SIZE)]);
+                    result.push((*chunk_coords * CHUNK_SIZE + coords_in_chunk, *voxel));
+                }
+            }
+        }
+        result.sort_unstable_by_key(|(coords, _)| (coords[2], coords[1], coords[0]));
+        result
+    }
+
+    fn get(&self, coords: Vect3i) -> Option<Voxel> {
+        let (chunk_coords, index) = Self::chunk_coords_and_index(coords);
+        self.chunks.get(&chunk_coords)?.data[index]
+    }
+
+    fn get_mut(&mut self, coords: Vect3i) -> Option<&mut Voxel> {
+        let (chunk_coords, index) = Self::chunk_coords_and_index(coords);
+        self.chunks.get_mut(&chunk_coords)?.data[index].as_mut()
+    }
+
+    fn set(&mut self, coords: Vect3i, voxel: Option<Voxel>) -> Option<Voxel> {
+        let (chunk_coords, index) = Self::chunk_coords_and_index(coords);
+        if voxel.is_none() && !self.chunks.contains_key(&chunk_coords) {
+            return None;
+        }
+        let chunk = self.chunks.entry(chunk_coords).or_insert_with(|| Box::new(Chunk { data: [None; CHUNK_CELL_COUNT], voxel_count: 0 }));
+        let old_voxel = std::mem::replace(&mut chunk.data[index], voxel);
+        let count_change = voxel.is_some() as isize - old_voxel.is_some() as isize;
+        chunk.voxel_count = chunk.voxel_count.wrapping_add_signed(count_change);
+        self.voxel_count = self.voxel_count.wrapping_add_signed(count_change);
+        if chunk.voxel_count == 0 {
+            self.chunks.remove(&chunk_coords);
+        }
+        old_voxel
+    }
+}
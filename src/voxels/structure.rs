@@ -1,68 +1,112 @@
+use std::cell::OnceCell;
+
 use crate::maths::boxes::Box3f;
 use crate::maths::boxes::Box3i;
+use crate::maths::matrix::Mat3d;
+use crate::maths::matrix::Mat3f;
 use crate::maths::segment::Segm3f;
+use crate::maths::vector::Vect3d;
 use crate::maths::vector::Vect3f;
 use crate::maths::vector::Vect3i;
 use super::catalog::VoxelCatalog;
+use super::catalog::VoxelMasses;
+use super::error::LoadError;
+use super::error::SaveError;
+use super::mesh::SurfaceMesh;
 use super::octtree::Octtree;
+use super::storage::StorageLayout;
+use super::storage::VoxelStorage;
+use super::transform::VoxelTransform;
 use super::voxel::Voxel;
+use super::voxel::VoxelID;
+
+#[derive(Clone, Copy)]
+pub struct RayHit {
+    pub coords: Vect3i,
+    pub face: Vect3i, // Normal of the face the segment enters the voxel by, zero when it starts inside the voxel.
+    pub distance: f32, // From the segment start.
+    #[allow(dead_code)]
+    pub point: Vect3f, // Where the segment enters the voxel.
+}
 
 #[derive(Clone)]
 pub struct Structure {
     voxel_box: Box3i,
     octtree: Octtree,
     mass: f32, // Expressed in tonnes
-    data: Vec<Option<Voxel>>,
+    // Moments are summed in double precision: the inertia tensor subtracts big values far from the origin.
+    mass_moment: Vect3d, // Sum of voxels coordinates weighted by their mass, to keep center of mass up to date
+    mass_second_moment: Mat3d, // Sum of voxels coordinates outer products weighted by their mass, to keep inertia up to date
+    storage: VoxelStorage,
+    surface_mesh: OnceCell<SurfaceMesh>, // Built when first needed, dropped whenever a voxel changes.
+    voxel_masses: VoxelMasses,
 }
 
-const VOXEL_WEIGHT: f32 = 10.0;
-
 impl Structure {
-    pub fn new_empty() -> Self {
+    pub const DEFAULT_LAYOUT: StorageLayout = StorageLayout::Chunked;
+
+    pub fn new_empty(catalog: &VoxelCatalog) -> Self {
+        Self::with_masses(catalog.voxel_masses(), Box3i::zero(), Self::DEFAULT_LAYOUT)
+    }
+
+    // Empty, with the same voxel types as the given structure.
+    pub fn new_empty_like(other: &Self) -> Self {
+        Self::with_masses(other.voxel_masses.clone(), Box3i::zero(), other.layout())
+    }
+
+    // Voxels can still be added out of the box, it only makes it grow.
+    pub fn with_layout(catalog: &VoxelCatalog, voxel_box: Box3i, layout: StorageLayout) -> Self {
+        Self::with_masses(catalog.voxel_masses(), voxel_box, layout)
+    }
+
+    fn with_masses(voxel_masses: VoxelMasses, voxel_box: Box3i, layout: StorageLayout) -> Self {
         Self {
-            voxel_box: Box3i::zero(),
+            voxel_box: voxel_box.clone(),
             octtree: Octtree::new(16),
             mass: 0.0,
-            data: vec![None; 1],
+            mass_moment: Vect3d::zero(),
+            mass_second_moment: Mat3d::zero(),
+            storage: VoxelStorage::new(layout, voxel_box),
+            surface_mesh: OnceCell::new(),
+            voxel_masses: voxel_masses,
         }
     }
 
-    pub fn new(min_x: i32, max_x: i32, min_y: i32, max_y: i32, min_z: i32, max_z: i32, voxel: Voxel) -> Self {
-        let extent_x = max_x - min_x + 1;
-        let extent_y = max_y - min_y + 1;
-        let extent_z = max_z - min_z + 1;
-        let vec_size: usize = (extent_x * extent_y * extent_z).try_into().unwrap();
-        let mut tree = Octtree::new(16);
+    pub fn new(catalog: &VoxelCatalog, min_x: i32, max_x: i32, min_y: i32, max_y: i32, min_z: i32, max_z: i32, voxel: Voxel) -> Self {
+        let mut result = Self::empty(catalog, min_x, max_x, min_y, max_y, min_z, max_z);
         for z in min_z..max_z + 1 {
             for y in min_y..max_y + 1 {
                 for x in min_x..max_x + 1 {
-                    let coord = Vect3i::new([x, y, z]);
-                    tree.add_voxel(coord);
+                    result.add_voxel(Vect3i::new([x, y, z]), voxel);
                 }
             }
         }
+        result
+    }
 
-        Self {
-            voxel_box: Box3i::from_min_max(Vect3i::new([min_x, min_y, min_z]), Vect3i::new([max_x, max_y, max_z])),
-            octtree: tree,
-            mass: vec_size as f32 * VOXEL_WEIGHT,
-            data: vec![Some(voxel); vec_size],
-        }
+    pub fn empty(catalog: &VoxelCatalog, min_x: i32, max_x: i32, min_y: i32, max_y: i32, min_z: i32, max_z: i32) -> Self {
+        let voxel_box = Box3i::from_min_max(Vect3i::new([min_x, min_y, min_z]), Vect3i::new([max_x, max_y, max_z]));
+        Self::with_layout(catalog, voxel_box, Self::DEFAULT_LAYOUT)
     }
 
-    pub fn empty(min_x: i32, max_x: i32, min_y: i32, max_y: i32, min_z: i32, max_z: i32) -> Self {
-        let extent_x = max_x - min_x + 1;
-        let extent_y = max_y - min_y + 1;
-        let extent_z = max_z - min_z + 1;
-        let vec_size: usize = (extent_x * extent_y * extent_z).try_into().unwrap();
-        Self {
-            voxel_box: Box3i::from_min_max(Vect3i::new([min_x, min_y, min_z]), Vect3i::new([max_x, max_y, max_z])),
-            octtree: Octtree::new(16),
-            mass: vec_size as f32 * VOXEL_WEIGHT,
-            data: vec![None; vec_size],
-        }
+    pub fn layout(&self) -> StorageLayout {
+        self.storage.layout()
     }
 
+    // Same voxels, stored with another layout.
+    pub fn to_layout(&self, layout: StorageLayout) -> Self {
+        let mut result = Self::with_masses(self.voxel_masses.clone(), self.voxel_box.clone(), layout);
+        self.for_each_voxel(|coords, voxel| result.add_voxel(coords, *voxel));
+        result
+    }
+
+    // Approximation of the memory used by the voxels, in bytes.
+    pub fn storage_memory_size(&self) -> usize {
+        self.storage.memory_size()
+    }
+
+    const VERSION_HEADER: &'static str = "vors";
+    const FORMAT_VERSION: u32 = 1;
     const SEPARATOR_EXTENT: &'static str = ";";
     const SEPARATOR_X: &'static str = " ";
     const SEPARATOR_Y: &'static str = "|";
@@ -70,64 +114,262 @@ impl Structure {
 
     pub fn serialize(&self) -> String {
         let mut result =
-        self.voxel_box.min()[0].to_string() + Self::SEPARATOR_EXTENT +
+        Self::VERSION_HEADER.to_string() + " " + &Self::FORMAT_VERSION.to_string() + Self::SEPARATOR_Z +
+        &self.voxel_box.min()[0].to_string() + Self::SEPARATOR_EXTENT +
         &self.voxel_box.max()[0].to_string() + Self::SEPARATOR_EXTENT +
         &self.voxel_box.min()[1].to_string() + Self::SEPARATOR_EXTENT +
         &self.voxel_box.max()[1].to_string() + Self::SEPARATOR_EXTENT +
         &self.voxel_box.min()[2].to_string() + Self::SEPARATOR_EXTENT +
         &self.voxel_box.max()[2].to_string();
         result += Self::SEPARATOR_Z;
-        for z in self.voxel_box.min()[2]..self.voxel_box.max()[2] + 1 {
-            for y in self.voxel_box.min()[1]..self.voxel_box.max()[1] + 1 {
-                for x in self.voxel_box.min()[0]..self.voxel_box.max()[0] + 1 {
-                    let voxel = self.data[self.voxel_index(Vect3i::new([x, y, z]))];
-                    if voxel.is_some() {
-                        result += &(voxel.unwrap().id as i32).to_string();
+        // Voxels come in the same order as the cells, only the separators of the empty cells between them are written,
+        // a whole empty row or slice at once.
+        let (min, max) = (self.voxel_box.min(), self.voxel_box.max());
+        let empty_row = Self::SEPARATOR_X.repeat(Self::box_size(&self.voxel_box, 0) as usize) + Self::SEPARATOR_Y;
+        let empty_slice = empty_row.repeat(Self::box_size(&self.voxel_box, 1) as usize) + Self::SEPARATOR_Z;
+        // Writes the separators of the cells from the cursor to the given coordinates, x going up to max x + 1.
+        let mut cursor = min;
+        let mut skip_to = |result: &mut String, coords: Vect3i| {
+            if (cursor[2], cursor[1]) < (coords[2], coords[1]) {
+                *result += &Self::SEPARATOR_X.repeat((max[0] + 1 - cursor[0]) as usize);
+                *result += Self::SEPARATOR_Y;
+                cursor[0] = min[0];
+                cursor[1] += 1;
+                while cursor[2] < coords[2] {
+                    if cursor[1] > max[1] {
+                        *result += Self::SEPARATOR_Z;
+                        cursor[1] = min[1];
+                        cursor[2] += 1;
+                    } else if cursor[1] == min[1] {
+                        *result += &empty_slice;
+                        cursor[2] += 1;
+                    } else {
+                        *result += &empty_row;
+                        cursor[1] += 1;
                     }
-                    result += Self::SEPARATOR_X;
                 }
-                result += Self::SEPARATOR_Y;
+                while cursor[1] < coords[1] {
+                    *result += &empty_row;
+                    cursor[1] += 1;
+                }
             }
-            result += Self::SEPARATOR_Z;
+            *result += &Self::SEPARATOR_X.repeat((coords[0] - cursor[0]) as usize);
+            cursor[0] = coords[0];
+        };
+        let mut voxels = vec![];
+        self.for_each_voxel(|coords, voxel| voxels.push((coords, voxel.id)));
+        for (coords, id) in voxels {
+            skip_to(&mut result, coords);
+            result += &id.0.to_string();
+            skip_to(&mut result, coords + Vect3i::new([1, 0, 0]));
         }
+        skip_to(&mut result, Vect3i::new([min[0], min[1], max[2] + 1]));
         result
     }
 
-    pub fn deserialize(catalog: &VoxelCatalog, str: &str) -> Self {
-        let mut lines = str.split(Self::SEPARATOR_Z);
-        let first_line = lines.next();
-        let mut extent_lines = first_line.unwrap().split(Self::SEPARATOR_EXTENT);
-        let box_min_x: i32 = extent_lines.next().unwrap().parse().unwrap();
-        let box_max_x: i32 = extent_lines.next().unwrap().parse().unwrap();
-        let box_min_y: i32 = extent_lines.next().unwrap().parse().unwrap();
-        let box_max_y: i32 = extent_lines.next().unwrap().parse().unwrap();
-        let box_min_z: i32 = extent_lines.next().unwrap().parse().unwrap();
-        let box_max_z: i32 = extent_lines.next().unwrap().parse().unwrap();
-        assert!(extent_lines.next().is_none());
-        let mut result = Self::empty(box_min_x, box_max_x, box_min_y, box_max_y, box_min_z, box_max_z);
+    // Files written before the version header was introduced start directly with the extent line,
+    // they are read as version 0, which has the exact same content as version 1.
+    pub fn deserialize(catalog: &VoxelCatalog, str: &str) -> Result<Self, LoadError> {
+        fn parse_number<T: std::str::FromStr>(line: usize, value: &str) -> Result<T, LoadError> {
+            value.trim().parse().map_err(|_| LoadError::InvalidNumber { line: line, value: value.to_string() })
+        }
+
+        let mut lines = str.split_terminator(Self::SEPARATOR_Z).map(|line| line.trim_end_matches('\r')).enumerate().map(|(index, line)| (index + 1, line)).peekable();
+        let (first_line_number, first_line) = lines.peek().copied().unwrap_or((1, ""));
+        if first_line.starts_with(Self::VERSION_HEADER) {
+            let mut header = first_line.split_whitespace();
+            let invalid_header = || LoadError::InvalidHeader { line: first_line_number, content: first_line.to_string() };
+            if header.next() != Some(Self::VERSION_HEADER) {
+                return Err(invalid_header());
+            }
+            let version: u32 = parse_number(first_line_number, header.next().ok_or_else(invalid_header)?)?;
+            if header.next().is_some() {
+                return Err(invalid_header());
+            }
+            if version > Self::FORMAT_VERSION {
+                return Err(LoadError::UnsupportedVersion { version: version });
+            }
+            lines.next();
+        }
+
+        let (extent_line_number, extent_line) = lines.next().unwrap_or((first_line_number, ""));
+        let extent: Vec<&str> = extent_line.split(Self::SEPARATOR_EXTENT).collect();
+        if extent.len() != 6 {
+            return Err(LoadError::InvalidHeader { line: extent_line_number, content: extent_line.to_string() });
+        }
+        let box_min_x: i32 = parse_number(extent_line_number, extent[0])?;
+        let box_max_x: i32 = parse_number(extent_line_number, extent[1])?;
+        let box_min_y: i32 = parse_number(extent_line_number, extent[2])?;
+        let box_max_y: i32 = parse_number(extent_line_number, extent[3])?;
+        let box_min_z: i32 = parse_number(extent_line_number, extent[4])?;
+        let box_max_z: i32 = parse_number(extent_line_number, extent[5])?;
+        if box_min_x > box_max_x || box_min_y > box_max_y || box_min_z > box_max_z {
+            return Err(LoadError::InvalidExtent { line: extent_line_number });
+        }
+
+        let mut result = Self::empty(catalog, box_min_x, box_max_x, box_min_y, box_max_y, box_min_z, box_max_z);
         for z in box_min_z..box_max_z + 1 {
-            let mut next_column = lines.next().unwrap().split(Self::SEPARATOR_Y);
-            for y in box_min_y..box_max_y + 1 {
-                let mut next_line = next_column.next().unwrap().split(Self::SEPARATOR_X);
-                for x in box_min_x..box_max_x + 1 {
-                    let voxel_str = next_line.next().unwrap();
+            let (line_number, slice) = lines.next().ok_or(LoadError::TruncatedSlice { line: extent_line_number + (z - box_min_z) as usize + 1, z: z })?;
+            let rows: Vec<&str> = slice.split_terminator(Self::SEPARATOR_Y).collect();
+            let expected_rows = (box_max_y - box_min_y + 1) as usize;
+            if rows.len() != expected_rows {
+                return Err(LoadError::ExtentMismatch { line: line_number, axis: 'y', expected: expected_rows, found: rows.len() });
+            }
+            for (y, row) in (box_min_y..box_max_y + 1).zip(rows) {
+                let cells: Vec<&str> = row.split_terminator(Self::SEPARATOR_X).collect();
+                let expected_cells = (box_max_x - box_min_x + 1) as usize;
+                if cells.len() != expected_cells {
+                    return Err(LoadError::ExtentMismatch { line: line_number, axis: 'x', expected: expected_cells, found: cells.len() });
+                }
+                for (x, voxel_str) in (box_min_x..box_max_x + 1).zip(cells) {
                     if !voxel_str.is_empty() {
-                        let voxel_id: i32 = voxel_str.parse().unwrap();
-                        result.add_voxel(Vect3i::new([x, y, z]), catalog.create_voxel(voxel_id.try_into().unwrap()));
+                        let voxel_id = VoxelID(parse_number(line_number, voxel_str)?);
+                        if !catalog.contains(voxel_id) {
+                            return Err(LoadError::InvalidVoxelID { line: line_number, id: voxel_id.0 });
+                        }
+                        result.add_voxel(Vect3i::new([x, y, z]), catalog.create_voxel(voxel_id));
                     }
                 }
             }
         }
-        result
+        let remaining_lines = lines.filter(|(_, line)| !line.trim().is_empty()).count();
+        if remaining_lines > 0 {
+            let expected_slices = (box_max_z - box_min_z + 1) as usize;
+            return Err(LoadError::ExtentMismatch { line: extent_line_number, axis: 'z', expected: expected_slices, found: expected_slices + remaining_lines });
+        }
+        Ok(result)
+    }
+
+    const BINARY_MAGIC: &'static [u8; 4] = b"VORB";
+    const BINARY_FORMAT_VERSION: u32 = 1;
+    const BINARY_EMPTY_RUN: u8 = 0;
+    const BINARY_VOXEL: u8 = 1;
+    const BINARY_MAX_CELL_COUNT: usize = 1 << 24; // Way over any structure of the game, low enough not to exhaust memory.
+    pub const BINARY_EXTENSION: &'static str = "vorb";
+
+    // Binary layout, little-endian: magic, version, extent as six i32 (min x, max x, min y, max y, min z, max z),
+    // then cells in z, y, x order. Consecutive empty cells are stored as one run (tag, u32 count),
+    // and each voxel as (tag, u16 ID, f32 life).
+    // Fails when the box holds more cells than deserialize_binary accepts.
+    pub fn serialize_binary(&self) -> Result<Vec<u8>, SaveError> {
+        let cell_count = Self::checked_cell_count(&self.voxel_box)
+            .filter(|count| *count <= Self::BINARY_MAX_CELL_COUNT)
+            .ok_or(SaveError::TooManyCells { max_cell_count: Self::BINARY_MAX_CELL_COUNT })?;
+
+        let mut result = Self::BINARY_MAGIC.to_vec();
+        result.extend_from_slice(&Self::BINARY_FORMAT_VERSION.to_le_bytes());
+        for axis in 0..3 {
+            result.extend_from_slice(&self.voxel_box.min()[axis].to_le_bytes());
+            result.extend_from_slice(&self.voxel_box.max()[axis].to_le_bytes());
+        }
+
+        // Runs longer than a u32 are split.
+        fn push_empty_run(result: &mut Vec<u8>, mut empty_run: usize) {
+            while empty_run > 0 {
+                let run = empty_run.min(u32::MAX as usize);
+                result.push(Structure::BINARY_EMPTY_RUN);
+                result.extend_from_slice(&(run as u32).to_le_bytes());
+                empty_run -= run;
+            }
+        }
+
+        // Empty runs are the gaps between the indices of consecutive voxels.
+        let mut next_index = 0;
+        self.for_each_voxel(|coords, voxel| {
+            let index = self.voxel_index(coords).unwrap(); // The cell count is checked above.
+            push_empty_run(&mut result, index - next_index);
+            result.push(Self::BINARY_VOXEL);
+            result.extend_from_slice(&voxel.id.0.to_le_bytes());
+            result.extend_from_slice(&voxel.life.to_le_bytes());
+            next_index = index + 1;
+        });
+        push_empty_run(&mut result, cell_count - next_index);
+        Ok(result)
+    }
+
+    pub fn deserialize_binary(catalog: &VoxelCatalog, bytes: &[u8]) -> Result<Self, LoadError> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.read_bytes(Self::BINARY_MAGIC.len())? != Self::BINARY_MAGIC {
+            return Err(LoadError::InvalidMagic);
+        }
+        let version = reader.read_u32()?;
+        if version > Self::BINARY_FORMAT_VERSION {
+            return Err(LoadError::UnsupportedVersion { version: version });
+        }
+
+        let extent_offset = reader.offset;
+        let mut min = Vect3i::zero();
+        let mut max = Vect3i::zero();
+        for axis in 0..3 {
+            min[axis] = reader.read_i32()?;
+            max[axis] = reader.read_i32()?;
+        }
+        if min[0] > max[0] || min[1] > max[1] || min[2] > max[2] {
+            return Err(LoadError::CorruptedBinary { offset: extent_offset, reason: "extent minimum is greater than its maximum" });
+        }
+
+        let mut cell_count: usize = 1;
+        for axis in 0..3 {
+            cell_count = max[axis].checked_sub(min[axis])
+                .and_then(|extent| extent.checked_add(1))
+                .and_then(|size| cell_count.checked_mul(size as usize))
+                .filter(|count| *count <= Self::BINARY_MAX_CELL_COUNT)
+                .ok_or(LoadError::CorruptedBinary { offset: extent_offset, reason: "extent is too big" })?;
+        }
+
+        let mut result = Self::empty(catalog, min[0], max[0], min[1], max[1], min[2], max[2]);
+        let mut index = 0;
+        while !reader.is_at_end() {
+            let tag_offset = reader.offset;
+            match reader.read_u8()? {
+                Self::BINARY_EMPTY_RUN => {
+                    let run = reader.read_u32()? as usize;
+                    if run == 0 || index + run > cell_count {
+                        return Err(LoadError::CorruptedBinary { offset: tag_offset, reason: "empty run does not fit in extent" });
+                    }
+                    index += run;
+                },
+                Self::BINARY_VOXEL => {
+                    let id = VoxelID(reader.read_u16()?);
+                    let life = reader.read_f32()?;
+                    if index >= cell_count {
+                        return Err(LoadError::CorruptedBinary { offset: tag_offset, reason: "voxel does not fit in extent" });
+                    }
+                    if !catalog.contains(id) {
+                        return Err(LoadError::CorruptedBinary { offset: tag_offset, reason: "unknown voxel ID" });
+                    }
+                    let mut voxel = catalog.create_voxel(id);
+                    voxel.life = life;
+                    result.add_voxel(result.voxel_coords(index), voxel);
+                    index += 1;
+                },
+                _ => return Err(LoadError::CorruptedBinary { offset: tag_offset, reason: "unknown cell tag" }),
+            }
+        }
+        if index != cell_count {
+            return Err(LoadError::CorruptedBinary { offset: reader.offset, reason: "fewer cells than extent" });
+        }
+        Ok(result)
+    }
+
+    // Detects the format from the file content, so text and binary files can be loaded the same way.
+    pub fn read_from_file(catalog: &VoxelCatalog, file_name: &str) -> Result<Self, LoadError> {
+        let structure_file_content = std::fs::read(file_name).map_err(|error| LoadError::Io { file_name: file_name.to_string(), error: error })?;
+        if structure_file_content.starts_with(Self::BINARY_MAGIC) {
+            return Structure::deserialize_binary(catalog, &structure_file_content);
+        }
+        Structure::deserialize(catalog, &String::from_utf8_lossy(&structure_file_content))
     }
 
-    pub fn read_from_file(catalog: &VoxelCatalog, file_name: &str) -> Self {
-        let structure_file_content = std::fs::read_to_string(file_name).expect(&format!("Unable to read '{}'", file_name));
-        Structure::deserialize(catalog, &structure_file_content)
+    // Files ending with the binary extension are written in binary, anything else as text.
+    pub fn write_to_file(&self, file_name: &str) -> Result<(), SaveError> {
+        let is_binary = std::path::Path::new(file_name).extension().is_some_and(|extension| extension == Self::BINARY_EXTENSION);
+        let content = if is_binary { self.serialize_binary()? } else { self.serialize().into_bytes() };
+        std::fs::write(file_name, content).map_err(|error| SaveError::Io { file_name: file_name.to_string(), error: error })
     }
 
     pub fn is_empty(&self) -> bool {
-        self.every_voxel_is(|voxel| voxel.is_none())
+        self.storage.voxel_count() == 0
     }
 
     pub fn get_box(&self) -> Box3f {
@@ -136,21 +378,62 @@ impl Structure {
         Box3f::from_min_max(min, max)
     }
 
+    pub(super) fn voxel_box(&self) -> &Box3i {
+        &self.voxel_box
+    }
+
     pub fn octtree(&self) -> &Octtree {
         &self.octtree
     }
 
+    // The catalog is expected to stay the same for the whole structure life, as the mesh is only built once.
+    pub fn surface_mesh(&self, catalog: &VoxelCatalog) -> &SurfaceMesh {
+        self.surface_mesh.get_or_init(|| SurfaceMesh::greedy_from_structure(self, catalog))
+    }
+
     pub fn mass(&self) -> f32 {
         self.mass
     }
 
+    // In structure coordinates, zero for an empty structure.
+    pub fn center_of_mass(&self) -> Vect3f {
+        if self.mass <= 0.0 {
+            return Vect3f::zero();
+        }
+        let center_of_mass = self.mass_moment / self.mass as f64;
+        Vect3f::new([center_of_mass[0] as f32, center_of_mass[1] as f32, center_of_mass[2] as f32])
+    }
+
+    // Around the center of mass, in structure axes. Each voxel is a solid cube of side 1.
+    pub fn inertia_tensor(&self) -> Mat3f {
+        const CUBE_INERTIA_FACTOR: f64 = 1.0 / 6.0;
+        let mut result = Mat3f::zero();
+        if self.mass <= 0.0 {
+            return result;
+        }
+        let mass = self.mass as f64;
+        let center_of_mass = self.mass_moment / mass;
+        let second_moment_trace = self.mass_second_moment[0][0] + self.mass_second_moment[1][1] + self.mass_second_moment[2][2];
+        for x in 0..3 {
+            for y in 0..3 {
+                let diagonal = if x == y { 1.0 } else { 0.0 };
+                let inertia_around_origin = (second_moment_trace + mass * CUBE_INERTIA_FACTOR) * diagonal - self.mass_second_moment[x][y];
+                // Parallel axis theorem to move from structure origin to center of mass.
+                let center_of_mass_inertia = mass * (center_of_mass.length_sq() * diagonal - center_of_mass[x] * center_of_mass[y]);
+                result[x][y] = (inertia_around_origin - center_of_mass_inertia) as f32;
+            }
+        }
+        result
+    }
+
     pub fn recalculate_box(&mut self) {
         let mut new_box = Box3i::new();
         self.for_each_voxel(|coord, _voxel| new_box.add(coord));
         if new_box.is_empty() {
             new_box = Box3i::zero();
         }
-        self.resize(new_box);
+        self.voxel_box = new_box.clone();
+        self.storage.fit(new_box);
     }
 
     pub fn has_voxel_on_coords(&self, coords: Vect3i) -> bool {
@@ -161,161 +444,122 @@ impl Structure {
     }
 
     pub fn get_voxel(&self, coords: Vect3i) -> Option<Voxel> {
-        let index = self.voxel_index(coords);
-        self.data[index]
+        assert!(self.voxel_box.contains(coords));
+        self.storage.get(coords)
     }
 
     #[cfg(test)]
     pub fn set_voxel(&mut self, x: i32, y: i32, z: i32, voxel: Option<Voxel>) {
-        let index = self.voxel_index(Vect3i::new([x, y, z]));
-        self.data[index] = voxel;
+        let coords = Vect3i::new([x, y, z]);
+        assert!(self.voxel_box.contains(coords));
+        self.surface_mesh.take();
+        if let Some(old_voxel) = self.storage.set(coords, voxel) {
+            self.remove_mass(coords, &old_voxel);
+            self.octtree.remove_voxel(coords);
+        }
+        if let Some(new_voxel) = voxel {
+            self.add_mass(coords, &new_voxel);
+            self.octtree.add_voxel(coords);
+        }
     }
 
     pub fn add_voxel(&mut self, coords: Vect3i, voxel: Voxel) {
-        if !self.voxel_box.contains(coords) {
-            let mut new_box = self.voxel_box.clone();
-            new_box.add(coords);
-            self.resize(new_box);
+        self.voxel_box.add(coords);
+        self.surface_mesh.take();
+        if let Some(old_voxel) = self.storage.set(coords, Some(voxel)) {
+            self.remove_mass(coords, &old_voxel);
         }
-        let index = self.voxel_index(coords);
-        self.data[index] = Some(voxel);
         self.octtree.add_voxel(coords);
-        self.mass += VOXEL_WEIGHT;
+        self.add_mass(coords, &voxel);
     }
 
     pub fn remove_voxel(&mut self, coords: Vect3i) -> Voxel {
-        let index = self.voxel_index(coords);
-        assert!(self.data[index].is_some());
-        let voxel = self.data[index].unwrap();
-        self.mass -= VOXEL_WEIGHT;
-        self.data[index] = None;
+        assert!(self.voxel_box.contains(coords));
+        let voxel = self.storage.set(coords, None).unwrap();
+        self.surface_mesh.take();
+        self.remove_mass(coords, &voxel);
         self.octtree.remove_voxel(coords);
         voxel
     }
 
     pub fn remove_voxel_ifp(&mut self, coords: Vect3i) {
-        let index = self.voxel_index(coords);
-        if self.data[index].is_some() {
-            self.mass -= VOXEL_WEIGHT;
-            self.data[index] = None;
+        assert!(self.voxel_box.contains(coords));
+        if let Some(voxel) = self.storage.set(coords, None) {
+            self.surface_mesh.take();
+            self.remove_mass(coords, &voxel);
             self.octtree.remove_voxel(coords);
         }
     }
 
-    pub fn every_voxel_is<F: Fn(&Option<Voxel>) -> bool>(&self, f: F) -> bool {
-        for z in self.voxel_box.min()[2]..self.voxel_box.max()[2] + 1 {
-            for y in self.voxel_box.min()[1]..self.voxel_box.max()[1] + 1 {
-                for x in self.voxel_box.min()[0]..self.voxel_box.max()[0] + 1 {
-                    let coords = Vect3i::new([x, y, z]);
-                    let index = self.voxel_index(coords);
-                    let voxel = &self.data[index];
-                    if !f(voxel) {
-                        return false;
-                    }
-                }
-            }
-        }
-        true
+    // Life can go below zero, the voxel stays until erase_dead_voxels.
+    pub fn damage_voxel(&mut self, coords: Vect3i, damage: f32) {
+        assert!(self.voxel_box.contains(coords));
+        self.surface_mesh.take();
+        self.storage.get_mut(coords).unwrap().life -= damage;
     }
 
-    pub fn for_each_voxel<F: FnMut(Vect3i, &Voxel)>(&self, mut f: F) {
-        for z in self.voxel_box.min()[2]..self.voxel_box.max()[2] + 1 {
-            for y in self.voxel_box.min()[1]..self.voxel_box.max()[1] + 1 {
-                for x in self.voxel_box.min()[0]..self.voxel_box.max()[0] + 1 {
-                    let coords = Vect3i::new([x, y, z]);
-                    if self.has_voxel(coords) {
-                        let index = self.voxel_index(coords);
-                        let voxel = &mut self.data[index].unwrap();
-                        f(coords, voxel);
-                    }
-                }
-            }
-        }
+    pub fn for_each_voxel<F: FnMut(Vect3i, &Voxel)>(&self, f: F) {
+        self.storage.for_each(f);
     }
 
     pub fn erase_dead_voxels(&mut self) -> Vec<Vect3i> {
         let mut destroyed_coords = vec![];
-        for z in self.voxel_box.min()[2]..self.voxel_box.max()[2] + 1 {
-            for y in self.voxel_box.min()[1]..self.voxel_box.max()[1] + 1 {
-                for x in self.voxel_box.min()[0]..self.voxel_box.max()[0] + 1 {
-                    let coords = Vect3i::new([x, y, z]);
-                    if self.has_voxel(coords) {
-                        let index = self.voxel_index(coords);
-                        let voxel = &mut self.data[index];
-                        if voxel.unwrap().life <= 0.0 {
-                            *voxel = None;
-                            self.mass -= VOXEL_WEIGHT;
-                            destroyed_coords.push(coords);
-                        }
-                    }
-                }
+        self.storage.for_each(|coords, voxel| {
+            if voxel.life <= 0.0 {
+                destroyed_coords.push(coords);
             }
+        });
+        for coords in &destroyed_coords {
+            let voxel = self.storage.set(*coords, None).unwrap();
+            self.surface_mesh.take();
+            self.remove_mass(*coords, &voxel);
+            self.octtree.remove_voxel(*coords);
         }
         destroyed_coords
     }
 
-    // TODO this function should not mut self, but it calls apply_on_voxels and... Read its comment.
-    pub fn outside_voxel_coords(&mut self, segment: Segm3f) -> Option<Vect3i> {
-        let mut result: Option<Vect3i> = None;
-        self.apply_on_voxels(segment, |_voxel: &mut Voxel, coords: &Vect3i, face: &Vect3i| {
-            if *face != Vect3i::zero() {
-                result = Some(*coords + *face);
-            }
-            true
-        });
-        result
+    pub fn outside_voxel_coords(&self, segment: Segm3f) -> Option<Vect3i> {
+        match self.ray_cast(segment) {
+            Some(hit) if hit.face != Vect3i::zero() => Some(hit.coords + hit.face),
+            _ => None,
+        }
     }
 
     #[cfg(test)]
-    pub fn get_face(&mut self, segment: Segm3f) -> Vect3i {
-        let mut result = Vect3i::zero();
-        self.apply_on_voxels(segment, |_voxel: &mut Voxel, _coords, face: &Vect3i| {
-            result = *face;
-            true
-        });
-        result
+    pub fn get_face(&self, segment: Segm3f) -> Vect3i {
+        self.ray_cast(segment).map_or(Vect3i::zero(), |hit| hit.face)
     }
 
-    pub fn for_first_voxel_in_segment<F: FnMut(&mut Voxel, &Vect3i)>(&mut self, segment: Segm3f, mut f: F) -> bool {
-        self.apply_on_voxels(segment, |voxel: &mut Voxel, coords, _face| {
-            f(voxel, coords);
+    // First voxel along the segment.
+    pub fn ray_cast(&self, segment: Segm3f) -> Option<RayHit> {
+        let mut result = None;
+        self.walk_segment(segment, |hit| {
+            result = Some(hit);
             true
-        })
+        });
+        result
     }
 
-    #[allow(dead_code)]
-    pub fn for_voxels_in_segment<F: Fn(&mut Voxel)>(&mut self, segment: Segm3f, f: F) -> bool {
-        self.apply_on_voxels(segment, |voxel: &mut Voxel, _coords, _face| {
-            f(voxel);
+    // Every voxel along the segment, from the closest to the farthest.
+    pub fn ray_cast_all(&self, segment: Segm3f) -> Vec<RayHit> {
+        let mut result = vec![];
+        self.walk_segment(segment, |hit| {
+            result.push(hit);
             false
-        })
+        });
+        result
     }
 
-    // Using "A Fast Voxel Traversal Algorithm for Ray Tracing" by John Amanatides and Andrew Woo, 1987
-    // http://www.cse.yorku.ca/~amana/research/grid.pdf
-    // Adapted to work in negative space by dogfuntom
-    // https://gist.github.com/dogfuntom/cc881c8fc86ad43d55d8
-    //
-    // This function should exists in two versions : &mut self with Fn closure, and &self with FnMut closure.
-    // But it seems impossible to do such a thing without making a complete duplicate of this already long function...
-    // So let's just put a version that is &mut self AND use a FnMut for now.
-    fn apply_on_voxels<F: FnMut(&mut Voxel, &Vect3i, &Vect3i) -> bool>(&mut self, segment: Segm3f, mut f: F) -> bool {
-        fn sign(n: f32) -> i32 {
-            if n > 0.0 {
-                return 1;
-            }
-            if n < 0.0 {
-                return -1;
-            }
-            0
-        }
-
+    // Same as ray_cast_all, with the plain Amanatides and Woo walk: every cell along the whole segment is visited,
+    // inside the structure box or not. Only kept as a reference for the octree walk.
+    pub fn ray_cast_all_cell_by_cell(&self, segment: Segm3f) -> Vec<RayHit> {
         fn intbound(s: f32, ds: f32) -> f32 {
             if ds == 0.0 {
                 return f32::MAX;
             }
 
-            (if ds > 0.0 { s.ceil() - s } else { s - s.floor()}) / ds.abs()
+            // Starting exactly on a boundary, the next one is a whole cell away when going up.
+            (if ds > 0.0 { s.floor() + 1.0 - s } else { s - s.floor() }) / ds.abs()
         }
 
         fn less_than(n: i32, step: i32, max: i32) -> bool {
@@ -329,108 +573,290 @@ impl Structure {
             }
         }
 
-        let mut new_segment = segment.clone();
-
-        new_segment.start += Vect3f::all(0.5);
-        new_segment.end += Vect3f::all(0.5);
+        let start = segment.start + Vect3f::all(0.5);
+        let end = segment.end + Vect3f::all(0.5);
+        let dir = segment.direction();
 
-        let dir = new_segment.direction();
-
-        let mut x = new_segment.start[0].floor() as i32;
-        let mut y = new_segment.start[1].floor() as i32;
-        let mut z = new_segment.start[2].floor() as i32;
+        let mut x = start[0].floor() as i32;
+        let mut y = start[1].floor() as i32;
+        let mut z = start[2].floor() as i32;
         let mut face = Vect3i::zero();
+        let mut entry = 0.0;
 
-        let end_x = new_segment.end[0].floor() as i32;
-        let end_y = new_segment.end[1].floor() as i32;
-        let end_z = new_segment.end[2].floor() as i32;
+        let end_x = end[0].floor() as i32;
+        let end_y = end[1].floor() as i32;
+        let end_z = end[2].floor() as i32;
 
-        let step_x = sign(dir[0]);
-        let step_y = sign(dir[1]);
-        let step_z = sign(dir[2]);
+        let step_x = Self::sign(dir[0]);
+        let step_y = Self::sign(dir[1]);
+        let step_z = Self::sign(dir[2]);
 
-        let mut max_x = intbound(new_segment.start[0], dir[0]);
-        let mut max_y = intbound(new_segment.start[1], dir[1]);
-        let mut max_z = intbound(new_segment.start[2], dir[2]);
+        let mut max_x = intbound(start[0], dir[0]);
+        let mut max_y = intbound(start[1], dir[1]);
+        let mut max_z = intbound(start[2], dir[2]);
 
         let delta_x = step_x as f32 / dir[0];
         let delta_y = step_y as f32 / dir[1];
         let delta_z = step_z as f32 / dir[2];
 
-        let mut hit = false;
+        let mut result = vec![];
         while
             less_than(x, step_x, end_x) &&
             less_than(y, step_y, end_y) &&
             less_than(z, step_z, end_z)
         {
             let coords = Vect3i::new([x, y, z]);
-            if self.voxel_box.contains(coords) {
-                let index = self.voxel_index(coords);
-                let local_hit = self.data[index].is_some();
-                hit |= local_hit;
-                if local_hit && f(&mut self.data[index].as_mut().unwrap(), &coords, &face) {
-                    break;
-                }
+            if self.voxel_box.contains(coords) && self.has_voxel(coords) {
+                result.push(RayHit {
+                    coords,
+                    face,
+                    distance: entry * dir.length(),
+                    point: segment.start + dir * entry,
+                });
             }
             if max_x < max_y {
                 if max_x < max_z {
                     x += step_x;
+                    entry = max_x;
                     max_x += delta_x;
                     face = Vect3i::new([-step_x, 0, 0]);
                 } else {
                     z += step_z;
+                    entry = max_z;
                     max_z += delta_z;
                     face = Vect3i::new([0, 0, -step_z]);
                 }
             } else {
                 if max_y < max_z {
                     y += step_y;
+                    entry = max_y;
                     max_y += delta_y;
                     face = Vect3i::new([0, -step_y, 0]);
                 } else {
                     z += step_z;
+                    entry = max_z;
                     max_z += delta_z;
                     face = Vect3i::new([0, 0, -step_z]);
                 }
             }
         }
-        hit
+        result
     }
 
-    fn resize(&mut self, new_box: Box3i) {
-        let vec_size = (new_box.extent()[0] + 1) * (new_box.extent()[1] + 1) * (new_box.extent()[2] + 1);
-        let mut new_data: Vec<Option<Voxel>> = vec![None; vec_size.try_into().unwrap()];
-        for z in self.voxel_box.min()[2]..self.voxel_box.max()[2] + 1 {
-            for y in self.voxel_box.min()[1]..self.voxel_box.max()[1] + 1 {
-                for x in self.voxel_box.min()[0]..self.voxel_box.max()[0] + 1 {
-                    let coords = Vect3i::new([x, y, z]);
-                    let index = Self::voxel_index_for_box(&self.voxel_box, coords);
-                    if self.data[index].is_some() {
-                        let new_index = Self::voxel_index_for_box(&new_box, coords);
-                        new_data[new_index] = self.data[index];
-                    }
+    #[allow(dead_code)]
+    pub fn for_first_voxel_in_segment<F: FnMut(&mut Voxel, &Vect3i)>(&mut self, segment: Segm3f, mut f: F) -> bool {
+        match self.ray_cast(segment) {
+            Some(hit) => {
+                self.surface_mesh.take();
+                f(self.storage.get_mut(hit.coords).unwrap(), &hit.coords);
+                true
+            },
+            None => false,
+        }
+    }
+
+    #[allow(dead_code)]
+    pub fn for_voxels_in_segment<F: Fn(&mut Voxel)>(&mut self, segment: Segm3f, f: F) -> bool {
+        let hits = self.ray_cast_all(segment);
+        if !hits.is_empty() {
+            self.surface_mesh.take();
+        }
+        for hit in &hits {
+            f(self.storage.get_mut(hit.coords).unwrap());
+        }
+        !hits.is_empty()
+    }
+
+    // Using "A Fast Voxel Traversal Algorithm for Ray Tracing" by John Amanatides and Andrew Woo, 1987
+    // http://www.cse.yorku.ca/~amana/research/grid.pdf
+    //
+    // The segment is first clipped to the structure box, then walked cell by cell, except that whole empty
+    // octree cells are crossed in one step. Boundaries are computed from the cells, not accumulated,
+    // so that jumping over a big cell is the same as stepping over a voxel.
+    //
+    // Calls f on each voxel crossed by the segment, until it returns true.
+    fn walk_segment<F: FnMut(RayHit) -> bool>(&self, segment: Segm3f, mut f: F) -> bool {
+        // Voxel c covers [c, c + 1[ once shifted.
+        let start = segment.start + Vect3f::all(0.5);
+        let dir = segment.direction();
+        let step = Vect3i::new([Self::sign(dir[0]), Self::sign(dir[1]), Self::sign(dir[2])]);
+
+        // Clip the segment to the structure box.
+        let box_min = Self::coords_as_f32(self.voxel_box.min());
+        let box_max = Self::coords_as_f32(self.voxel_box.max()) + Vect3f::all(1.0);
+        let mut entry = 0.0_f32;
+        let mut exit = 1.0_f32;
+        let mut entry_axis = None;
+        for axis in 0..3 {
+            if dir[axis] == 0.0 {
+                if start[axis] < box_min[axis] || start[axis] >= box_max[axis] {
+                    return false;
+                }
+                continue;
+            }
+            let (near, far) = if dir[axis] > 0.0 { (box_min[axis], box_max[axis]) } else { (box_max[axis], box_min[axis]) };
+            let near_t = (near - start[axis]) / dir[axis];
+            let far_t = (far - start[axis]) / dir[axis];
+            if near_t > entry {
+                entry = near_t;
+                entry_axis = Some(axis);
+            }
+            exit = exit.min(far_t);
+        }
+        if entry > exit {
+            return false;
+        }
+
+        // Starting outside, the walk starts from the cell just before the box, so that the entry face is found
+        // the same way as for any other cell.
+        let mut face = Vect3i::zero();
+        let mut coords = match entry_axis {
+            None => self.cell_at(start, &self.voxel_box),
+            Some(_) => Self::cell_before(start + dir * entry, step),
+        };
+
+        let mut hit = false;
+        loop {
+            let mut cell = Box3i::from_min_max(coords, coords);
+            if self.voxel_box.contains(coords) && self.has_voxel(coords) {
+                hit = true;
+                let ray_hit = RayHit {
+                    coords,
+                    face,
+                    distance: entry * dir.length(),
+                    point: segment.start + dir * entry,
+                };
+                if f(ray_hit) {
+                    break;
                 }
+            } else if let Some(empty_box) = self.octtree.empty_box(coords) {
+                cell = empty_box;
+            }
+
+            // Leave the current cell by the closest boundary.
+            let mut next_entry = [f32::MAX; 3];
+            for axis in 0..3 {
+                if step[axis] != 0 {
+                    let boundary = if step[axis] > 0 { cell.max()[axis] + 1 } else { cell.min()[axis] };
+                    next_entry[axis] = (boundary as f32 - start[axis]) / dir[axis];
+                }
+            }
+            let axis = if next_entry[0] < next_entry[1] {
+                if next_entry[0] < next_entry[2] { 0 } else { 2 }
+            } else {
+                if next_entry[1] < next_entry[2] { 1 } else { 2 }
+            };
+            entry = next_entry[axis];
+            if entry > exit {
+                break;
+            }
+            coords = Self::cell_before(start + dir * entry, step);
+            for other_axis in 0..3 {
+                coords[other_axis] = coords[other_axis].clamp(cell.min()[other_axis], cell.max()[other_axis]);
             }
+            coords[axis] = if step[axis] > 0 { cell.max()[axis] + 1 } else { cell.min()[axis] - 1 };
+            face = Vect3i::zero();
+            face[axis] = -step[axis];
+        }
+        hit
+    }
+
+    fn sign(n: f32) -> i32 {
+        if n > 0.0 {
+            return 1;
+        }
+        if n < 0.0 {
+            return -1;
+        }
+        0
+    }
+
+    // Cell the walk is in when reaching a shifted position: boundaries exactly on the position are not crossed yet,
+    // they will be in the usual order when several of them are crossed at once.
+    fn cell_before(position: Vect3f, step: Vect3i) -> Vect3i {
+        let mut result = Vect3i::zero();
+        for axis in 0..3 {
+            result[axis] = if step[axis] > 0 { position[axis].ceil() as i32 - 1 } else { position[axis].floor() as i32 };
+        }
+        result
+    }
+
+    // Cell holding a shifted position, kept in the given box against rounding errors.
+    fn cell_at(&self, position: Vect3f, cell_box: &Box3i) -> Vect3i {
+        let mut result = Vect3i::zero();
+        for axis in 0..3 {
+            result[axis] = (position[axis].floor() as i32).clamp(cell_box.min()[axis], cell_box.max()[axis]);
+        }
+        result
+    }
+
+    fn coords_as_f32(coords: Vect3i) -> Vect3f {
+        Vect3f::new([coords[0] as f32, coords[1] as f32, coords[2] as f32])
+    }
+
+    // Expressed in tonnes, given by the catalog density of the voxel type.
+    pub fn voxel_mass(&self, voxel: &Voxel) -> f32 {
+        self.voxel_masses.get(voxel.id)
+    }
+
+    fn add_mass(&mut self, coords: Vect3i, voxel: &Voxel) {
+        let coords_d = Vect3d::new([coords[0] as f64, coords[1] as f64, coords[2] as f64]);
+        let mass = self.voxel_mass(voxel);
+        self.mass += mass;
+        self.mass_moment += coords_d * mass as f64;
+        for x in 0..3 {
+            self.mass_second_moment[x] += coords_d * (coords_d[x] * mass as f64);
+        }
+    }
+
+    fn remove_mass(&mut self, coords: Vect3i, voxel: &Voxel) {
+        let coords_d = Vect3d::new([coords[0] as f64, coords[1] as f64, coords[2] as f64]);
+        let mass = self.voxel_mass(voxel);
+        self.mass -= mass;
+        self.mass_moment -= coords_d * mass as f64;
+        for x in 0..3 {
+            self.mass_second_moment[x] -= coords_d * (coords_d[x] * mass as f64);
         }
-        self.voxel_box = new_box;
-        self.data = new_data;
     }
 
-    fn voxel_index(&self, coords: Vect3i) -> usize {
-        Self::voxel_index_for_box(&self.voxel_box, coords)
+    // Sizes and offsets are computed in i64, as the box of a chunked structure can span the whole i32 range.
+    fn box_size(voxel_box: &Box3i, axis: usize) -> i64 {
+        voxel_box.max()[axis] as i64 - voxel_box.min()[axis] as i64 + 1
+    }
+
+    // None when the index does not fit in memory.
+    fn voxel_index(&self, coords: Vect3i) -> Option<usize> {
+        let mut result: i64 = 0;
+        for axis in (0..3).rev() {
+            let coord_in_data = coords[axis] as i64 - self.voxel_box.min()[axis] as i64;
+            result = result.checked_mul(Self::box_size(&self.voxel_box, axis))?.checked_add(coord_in_data)?;
+        }
+        usize::try_from(result).ok()
+    }
+
+    fn voxel_coords(&self, index: usize) -> Vect3i {
+        let mut index = index as i64;
+        let mut result = Vect3i::zero();
+        for axis in 0..3 {
+            let size = Self::box_size(&self.voxel_box, axis);
+            result[axis] = (self.voxel_box.min()[axis] as i64 + index % size) as i32;
+            index /= size;
+        }
+        result
     }
 
-    fn voxel_index_for_box(voxel_box: &Box3i, coords: Vect3i) -> usize {
-        assert!(voxel_box.contains(coords));
-        let extent = voxel_box.extent() + Vect3i::new([1, 1, 1]);
-        let coords_in_data = coords - voxel_box.min();
-        (coords_in_data[2] * (extent[0] * extent[1]) + coords_in_data[1] * extent[0] + coords_in_data[0]).try_into().unwrap()
+    // None when the count does not fit in memory.
+    fn checked_cell_count(voxel_box: &Box3i) -> Option<usize> {
+        let mut result: usize = 1;
+        for axis in 0..3 {
+            result = result.checked_mul(usize::try_from(Self::box_size(voxel_box, axis)).ok()?)?;
+        }
+        Some(result)
     }
 
     fn has_voxel(&self, coords: Vect3i) -> bool {
         assert!(self.voxel_box.contains(coords));
-        let index = self.voxel_index(coords);
-        self.data[index].is_some()
+        self.storage.get(coords).is_some()
     }
 
     pub fn recenter(&mut self) -> Vect3i {
@@ -441,13 +867,90 @@ impl Structure {
             voxels.push((coords, *voxel));
         });
         let center = new_box.center();
-        *self = Structure::new_empty();
+        *self = Self::new_empty_like(self);
         for coords_and_voxel in voxels {
             let new_coords = coords_and_voxel.0 - center;
             self.add_voxel(new_coords, coords_and_voxel.1);
         }
         center
     }
+
+    // The box is transformed as well, even its empty parts.
+    #[allow(dead_code)]
+    pub fn transformed(&self, transform: &VoxelTransform) -> Self {
+        let mut result = Self::with_masses(self.voxel_masses.clone(), transform.apply_to_box(&self.voxel_box), self.layout());
+        self.for_each_voxel(|coords, voxel| result.add_voxel(transform.apply(coords), *voxel));
+        result
+    }
+
+    #[allow(dead_code)]
+    pub fn rotate(&mut self, axis: usize, quarter_turns: i32) {
+        *self = self.transformed(&VoxelTransform::rotation(axis, quarter_turns));
+    }
+
+    #[allow(dead_code)]
+    pub fn mirror(&mut self, axis: usize) {
+        *self = self.transformed(&VoxelTransform::mirror(axis));
+    }
+
+    #[allow(dead_code)]
+    pub fn translate(&mut self, offset: Vect3i) {
+        *self = self.transformed(&VoxelTransform::translation(offset));
+    }
+
+    // Adds the voxels of other, once transformed, to this structure. When some voxels overlap nothing is added,
+    // and their coordinates in this structure are returned.
+    #[allow(dead_code)]
+    pub fn merge(&mut self, other: &Self, transform: &VoxelTransform) -> Result<(), Vec<Vect3i>> {
+        let mut conflicts = vec![];
+        other.for_each_voxel(|coords, _voxel| {
+            let new_coords = transform.apply(coords);
+            if self.has_voxel_on_coords(new_coords) {
+                conflicts.push(new_coords);
+            }
+        });
+        if !conflicts.is_empty() {
+            return Err(conflicts);
+        }
+        other.for_each_voxel(|coords, voxel| self.add_voxel(transform.apply(coords), *voxel));
+        Ok(())
+    }
+
+    // For the boolean operations, other is moved by offset and the result box fits the result voxels.
+
+    // Where voxels overlap, the ones of this structure are kept.
+    #[allow(dead_code)]
+    pub fn union(&self, other: &Self, offset: Vect3i) -> Self {
+        let mut result = self.to_layout(self.layout());
+        other.for_each_voxel(|coords, voxel| {
+            if !self.has_voxel_on_coords(coords + offset) {
+                result.add_voxel(coords + offset, *voxel);
+            }
+        });
+        result.recalculate_box();
+        result
+    }
+
+    #[allow(dead_code)]
+    pub fn subtraction(&self, other: &Self, offset: Vect3i) -> Self {
+        self.filtered(|coords| !other.has_voxel_on_coords(coords - offset))
+    }
+
+    #[allow(dead_code)]
+    pub fn intersection(&self, other: &Self, offset: Vect3i) -> Self {
+        self.filtered(|coords| other.has_voxel_on_coords(coords - offset))
+    }
+
+    fn filtered<F: Fn(Vect3i) -> bool>(&self, keep: F) -> Self {
+        let mut result = Self::new_empty_like(self);
+        self.for_each_voxel(|coords, voxel| {
+            if keep(coords) {
+                result.add_voxel(coords, *voxel);
+            }
+        });
+        result.recalculate_box();
+        result
+    }
 }
 
 impl Eq for Structure {}
@@ -469,3 +972,50 @@ impl PartialEq for Structure {
         true
     }
 }
+
+pub(super) struct ByteReader<'a> {
+    bytes: &'a [u8],
+    pub(super) offset: usize,
+}
+
+impl<'a> ByteReader<'a> {
+    pub(super) fn new(bytes: &'a [u8]) -> Self {
+        Self {
+            bytes: bytes,
+            offset: 0,
+        }
+    }
+
+    pub(super) fn is_at_end(&self) -> bool {
+        self.offset >= self.bytes.len()
+    }
+
+    pub(super) fn read_bytes(&mut self, count: usize) -> Result<&'a [u8], LoadError> {
+        if self.offset + count > self.bytes.len() {
+            return Err(LoadError::CorruptedBinary { offset: self.bytes.len(), reason: "unexpected end of data" });
+        }
+        let result = &self.bytes[self.offset..self.offset + count];
+        self.offset += count;
+        Ok(result)
+    }
+
+    pub(super) fn read_u8(&mut self) -> Result<u8, LoadError> {
+        Ok(self.read_bytes(1)?[0])
+    }
+
+    pub(super) fn read_u16(&mut self) -> Result<u16, LoadError> {
+        Ok(u16::from_le_bytes(self.read_bytes(2)?.try_into().unwrap()))
+    }
+
+    pub(super) fn read_u32(&mut self) -> Result<u32, LoadError> {
+        Ok(u32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    pub(super) fn read_i32(&mut self) -> Result<i32, LoadError> {
+        Ok(i32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+
+    pub(super) fn read_f32(&mut self) -> Result<f32, LoadError> {
+        Ok(f32::from_le_bytes(self.read_bytes(4)?.try_into().unwrap()))
+    }
+}
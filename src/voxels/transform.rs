@@ -0,0 +1,72 @@
+use crate::maths::boxes::Box3i;
+use crate::maths::vector::Vect3i;
+
+// Maps voxel coordinates onto voxel coordinates: quarter turns, mirrors and integer translations only,
+// so that voxels stay on the grid.
+#[allow(dead_code)]
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub struct VoxelTransform {
+    axes: [Vect3i; 3], // Images of the x, y and z axes.
+    offset: Vect3i,
+}
+
+#[allow(dead_code)]
+impl VoxelTransform {
+    pub fn identity() -> Self {
+        Self {
+            axes: [Vect3i::new([1, 0, 0]), Vect3i::new([0, 1, 0]), Vect3i::new([0, 0, 1])],
+            offset: Vect3i::zero(),
+        }
+    }
+
+    pub fn translation(offset: Vect3i) -> Self {
+        Self {
+            offset,
+            ..Self::identity()
+        }
+    }
+
+    // Counterclockwise when looking from the positive side of the axis, as the float rotations.
+    pub fn rotation(axis: usize, quarter_turns: i32) -> Self {
+        let (cos, sin) = [(1, 0), (0, 1), (-1, 0), (0, -1)][quarter_turns.rem_euclid(4) as usize];
+        let next_axis = (axis + 1) % 3;
+        let last_axis = (axis + 2) % 3;
+        let mut result = Self::identity();
+        let next_axis_unit = result.axes[next_axis];
+        let last_axis_unit = result.axes[last_axis];
+        result.axes[next_axis] = next_axis_unit * cos + last_axis_unit * sin;
+        result.axes[last_axis] = last_axis_unit * cos - next_axis_unit * sin;
+        result
+    }
+
+    // Across the plane orthogonal to the axis and going through the origin.
+    pub fn mirror(axis: usize) -> Self {
+        let mut result = Self::identity();
+        result.axes[axis] = -result.axes[axis];
+        result
+    }
+
+    // Applies self, then other.
+    pub fn then(&self, other: &Self) -> Self {
+        Self {
+            axes: [other.apply_linear(self.axes[0]), other.apply_linear(self.axes[1]), other.apply_linear(self.axes[2])],
+            offset: other.apply(self.offset),
+        }
+    }
+
+    pub fn apply(&self, coords: Vect3i) -> Vect3i {
+        self.apply_linear(coords) + self.offset
+    }
+
+    pub fn apply_to_box(&self, voxel_box: &Box3i) -> Box3i {
+        let mut result = Box3i::new();
+        for corner in voxel_box.corners() {
+            result.add(self.apply(corner));
+        }
+        result
+    }
+
+    fn apply_linear(&self, coords: Vect3i) -> Vect3i {
+        self.axes[0] * coords[0] + self.axes[1] * coords[1] + self.axes[2] * coords[2]
+    }
+}
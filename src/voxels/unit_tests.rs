@@ -5,9 +5,15 @@ use crate::maths::vector::Vect3f;
 use crate::maths::vector::Vect3i;
 use super::structure::Structure;
 use super::catalog::VoxelCatalog;
+use super::error::LoadError;
 use super::octtree::Octtree;
+use super::voxel::DamageType;
+use super::voxel::ModuleType;
 use super::voxel::Voxel;
+use super::voxel::TextureType;
+use super::voxel::VOXEL_VOLUME;
 use super::voxel::VoxelID;
+use crate::maths::testing;
 
 fn assert_octtree_equals(tree: &Octtree, expected_boxes: &Vec<Box3i>) {
     let mut count = 0;
@@ -33,7 +39,8 @@ fn octtree() {
     assert_octtree_equals(&tree, &expected_boxes);
 
     tree.add_voxel(Vect3i::new([2, 1, -1]));
-    tree.add_voxel(Vect3i::new([-4, -4, -4]));
+    // The lowest voxel held by the root, -4 would make it grow.
+    tree.add_voxel(Vect3i::new([-3, -3, -3]));
     let new_sub_box = Box3i::from_min_max(Vect3i::new([-4, -4, -4]), Vect3i::new([0, 0, 0]));
     let new_sub_sub_box = Box3i::from_min_max(Vect3i::new([-4, -4, -4]), Vect3i::new([-2, -2, -2]));
     let mut new_expected_boxes = expected_boxes.clone();
@@ -42,24 +49,70 @@ fn octtree() {
 
     assert_octtree_equals(&tree, &new_expected_boxes);
 
-    tree.remove_voxel(Vect3i::new([-4, -4, -4]));
+    tree.remove_voxel(Vect3i::new([-3, -3, -3]));
 
     assert_octtree_equals(&tree, &expected_boxes);
 }
 
+#[test]
+fn octtree_grow_and_shrink() {
+    let mut tree = Octtree::new(4);
+    tree.add_voxel(Vect3i::new([1, 1, -1]));
+    tree.add_voxel(Vect3i::new([-3, 2, 4]));
+    let mut small_boxes = vec![];
+    tree.walk(&mut |oct, _| small_boxes.push(oct));
+
+    tree.add_voxel(Vect3i::new([-4, 0, 0]));
+    tree.add_voxel(Vect3i::new([20, -30, 7]));
+    assert!(tree.size() == 32);
+    for coord in [[1, 1, -1], [-3, 2, 4], [-4, 0, 0], [20, -30, 7]] {
+        assert!(tree.has_voxel(Vect3i::new(coord)));
+    }
+    assert!(!tree.has_voxel(Vect3i::new([2, 1, -1])));
+    assert!(!tree.has_voxel(Vect3i::new([100, 0, 0])));
+
+    tree.remove_voxel(Vect3i::new([20, -30, 7]));
+    assert!(tree.size() == 8);
+    tree.remove_voxel(Vect3i::new([-4, 0, 0]));
+    assert!(tree.size() == 4);
+    assert_octtree_equals(&tree, &small_boxes);
+
+    tree.remove_voxel(Vect3i::new([1, 1, -1]));
+    tree.remove_voxel(Vect3i::new([-3, 2, 4]));
+    assert!(tree.size() == 4);
+    assert_octtree_equals(&tree, &vec![Box3i::from_min_max(Vect3i::new([-4, -4, -4]), Vect3i::new([4, 4, 4]))]);
+}
+
+#[test]
+fn structure_wider_than_initial_octtree() {
+    let catalog = VoxelCatalog::create();
+    let mut structure = Structure::new(&catalog, -40, 40, 0, 0, 0, 0, TEST_VOXEL);
+    let segment = Segm3f::new(Vect3f::new([60.0, 0.0, 0.0]), Vect3f::new([-60.0, 0.0, 0.0]));
+    assert!(structure.ray_cast(segment).unwrap().coords == Vect3i::new([40, 0, 0]));
+    assert!(structure.ray_cast_all(segment).len() == 81);
+    assert!(structure.ray_cast_all(segment).len() == structure.ray_cast_all_cell_by_cell(segment).len());
+
+    for x in 20..=40 {
+        structure.set_voxel(x, 0, 0, None);
+    }
+    assert!(structure.ray_cast(segment).unwrap().coords == Vect3i::new([19, 0, 0]));
+    assert!(structure.ray_cast_all(segment).len() == 60);
+}
+
 const TEST_VOXEL: Voxel = Voxel{
     life: 1.0,
-    id: VoxelID::ShipCore,
+    id: VoxelID::SHIP_CORE,
 };
 
 const TEST_DEAD_VOXEL: Voxel = Voxel{
     life: 0.0,
-    id: VoxelID::ShipCore,
+    id: VoxelID::SHIP_CORE,
 };
 
 #[test]
 fn structure_recalculate_box() {
-    let mut structure = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+    let catalog = VoxelCatalog::create();
+    let mut structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
     let min = Vect3f::new([-2.5, -1.5, -1.5]);
     let max = Vect3f::new([4.5, 1.5, 0.5]);
     assert!(structure.get_box() == Box3f::from_min_max(min, max));
@@ -89,6 +142,7 @@ fn structure_recalculate_box() {
 
 #[test]
 fn structure_segment_intersection() {
+    let catalog = VoxelCatalog::create();
     let kill_voxels = |voxel: &mut Voxel| {
         voxel.life = 0.0;
     };
@@ -97,10 +151,10 @@ fn structure_segment_intersection() {
         let segment_start = Vect3f::new([-10.0, 0.0, 0.0]);
         let segment_end = Vect3f::new([10.0, 0.0, 0.0]);
         let segment = Segm3f::new(segment_start, segment_end);
-        let mut structure = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+        let mut structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
         structure.for_voxels_in_segment(segment, kill_voxels);
 
-        let expected_structure = { let mut structure = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+        let expected_structure = { let mut structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
             structure.set_voxel(-2, 0, 0, Some(TEST_DEAD_VOXEL));
             structure.set_voxel(-1, 0, 0, Some(TEST_DEAD_VOXEL));
             structure.set_voxel(0, 0, 0, Some(TEST_DEAD_VOXEL));
@@ -120,12 +174,12 @@ fn structure_segment_intersection() {
         let segment_2_start = Vect3f::new([-10.0, 1.4, 0.0]);
         let segment_2_end = Vect3f::new([10.0, 1.4, 0.0]);
         let segment_2 = Segm3f::new(segment_2_start, segment_2_end);
-        let mut structure_1 = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+        let mut structure_1 = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
         structure_1.for_voxels_in_segment(segment_1, kill_voxels);
-        let mut structure_2 = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+        let mut structure_2 = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
         structure_2.for_voxels_in_segment(segment_2, kill_voxels);
 
-        let expected_structure = { let mut structure = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+        let expected_structure = { let mut structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
             structure.set_voxel(-2, 1, 0, Some(TEST_DEAD_VOXEL));
             structure.set_voxel(-1, 1, 0, Some(TEST_DEAD_VOXEL));
             structure.set_voxel(0, 1, 0, Some(TEST_DEAD_VOXEL));
@@ -146,12 +200,12 @@ fn structure_segment_intersection() {
         let segment_2_start = Vect3f::new([-10.0, -1.4, 0.0]);
         let segment_2_end = Vect3f::new([10.0, -1.4, 0.0]);
         let segment_2 = Segm3f::new(segment_2_start, segment_2_end);
-        let mut structure_1 = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+        let mut structure_1 = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
         structure_1.for_voxels_in_segment(segment_1, kill_voxels);
-        let mut structure_2 = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+        let mut structure_2 = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
         structure_2.for_voxels_in_segment(segment_2, kill_voxels);
 
-        let expected_structure = { let mut structure = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+        let expected_structure = { let mut structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
             structure.set_voxel(-2, -1, 0, Some(TEST_DEAD_VOXEL));
             structure.set_voxel(-1, -1, 0, Some(TEST_DEAD_VOXEL));
             structure.set_voxel(0, -1, 0, Some(TEST_DEAD_VOXEL));
@@ -168,6 +222,7 @@ fn structure_segment_intersection() {
 
 #[test]
 fn structure_segment_first_intersection() {
+    let catalog = VoxelCatalog::create();
     let kill_voxels = |voxel: &mut Voxel, _coords: &Vect3i| {
         voxel.life = 0.0;
     };
@@ -176,10 +231,10 @@ fn structure_segment_first_intersection() {
         let segment_start = Vect3f::new([-10.0, 0.0, 0.0]);
         let segment_end = Vect3f::new([10.0, 0.0, 0.0]);
         let segment = Segm3f::new(segment_start, segment_end);
-        let mut structure = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+        let mut structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
         structure.for_first_voxel_in_segment(segment, kill_voxels);
 
-        let mut expected_structure = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+        let mut expected_structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
         expected_structure.set_voxel(-2, 0, 0, Some(TEST_DEAD_VOXEL));
         assert!(structure == expected_structure);
 
@@ -193,15 +248,16 @@ fn structure_segment_first_intersection() {
 
 #[test]
 fn structure_segment_intersection_end() {
+    let catalog = VoxelCatalog::create();
     let segment_start = Vect3f::new([-1.0, 0.0, 0.0]);
     let segment_end = Vect3f::new([1.0, -1.0, 0.0]);
     let segment = Segm3f::new(segment_start, segment_end);
-    let mut structure = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+    let mut structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
     structure.for_voxels_in_segment(segment, |voxel: &mut Voxel| {
         voxel.life = 0.0;
     });
 
-    let expected_structure = { let mut structure = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+    let expected_structure = { let mut structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
         structure.set_voxel(-1, 0, 0, Some(TEST_DEAD_VOXEL));
         structure.set_voxel(0, 0, 0, Some(TEST_DEAD_VOXEL));
         structure.set_voxel(0, -1, 0, Some(TEST_DEAD_VOXEL));
@@ -213,7 +269,8 @@ fn structure_segment_intersection_end() {
 
 #[test]
 fn structure_segment_intersection_face() {
-    let mut structure = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+    let catalog = VoxelCatalog::create();
+    let structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
 
     // Inner start (no face detected, the ray start inside a voxel)
     {
@@ -273,9 +330,106 @@ fn structure_segment_intersection_face() {
     }
 }
 
+#[test]
+fn structure_ray_cast() {
+    let catalog = VoxelCatalog::create();
+    let structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
+
+    {
+        let segment = Segm3f::new(Vect3f::new([-10.0, 0.2, 0.0]), Vect3f::new([10.0, 0.2, 0.0]));
+        let hit = structure.ray_cast(segment).unwrap();
+        assert!(hit.coords == Vect3i::new([-2, 0, 0]));
+        assert!(hit.face == Vect3i::new([-1, 0, 0]));
+        assert!(testing::equals_with_delta(hit.distance, 7.5, 0.0001));
+        assert!(testing::vec_equals_with_delta(hit.point, Vect3f::new([-2.5, 0.2, 0.0]), 0.0001));
+
+        let hits = structure.ray_cast_all(segment);
+        assert!(hits.len() == 7);
+        for (i, hit) in hits.iter().enumerate() {
+            assert!(hit.coords == Vect3i::new([i as i32 - 2, 0, 0]));
+            assert!(testing::equals_with_delta(hit.distance, 7.5 + i as f32, 0.0001));
+        }
+        assert!(hits[1].face == Vect3i::new([-1, 0, 0]));
+    }
+    {
+        // From above, diagonally.
+        let segment = Segm3f::new(Vect3f::new([0.0, 4.0, 4.5]), Vect3f::new([0.0, -4.0, -3.5]));
+        let hit = structure.ray_cast(segment).unwrap();
+        assert!(hit.coords == Vect3i::zero());
+        assert!(hit.face == Vect3i::new([0, 0, 1]));
+        assert!(testing::vec_equals_with_delta(hit.point, Vect3f::new([0.0, 0.0, 0.5]), 0.0001));
+        assert!(testing::equals_with_delta(hit.distance, 4.0 * f32::sqrt(2.0), 0.0001));
+    }
+    {
+        // Starting inside a voxel, there is no entry face.
+        let segment = Segm3f::new(Vect3f::new([0.0, 0.0, 0.0]), Vect3f::new([0.0, 0.0, -10.0]));
+        let hit = structure.ray_cast(segment).unwrap();
+        assert!(hit.coords == Vect3i::zero());
+        assert!(hit.face == Vect3i::zero());
+        assert!(hit.distance == 0.0);
+    }
+    {
+        let segment = Segm3f::new(Vect3f::new([-10.0, 5.0, 0.0]), Vect3f::new([10.0, 5.0, 0.0]));
+        assert!(structure.ray_cast(segment).is_none());
+        assert!(structure.ray_cast_all(segment).is_empty());
+    }
+}
+
+#[test]
+fn structure_ray_cast_skipping_empty_cells() {
+    let catalog = VoxelCatalog::create();
+    // Sparse voxels in a big box, so that the octree has big empty cells.
+    let mut structure = Structure::empty(&catalog, -15, 15, -15, 15, -15, 15);
+    for i in 0..40 {
+        let coords = Vect3i::new([(i * 7) % 31 - 15, (i * 13) % 31 - 15, (i * 19) % 31 - 15]);
+        structure.add_voxel(coords, TEST_VOXEL);
+    }
+    structure.add_voxel(Vect3i::new([15, 15, 15]), TEST_VOXEL);
+    structure.add_voxel(Vect3i::new([-15, -15, -15]), TEST_VOXEL);
+
+    let mut total_hits = 0;
+    for i in 0..200 {
+        let start = Vect3f::new([(i % 7) as f32 * 5.3 - 20.0, (i % 11) as f32 * 3.7 - 20.0, -20.0 + (i % 3) as f32]);
+        let end = Vect3f::new([20.0 - (i % 13) as f32 * 2.9, 20.0 - (i % 5) as f32 * 7.1, 20.0 - (i % 17) as f32 * 2.3]);
+        for segment in [Segm3f::new(start, end), Segm3f::new(end, start), Segm3f::new(start * 0.3, end * 0.2)] {
+            let hits = structure.ray_cast_all(segment);
+            let expected_hits = structure.ray_cast_all_cell_by_cell(segment);
+            assert!(hits.len() == expected_hits.len());
+            for (hit, expected_hit) in hits.iter().zip(expected_hits.iter()) {
+                assert!(hit.coords == expected_hit.coords);
+                assert!(hit.face == expected_hit.face);
+                assert!(testing::equals_with_delta(hit.distance, expected_hit.distance, 0.001));
+            }
+            total_hits += hits.len();
+        }
+    }
+    assert!(total_hits > 0);
+
+    // Along the diagonal, through both corners.
+    let segment = Segm3f::new(Vect3f::all(-20.0), Vect3f::all(20.0));
+    let hits = structure.ray_cast_all(segment);
+    assert!(hits.first().unwrap().coords == Vect3i::all(-15));
+    assert!(hits.last().unwrap().coords == Vect3i::all(15));
+}
+
+#[test]
+fn octtree_empty_box() {
+    let mut octtree = Octtree::new(16);
+    octtree.add_voxel(Vect3i::new([1, 1, 1]));
+    assert!(octtree.empty_box(Vect3i::new([1, 1, 1])).is_none());
+    // Biggest empty cell is the whole octant.
+    assert!(octtree.empty_box(Vect3i::new([-5, -5, -5])) == Some(Box3i::from_min_max(Vect3i::all(-15), Vect3i::all(0))));
+    assert!(octtree.empty_box(Vect3i::new([2, 1, 1])) == Some(Box3i::from_min_max(Vect3i::new([2, 1, 1]), Vect3i::new([2, 1, 1]))));
+    assert!(octtree.empty_box(Vect3i::new([6, 6, 6])) == Some(Box3i::from_min_max(Vect3i::all(5), Vect3i::all(8))));
+    // Outside of the octree.
+    assert!(octtree.empty_box(Vect3i::new([-16, 0, 0])).is_none());
+    assert!(octtree.empty_box(Vect3i::new([17, 0, 0])).is_none());
+}
+
 #[test]
 fn structure_outside_voxel_coords() {
-    let mut structure = Structure::new(-2, 4, -1, 1, -1, 0, TEST_VOXEL);
+    let catalog = VoxelCatalog::create();
+    let mut structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, TEST_VOXEL);
     structure.set_voxel(-2, -1, -1, None);
 
     {
@@ -299,13 +453,890 @@ fn structure_outside_voxel_coords() {
 fn structure_serialization() {
     let catalog = VoxelCatalog::create();
     let structure = {
-        let mut structure = Structure::new(-2, 4, -1, 1, -1, 0, catalog.create_voxel(VoxelID::LightHull));
-        structure.set_voxel(0, 0, 0, Some(catalog.create_voxel(VoxelID::ShipCore)));
-        structure.set_voxel(2, 1, -1, Some(catalog.create_voxel(VoxelID::ShipCore)));
+        let mut structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, catalog.create_voxel(VoxelID::LIGHT_HULL));
+        structure.set_voxel(0, 0, 0, Some(catalog.create_voxel(VoxelID::SHIP_CORE)));
+        structure.set_voxel(2, 1, -1, Some(catalog.create_voxel(VoxelID::SHIP_CORE)));
         structure.set_voxel(-1, 1, -1, None);
         structure.set_voxel(0, 1, -1, None);
         structure
     };
     let serialization = structure.serialize();
-    assert!(Structure::deserialize(&catalog, &serialization) == structure);
-}
\ No newline at end of file
+    assert!(serialization.starts_with("vors 1\n"));
+    assert!(Structure::deserialize(&catalog, &serialization).unwrap() == structure);
+
+    // Empty rows and slices between sparse voxels.
+    let mut sparse = Structure::new_empty(&catalog);
+    sparse.add_voxel(Vect3i::new([1, 0, 0]), catalog.create_voxel(VoxelID::LIGHT_HULL));
+    sparse.add_voxel(Vect3i::new([0, 1, 3]), catalog.create_voxel(VoxelID::SHIP_CORE));
+    assert!(sparse.serialize() == "vors 1\n0;1;0;1;0;3\n 1 |  |\n  |  |\n  |  |\n  |0  |\n");
+    assert!(Structure::deserialize(&catalog, &sparse.serialize()).unwrap() == sparse);
+}
+
+#[test]
+fn structure_deserialization_without_version() {
+    let catalog = VoxelCatalog::create();
+    let expected_structure = {
+        let mut structure = Structure::new(&catalog, 0, 1, 0, 0, 0, 1, catalog.create_voxel(VoxelID::LIGHT_HULL));
+        structure.set_voxel(1, 0, 1, None);
+        structure
+    };
+    let serialization = "0;1;0;0;0;1\n1 1 |\n1  |\n";
+    assert!(Structure::deserialize(&catalog, serialization).unwrap() == expected_structure);
+
+    assert!(Structure::read_from_file(&catalog, "structures/tie.vors").is_ok());
+    assert!(Structure::read_from_file(&catalog, "structures/x_wing.vors").is_ok());
+}
+
+#[test]
+fn structure_deserialization_errors() {
+    let catalog = VoxelCatalog::create();
+
+    let result = Structure::deserialize(&catalog, "vors 2\n0;0;0;0;0;0\n1 |\n");
+    assert!(matches!(result, Err(LoadError::UnsupportedVersion { version: 2 })));
+
+    let result = Structure::deserialize(&catalog, "vors one\n0;0;0;0;0;0\n1 |\n");
+    assert!(matches!(result, Err(LoadError::InvalidNumber { line: 1, .. })));
+
+    let result = Structure::deserialize(&catalog, "vors 1\n0;0;0;0;0\n1 |\n");
+    assert!(matches!(result, Err(LoadError::InvalidHeader { line: 2, .. })));
+
+    let result = Structure::deserialize(&catalog, "0;0;0;0;1;0\n");
+    assert!(matches!(result, Err(LoadError::InvalidExtent { line: 1 })));
+
+    let result = Structure::deserialize(&catalog, "0;1;0;0;0;0\n1 x |\n");
+    assert!(matches!(result, Err(LoadError::InvalidNumber { line: 2, .. })));
+
+    let result = Structure::deserialize(&catalog, "0;1;0;0;0;1\n1 1 |\n1 42 |\n");
+    assert!(matches!(result, Err(LoadError::InvalidVoxelID { line: 3, id: 42 })));
+
+    let result = Structure::deserialize(&catalog, "0;2;0;0;0;0\n1 1 |\n");
+    assert!(matches!(result, Err(LoadError::ExtentMismatch { line: 2, axis: 'x', expected: 3, found: 2 })));
+
+    let result = Structure::deserialize(&catalog, "0;0;0;1;0;0\n1 |1 |1 |\n");
+    assert!(matches!(result, Err(LoadError::ExtentMismatch { line: 2, axis: 'y', expected: 2, found: 3 })));
+
+    let result = Structure::deserialize(&catalog, "0;0;0;0;0;0\n1 |\n1 |\n");
+    assert!(matches!(result, Err(LoadError::ExtentMismatch { line: 1, axis: 'z', expected: 1, found: 2 })));
+
+    let result = Structure::deserialize(&catalog, "vors 1\n0;0;0;0;0;2\n1 |\n1 |\n");
+    assert!(matches!(result, Err(LoadError::TruncatedSlice { line: 5, z: 2 })));
+
+    let result = Structure::read_from_file(&catalog, "structures/does_not_exist.vors");
+    assert!(matches!(result, Err(LoadError::Io { .. })));
+}
+
+#[test]
+fn structure_binary_serialization() {
+    let catalog = VoxelCatalog::create();
+    let structure = {
+        let mut structure = Structure::new(&catalog, -2, 4, -1, 1, -1, 0, catalog.create_voxel(VoxelID::LIGHT_HULL));
+        structure.set_voxel(0, 0, 0, Some(Voxel{life: 0.5, id: VoxelID::SHIP_CORE}));
+        structure.set_voxel(-2, -1, -1, None);
+        structure.set_voxel(-1, -1, -1, None);
+        structure.set_voxel(4, 1, 0, None);
+        structure
+    };
+    let serialization = structure.serialize_binary().unwrap();
+    let deserialized = Structure::deserialize_binary(&catalog, &serialization).unwrap();
+    assert!(deserialized == structure);
+    assert!(deserialized.get_voxel(Vect3i::new([0, 0, 0])).unwrap().life == 0.5);
+}
+
+#[test]
+fn structure_binary_run_length() {
+    let catalog = VoxelCatalog::create();
+    let mut structure = Structure::new_empty(&catalog);
+    structure.add_voxel(Vect3i::new([-15, -15, -15]), TEST_VOXEL);
+    structure.add_voxel(Vect3i::new([15, 15, 15]), TEST_VOXEL);
+    let serialization = structure.serialize_binary().unwrap();
+    // Header and extent, two voxels and a single run of empty cells between them.
+    assert!(serialization.len() == 4 + 4 + 24 + 2 * 7 + 5);
+    assert!(serialization.len() < structure.serialize().len() / 100);
+    assert!(Structure::deserialize_binary(&catalog, &serialization).unwrap() == structure);
+}
+
+#[test]
+fn structure_binary_text_conversion() {
+    let catalog = VoxelCatalog::create();
+    let text_structure = Structure::read_from_file(&catalog, "structures/tie.vors").unwrap();
+    let binary_structure = Structure::deserialize_binary(&catalog, &text_structure.serialize_binary().unwrap()).unwrap();
+    assert!(binary_structure == text_structure);
+    assert!(Structure::deserialize(&catalog, &binary_structure.serialize()).unwrap() == text_structure);
+}
+
+#[test]
+fn structure_binary_deserialization_errors() {
+    let catalog = VoxelCatalog::create();
+    let serialization = Structure::new(&catalog, 0, 1, 0, 0, 0, 0, TEST_VOXEL).serialize_binary().unwrap();
+
+    let result = Structure::deserialize_binary(&catalog, b"VORS");
+    assert!(matches!(result, Err(LoadError::InvalidMagic)));
+
+    let result = Structure::deserialize_binary(&catalog, &serialization[..serialization.len() - 2]);
+    assert!(matches!(result, Err(LoadError::CorruptedBinary { .. })));
+
+    let mut unknown_id = serialization.clone();
+    unknown_id[33] = 42;
+    let result = Structure::deserialize_binary(&catalog, &unknown_id);
+    assert!(matches!(result, Err(LoadError::CorruptedBinary { offset: 32, .. })));
+
+    let mut too_many_cells = serialization.clone();
+    too_many_cells.extend_from_slice(&[0, 1, 0, 0, 0]);
+    let result = Structure::deserialize_binary(&catalog, &too_many_cells);
+    assert!(matches!(result, Err(LoadError::CorruptedBinary { offset: 46, .. })));
+
+    let mut huge_extent = serialization[..8].to_vec();
+    for _ in 0..3 {
+        huge_extent.extend_from_slice(&i32::MIN.to_le_bytes());
+        huge_extent.extend_from_slice(&i32::MAX.to_le_bytes());
+    }
+    let result = Structure::deserialize_binary(&catalog, &huge_extent);
+    assert!(matches!(result, Err(LoadError::CorruptedBinary { offset: 8, .. })));
+}
+
+#[test]
+fn structure_binary_serialization_too_big() {
+    use super::error::SaveError;
+
+    let catalog = VoxelCatalog::create();
+    // Fine for chunked storage, but not loadable back.
+    let mut station = Structure::new_empty(&catalog);
+    station.add_voxel(Vect3i::zero(), TEST_VOXEL);
+    station.add_voxel(Vect3i::all(300), TEST_VOXEL);
+    assert!(matches!(station.serialize_binary(), Err(SaveError::TooManyCells { .. })));
+    // The cell count does not even fit in an i32.
+    station.add_voxel(Vect3i::all(1500), TEST_VOXEL);
+    assert!(matches!(station.serialize_binary(), Err(SaveError::TooManyCells { .. })));
+}
+
+#[test]
+fn catalog_deserialization() {
+    let definitions = "# Comment\n\n0;ShipCore;5;Core;10;3;0;0;0;None;0;0\n7;Armor;12.5;DarkHull;25;4.5;0.5;-0.25;1;None;0;0\n3;Canon;5;Canon;10;2;0;0;0;None;0;0\n9;Engine;4;Canon;10;2;0;0;0;Thruster;500;2.5\n";
+    let catalog = VoxelCatalog::deserialize(definitions).unwrap();
+    assert!(catalog.ids().clone() == vec![VoxelID(0), VoxelID(3), VoxelID(7), VoxelID(9)]);
+    assert!(!catalog.contains(VoxelID(1)));
+
+    let armor = catalog.get_descriptor(VoxelID(7));
+    assert!(armor.name == "Armor");
+    assert!(armor.max_life == 12.5);
+    assert!(armor.texture_type == TextureType::DarkHull);
+    assert!(armor.density == 25.0);
+    assert!(armor.penetration_resistance == 4.5);
+    assert!(armor.damage_resistances == [0.5, -0.25, 1.0]);
+    assert!(armor.effective_damage(2.0, DamageType::Kinetic) == 1.0);
+    assert!(armor.effective_damage(2.0, DamageType::Energy) == 2.5);
+    assert!(armor.effective_damage(2.0, DamageType::Explosive) == 0.0);
+    assert!(armor.module == None);
+    assert!(catalog.voxel_masses().get(VoxelID(7)) == 25.0);
+
+    let engine = catalog.get_descriptor(VoxelID(9));
+    assert!(engine.module == Some(ModuleType::Thruster));
+    assert!(engine.module_output == 500.0);
+    assert!(engine.power_draw == 2.5);
+    assert!(catalog.create_voxel(VoxelID(7)) == Voxel{id: VoxelID(7), life: 12.5});
+
+    let structure = Structure::deserialize(&catalog, "0;1;0;0;0;0\n0 7 |\n").unwrap();
+    assert!(structure.get_voxel(Vect3i::new([1, 0, 0])) == Some(Voxel{id: VoxelID(7), life: 12.5}));
+    let result = Structure::deserialize(&catalog, "0;1;0;0;0;0\n0 1 |\n");
+    assert!(matches!(result, Err(LoadError::InvalidVoxelID { line: 2, id: 1 })));
+}
+
+#[test]
+fn catalog_deserialization_errors() {
+    let result = VoxelCatalog::deserialize("0;ShipCore;5;Core;10\n");
+    assert!(matches!(result, Err(LoadError::InvalidDefinition { line: 1, .. })));
+
+    let result = VoxelCatalog::deserialize("0;ShipCore;5;Core;10;3;0;0;0;None;0;0\n0;;5;Core;10;3;0;0;0;None;0;0\n");
+    assert!(matches!(result, Err(LoadError::InvalidDefinition { line: 2, .. })));
+
+    let result = VoxelCatalog::deserialize("0;Ship Core;5;Core;10;3;0;0;0;None;0;0\n");
+    assert!(matches!(result, Err(LoadError::InvalidDefinition { line: 1, .. })));
+
+    let result = VoxelCatalog::deserialize("0;ShipCore;0;Core;10;3;0;0;0;None;0;0\n");
+    assert!(matches!(result, Err(LoadError::InvalidDefinition { line: 1, .. })));
+
+    let result = VoxelCatalog::deserialize("0;ShipCore;5;Core;10;-1;0;0;0;None;0;0\n");
+    assert!(matches!(result, Err(LoadError::InvalidDefinition { line: 1, .. })));
+
+    let result = VoxelCatalog::deserialize("0;ShipCore;5;Core;10;3\n");
+    assert!(matches!(result, Err(LoadError::InvalidDefinition { line: 1, .. })));
+
+    let result = VoxelCatalog::deserialize("0;ShipCore;5;Core;10;3;0;1.5;0;None;0;0\n");
+    assert!(matches!(result, Err(LoadError::InvalidDefinition { line: 1, .. })));
+
+    for definition in ["0;ShipCore;NaN;Core;10;3;0;0;0;None;0;0", "0;ShipCore;5;Core;inf;3;0;0;0;None;0;0", "0;ShipCore;5;Core;10;3;0;-inf;0;None;0;0", "0;ShipCore;5;Core;10;3;0;0;0;Reactor;NaN;0"] {
+        assert!(matches!(VoxelCatalog::deserialize(definition), Err(LoadError::InvalidDefinition { line: 1, .. })));
+    }
+
+    let result = VoxelCatalog::deserialize("0;ShipCore;5;Core;10;3;0;x;0;None;0;0\n");
+    assert!(matches!(result, Err(LoadError::InvalidNumber { line: 1, .. })));
+
+    let result = VoxelCatalog::deserialize("-1;ShipCore;5;Core;10;3;0;0;0;None;0;0\n");
+    assert!(matches!(result, Err(LoadError::InvalidNumber { line: 1, .. })));
+
+    let result = VoxelCatalog::deserialize("0;ShipCore;5;Golden;10;3;0;0;0;None;0;0\n");
+    assert!(matches!(result, Err(LoadError::UnknownTextureType { line: 1, .. })));
+
+    let result = VoxelCatalog::deserialize("0;ShipCore;5;Core;10;3;0;0;0;Warp;1;1\n");
+    assert!(matches!(result, Err(LoadError::UnknownModuleType { line: 1, .. })));
+
+    let result = VoxelCatalog::deserialize("0;ShipCore;5;Core;10;3;0;0;0;Thruster;-1;1\n");
+    assert!(matches!(result, Err(LoadError::InvalidDefinition { line: 1, .. })));
+
+    let result = VoxelCatalog::deserialize("0;ShipCore;5;Core;10;3;0;0;0;None;0;0\n# Comment\n0;Other;5;Core;10;3;0;0;0;None;0;0\n");
+    assert!(matches!(result, Err(LoadError::DuplicateVoxelID { line: 3, id: 0 })));
+}
+
+#[test]
+fn structure_mass_and_center_of_mass() {
+    let catalog = VoxelCatalog::create();
+    let light_voxel = Voxel{life: 1.0, id: VoxelID::LIGHT_HULL};
+    let heavy_voxel = Voxel{life: 1.0, id: VoxelID::SHIP_CORE};
+
+    let mut structure = Structure::new(&catalog, -1, 1, 0, 0, 0, 0, light_voxel);
+    let light_mass = structure.voxel_mass(&light_voxel);
+    let heavy_mass = structure.voxel_mass(&heavy_voxel);
+    assert!(heavy_mass > light_mass);
+    assert!(structure.mass() == 3.0 * light_mass);
+    assert!(structure.center_of_mass() == Vect3f::zero());
+
+    structure.add_voxel(Vect3i::new([2, 0, 0]), heavy_voxel);
+    assert!(structure.mass() == 3.0 * light_mass + heavy_mass);
+    assert!(testing::vec_equals_with_delta(structure.center_of_mass(), Vect3f::new([2.0 * heavy_mass / (3.0 * light_mass + heavy_mass), 0.0, 0.0]), 1e-5));
+
+    // Replacing a voxel must not count it twice.
+    structure.add_voxel(Vect3i::new([2, 0, 0]), light_voxel);
+    assert!(structure.mass() == 4.0 * light_mass);
+    assert!(structure.center_of_mass() == Vect3f::new([0.5, 0.0, 0.0]));
+
+    structure.remove_voxel(Vect3i::new([-1, 0, 0]));
+    structure.remove_voxel_ifp(Vect3i::new([-1, 0, 0]));
+    assert!(structure.mass() == 3.0 * light_mass);
+    assert!(structure.center_of_mass() == Vect3f::new([1.0, 0.0, 0.0]));
+
+    structure.set_voxel(2, 0, 0, Some(Voxel{life: 0.0, id: VoxelID::LIGHT_HULL}));
+    structure.erase_dead_voxels();
+    assert!(structure.mass() == 2.0 * light_mass);
+    assert!(structure.center_of_mass() == Vect3f::new([0.5, 0.0, 0.0]));
+    assert!(!structure.octtree().has_voxel(Vect3i::new([2, 0, 0])));
+
+    assert!(Structure::empty(&catalog, -4, 4, -4, 4, -4, 4).mass() == 0.0);
+    assert!(Structure::new_empty(&catalog).center_of_mass() == Vect3f::zero());
+}
+
+#[test]
+fn structure_mass_from_catalog() {
+    let catalog = VoxelCatalog::create();
+    let structure = Structure::deserialize(&catalog, "0;1;0;0;0;0\n0 1 |\n").unwrap();
+    let core_mass = catalog.get_descriptor(VoxelID::SHIP_CORE).density * VOXEL_VOLUME;
+    let hull_mass = catalog.get_descriptor(VoxelID::LIGHT_HULL).density * VOXEL_VOLUME;
+    assert!(structure.mass() == core_mass + hull_mass);
+    assert!(structure.center_of_mass() == Vect3f::new([hull_mass / (core_mass + hull_mass), 0.0, 0.0]));
+}
+
+#[test]
+fn structure_storage_layouts() {
+    use super::storage::StorageLayout;
+
+    let catalog = VoxelCatalog::create();
+
+    let mut chunked = Structure::new_empty(&catalog);
+    assert!(chunked.layout() == StorageLayout::Chunked);
+    // Across chunk boundaries and far from each other.
+    for coords in [[-9, 0, 0], [-8, 0, 0], [-1, -1, -1], [0, 0, 0], [7, 8, 0], [100, -60, 20]] {
+        chunked.add_voxel(Vect3i::new(coords), TEST_VOXEL);
+    }
+    let dense = chunked.to_layout(StorageLayout::Dense);
+    assert!(dense.layout() == StorageLayout::Dense);
+    assert!(dense == chunked);
+    assert!(dense.mass() == chunked.mass());
+    assert!(dense.serialize_binary().unwrap() == chunked.serialize_binary().unwrap());
+    assert!(dense.serialize() == chunked.serialize());
+    // Both layouts only visit their voxels, in the same order.
+    let mut dense_coords = vec![];
+    dense.for_each_voxel(|coords, _voxel| dense_coords.push(coords));
+    let mut chunked_coords = vec![];
+    chunked.for_each_voxel(|coords, _voxel| chunked_coords.push(coords));
+    assert!(chunked_coords.len() == 6);
+    assert!(chunked_coords == dense_coords);
+    assert!(chunked.storage_memory_size() * 10 < dense.storage_memory_size());
+
+    for mut structure in [chunked, dense] {
+        assert!(structure.get_voxel(Vect3i::new([-8, 0, 0])).is_some());
+        assert!(structure.get_voxel(Vect3i::new([-7, 0, 0])).is_none());
+        structure.remove_voxel(Vect3i::new([100, -60, 20]));
+        structure.recalculate_box();
+        assert!(structure.get_box() == Box3f::from_min_max(Vect3f::new([-9.5, -1.5, -1.5]), Vect3f::new([7.5, 8.5, 0.5])));
+        for coords in [[-9, 0, 0], [-8, 0, 0], [-1, -1, -1], [0, 0, 0], [7, 8, 0]] {
+            structure.remove_voxel(Vect3i::new(coords));
+        }
+        assert!(structure.is_empty());
+    }
+}
+
+#[test]
+fn voxel_transform() {
+    use super::transform::VoxelTransform;
+
+    let coords = Vect3i::new([1, 2, 3]);
+    assert!(VoxelTransform::rotation(2, 1).apply(coords) == Vect3i::new([-2, 1, 3]));
+    assert!(VoxelTransform::rotation(0, 1).apply(coords) == Vect3i::new([1, -3, 2]));
+    assert!(VoxelTransform::rotation(1, -1).apply(coords) == Vect3i::new([-3, 2, 1]));
+    assert!(VoxelTransform::rotation(1, 4) == VoxelTransform::identity());
+    assert!(VoxelTransform::mirror(1).apply(coords) == Vect3i::new([1, -2, 3]));
+
+    let transform = VoxelTransform::rotation(2, 1).then(&VoxelTransform::translation(Vect3i::new([10, 0, 0])));
+    assert!(transform.apply(coords) == Vect3i::new([8, 1, 3]));
+    let transform = VoxelTransform::translation(Vect3i::new([10, 0, 0])).then(&VoxelTransform::rotation(2, 1));
+    assert!(transform.apply(coords) == Vect3i::new([-2, 11, 3]));
+}
+
+#[test]
+fn structure_transforms() {
+    let catalog = VoxelCatalog::create();
+    let heavy_voxel = Voxel{life: 1.0, id: VoxelID::HEAVY_HULL};
+    // An L in the xy plane, heavier at the end of its long side.
+    let mut structure = Structure::new(&catalog, 0, 2, 0, 0, 0, 0, TEST_VOXEL);
+    structure.add_voxel(Vect3i::new([0, 1, 0]), TEST_VOXEL);
+    structure.add_voxel(Vect3i::new([2, 0, 0]), heavy_voxel);
+    let original = structure.clone();
+
+    structure.rotate(2, 1);
+    assert!(structure.get_voxel(Vect3i::new([0, 2, 0])).unwrap().id == heavy_voxel.id);
+    assert!(structure.get_voxel(Vect3i::new([-1, 0, 0])).is_some());
+    assert!(structure.get_box() == Box3f::from_min_max(Vect3f::new([-1.5, -0.5, -0.5]), Vect3f::new([0.5, 2.5, 0.5])));
+    assert!(structure.mass() == original.mass());
+    let center_of_mass = original.center_of_mass();
+    assert!(testing::vec_equals_with_delta(structure.center_of_mass(), Vect3f::new([-center_of_mass[1], center_of_mass[0], 0.0]), 1e-5));
+    assert!(structure.octtree().has_voxel(Vect3i::new([0, 2, 0])));
+    assert!(!structure.octtree().has_voxel(Vect3i::new([2, 0, 0])));
+    let segment = Segm3f::new(Vect3f::new([0.0, 10.0, 0.0]), Vect3f::new([0.0, -10.0, 0.0]));
+    assert!(structure.ray_cast(segment).unwrap().coords == Vect3i::new([0, 2, 0]));
+
+    structure.rotate(2, 3);
+    assert!(structure == original);
+
+    structure.mirror(0);
+    assert!(structure.get_voxel(Vect3i::new([-2, 0, 0])).unwrap().id == heavy_voxel.id);
+    assert!(structure.center_of_mass()[0] == -center_of_mass[0]);
+    structure.mirror(0);
+    assert!(structure == original);
+
+    structure.translate(Vect3i::new([5, -5, 1]));
+    assert!(structure.get_voxel(Vect3i::new([7, -5, 1])).unwrap().id == heavy_voxel.id);
+    assert!(structure.get_box() == original.get_box() + Vect3f::new([5.0, -5.0, 1.0]));
+    assert!(!structure.octtree().has_voxel(Vect3i::new([2, 0, 0])));
+}
+
+#[test]
+fn structure_merge() {
+    use super::transform::VoxelTransform;
+
+    let catalog = VoxelCatalog::create();
+
+    let mut hull = Structure::new(&catalog, -2, 2, -1, 1, 0, 0, TEST_VOXEL);
+    let wing = Structure::new(&catalog, 0, 3, 0, 0, 0, 0, TEST_VOXEL);
+
+    // Along y from the hull center, it overlaps the hull on two voxels.
+    let overlapping = VoxelTransform::rotation(2, 1);
+    let conflicts = hull.merge(&wing, &overlapping).unwrap_err();
+    assert!(conflicts.len() == 2);
+    assert!(conflicts.contains(&Vect3i::new([0, 0, 0])) && conflicts.contains(&Vect3i::new([0, 1, 0])));
+    assert!(hull == Structure::new(&catalog, -2, 2, -1, 1, 0, 0, TEST_VOXEL));
+
+    let next_to_hull = overlapping.then(&VoxelTransform::translation(Vect3i::new([0, 2, 0])));
+    assert!(hull.merge(&wing, &next_to_hull).is_ok());
+    assert!(hull.mass() == 19.0 * hull.voxel_mass(&TEST_VOXEL));
+    assert!(hull.get_voxel(Vect3i::new([0, 5, 0])).is_some());
+    assert!(hull.octtree().has_voxel(Vect3i::new([0, 5, 0])));
+    assert!(hull.get_box() == Box3f::from_min_max(Vect3f::new([-2.5, -1.5, -0.5]), Vect3f::new([2.5, 5.5, 0.5])));
+}
+
+#[test]
+fn structure_boolean_operations() {
+    let catalog = VoxelCatalog::create();
+    let other_voxel = Voxel{life: 1.0, id: VoxelID::LIGHT_HULL};
+    let hull = Structure::new(&catalog, -2, 2, -2, 2, -2, 2, TEST_VOXEL);
+    let bay = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, other_voxel);
+
+    // A hangar bay opened on the top face.
+    let carved = hull.subtraction(&bay, Vect3i::new([0, 0, 2]));
+    assert!(carved.mass() == (125.0 - 18.0) * hull.voxel_mass(&TEST_VOXEL));
+    assert!(carved.get_voxel(Vect3i::new([0, 0, 1])).is_none());
+    assert!(carved.get_voxel(Vect3i::new([0, 0, 0])).is_some());
+    assert!(!carved.octtree().has_voxel(Vect3i::new([1, -1, 2])));
+    assert!(carved.octtree().has_voxel(Vect3i::new([2, -1, 2])));
+    assert!(carved.get_box() == hull.get_box());
+    let segment = Segm3f::new(Vect3f::new([0.0, 0.0, 10.0]), Vect3f::new([0.0, 0.0, -10.0]));
+    assert!(carved.ray_cast(segment).unwrap().coords == Vect3i::new([0, 0, 0]));
+
+    let common = hull.intersection(&bay, Vect3i::new([2, 2, 2]));
+    assert!(common == Structure::new(&catalog, 1, 2, 1, 2, 1, 2, TEST_VOXEL));
+    assert!(common.mass() == 8.0 * hull.voxel_mass(&TEST_VOXEL));
+    assert!(common.center_of_mass() == Vect3f::new([1.5, 1.5, 1.5]));
+
+    assert!(hull.intersection(&bay, Vect3i::new([10, 0, 0])).is_empty());
+    assert!(hull.subtraction(&hull, Vect3i::zero()).is_empty());
+
+    let union = hull.union(&bay, Vect3i::new([3, 0, 0]));
+    assert!(union.mass() == 125.0 * hull.voxel_mass(&TEST_VOXEL) + 18.0 * hull.voxel_mass(&other_voxel));
+    assert!(union.get_voxel(Vect3i::new([2, 0, 0])).unwrap().id == TEST_VOXEL.id);
+    assert!(union.get_voxel(Vect3i::new([4, 1, 1])).unwrap().id == other_voxel.id);
+    assert!(union.octtree().has_voxel(Vect3i::new([4, 1, 1])));
+    assert!(union.get_box() == Box3f::from_min_max(Vect3f::new([-2.5, -2.5, -2.5]), Vect3f::new([4.5, 2.5, 2.5])));
+}
+
+#[test]
+fn structure_components() {
+    use super::connectivity;
+    use super::connectivity::MainComponentRule;
+    use super::connectivity::Neighbourhood;
+
+    let catalog = VoxelCatalog::create();
+
+    let hull_voxel = Voxel{life: 1.0, id: VoxelID::LIGHT_HULL};
+    let core_voxel = Voxel{life: 1.0, id: VoxelID::SHIP_CORE};
+    let mut structure = Structure::new(&catalog, -3, -1, 0, 0, 0, 0, hull_voxel);
+    structure.add_voxel(Vect3i::new([0, 0, 0]), hull_voxel);
+    structure.add_voxel(Vect3i::new([2, 1, 0]), core_voxel);
+    // Only touching the previous one by an edge.
+    structure.add_voxel(Vect3i::new([3, 2, 0]), hull_voxel);
+    structure.remove_voxel(Vect3i::new([-1, 0, 0]));
+
+    let components = structure.components(Neighbourhood::Faces);
+    assert!(components.len() == 4);
+    let main_index = connectivity::main_component(&components, MainComponentRule::Origin).unwrap();
+    assert!(components[main_index].coords == vec![Vect3i::new([0, 0, 0])]);
+    let main_index = connectivity::main_component(&components, MainComponentRule::Mass).unwrap();
+    assert!(components[main_index].mass == 2.0 * structure.voxel_mass(&hull_voxel));
+    assert!(components[main_index].contains(Vect3i::new([-3, 0, 0])) && components[main_index].contains(Vect3i::new([-2, 0, 0])));
+    let main_index = connectivity::main_component(&components, MainComponentRule::CoreVoxel).unwrap();
+    assert!(components[main_index].has_core);
+    assert!(components[main_index].coords == vec![Vect3i::new([2, 1, 0])]);
+
+    assert!(structure.components(Neighbourhood::FacesAndEdges).len() == 3);
+    assert!(structure.components(Neighbourhood::FacesEdgesAndCorners).len() == 3);
+    // Touching the origin voxel by an edge, and the core voxel by a corner.
+    structure.add_voxel(Vect3i::new([1, 0, 1]), hull_voxel);
+    assert!(structure.components(Neighbourhood::FacesAndEdges).len() == 3);
+    assert!(structure.components(Neighbourhood::FacesEdgesAndCorners).len() == 2);
+    assert!(connectivity::main_component(&structure.components(Neighbourhood::Faces), MainComponentRule::CoreVoxel).is_some());
+    assert!(connectivity::main_component(&Structure::new(&catalog, 0, 1, 0, 0, 0, 0, hull_voxel).components(Neighbourhood::Faces), MainComponentRule::CoreVoxel).is_none());
+}
+
+#[test]
+fn structure_split_components() {
+    use super::connectivity::Neighbourhood;
+
+    let catalog = VoxelCatalog::create();
+
+    let mut structure = Structure::new(&catalog, -3, 3, -1, 1, 0, 0, TEST_VOXEL);
+    structure.remove_voxel(Vect3i::new([0, 0, 0]));
+    structure.remove_voxel(Vect3i::new([0, 1, 0]));
+    let neighbours = [Vect3i::new([-1, 0, 0]), Vect3i::new([1, 0, 0])];
+    // Still held by the bottom row.
+    assert!(structure.split_components(&neighbours, Neighbourhood::Faces).is_none());
+
+    structure.remove_voxel(Vect3i::new([0, -1, 0]));
+    let components = structure.split_components(&neighbours, Neighbourhood::Faces).unwrap();
+    assert!(components.len() == 2);
+    assert!(components.iter().all(|component| component.coords.len() == 9 && component.mass == 9.0 * structure.voxel_mass(&TEST_VOXEL)));
+}
+
+#[test]
+fn structure_articulation_voxels() {
+    use super::connectivity::Neighbourhood;
+
+    let catalog = VoxelCatalog::create();
+
+    let bar = Structure::new(&catalog, 0, 4, 0, 0, 0, 0, TEST_VOXEL);
+    assert!(bar.articulation_voxels(Neighbourhood::Faces) == vec![Vect3i::new([1, 0, 0]), Vect3i::new([2, 0, 0]), Vect3i::new([3, 0, 0])]);
+
+    // A plate has no weak point, until a mast is added on it.
+    let mut structure = Structure::new(&catalog, -1, 1, -1, 1, 0, 0, TEST_VOXEL);
+    assert!(structure.articulation_voxels(Neighbourhood::Faces).is_empty());
+    structure.add_voxel(Vect3i::new([0, 0, 1]), TEST_VOXEL);
+    structure.add_voxel(Vect3i::new([0, 0, 2]), TEST_VOXEL);
+    assert!(structure.articulation_voxels(Neighbourhood::Faces) == vec![Vect3i::new([0, 0, 0]), Vect3i::new([0, 0, 1])]);
+    // The mast base also touches the plate by its edges.
+    assert!(structure.articulation_voxels(Neighbourhood::FacesAndEdges) == vec![Vect3i::new([0, 0, 1])]);
+}
+
+fn vox_chunk(id: &[u8; 4], content: &[u8], children: &[u8]) -> Vec<u8> {
+    let mut result = id.to_vec();
+    result.extend_from_slice(&(content.len() as u32).to_le_bytes());
+    result.extend_from_slice(&(children.len() as u32).to_le_bytes());
+    result.extend_from_slice(content);
+    result.extend_from_slice(children);
+    result
+}
+
+fn vox_file(size: [i32; 3], voxels: &[[u8; 4]]) -> Vec<u8> {
+    let size_content: Vec<u8> = size.iter().flat_map(|value| value.to_le_bytes()).collect();
+    let mut xyzi_content = (voxels.len() as u32).to_le_bytes().to_vec();
+    xyzi_content.extend(voxels.iter().flatten());
+    let mut children = vox_chunk(b"PACK", &1_u32.to_le_bytes(), &[]);
+    children.extend(vox_chunk(b"SIZE", &size_content, &[]));
+    children.extend(vox_chunk(b"XYZI", &xyzi_content, &[]));
+    children.extend(vox_chunk(b"nTRN", &[0; 12], &[]));
+    let mut result = b"VOX ".to_vec();
+    result.extend_from_slice(&150_i32.to_le_bytes());
+    result.extend(vox_chunk(b"MAIN", &[], &children));
+    result
+}
+
+#[test]
+fn structure_vox_import() {
+    use super::vox::VoxPalette;
+
+    let catalog = VoxelCatalog::create();
+    let palette = VoxPalette::create(&catalog);
+    let bytes = vox_file([4, 3, 1], &[[0, 0, 0, 1], [3, 2, 0, 2], [2, 1, 0, 2]]);
+    let structure = Structure::deserialize_vox(&catalog, &palette, &bytes).unwrap();
+
+    let mut expected = Structure::new_empty(&catalog);
+    expected.add_voxel(Vect3i::new([-2, -1, 0]), catalog.create_voxel(VoxelID::SHIP_CORE));
+    expected.add_voxel(Vect3i::new([1, 1, 0]), catalog.create_voxel(VoxelID::LIGHT_HULL));
+    expected.add_voxel(Vect3i::new([0, 0, 0]), catalog.create_voxel(VoxelID::LIGHT_HULL));
+    expected.recalculate_box();
+    assert!(structure == expected);
+    assert!(structure.mass() == expected.mass());
+    assert!(structure.octtree().has_voxel(Vect3i::new([1, 1, 0])));
+    assert!(Structure::deserialize(&catalog, &structure.serialize()).unwrap() == structure);
+
+    let unmapped = vox_file([4, 3, 1], &[[0, 0, 0, 1], [1, 0, 0, 200]]);
+    assert!(matches!(Structure::deserialize_vox(&catalog, &palette, &unmapped), Err(LoadError::UnmappedPaletteIndex { index: 200 })));
+    let out_of_size = vox_file([4, 3, 1], &[[0, 3, 0, 1]]);
+    assert!(matches!(Structure::deserialize_vox(&catalog, &palette, &out_of_size), Err(LoadError::CorruptedBinary { .. })));
+    let truncated = &bytes[..bytes.len() - 20];
+    assert!(matches!(Structure::deserialize_vox(&catalog, &palette, truncated), Err(LoadError::CorruptedBinary { .. })));
+    assert!(matches!(Structure::deserialize_vox(&catalog, &palette, &structure.serialize_binary().unwrap()), Err(LoadError::CorruptedBinary { offset: 0, .. })));
+}
+
+#[test]
+fn structure_vox_round_trip() {
+    use super::error::SaveError;
+    use super::vox::VoxPalette;
+
+    let catalog = VoxelCatalog::create();
+    let palette = VoxPalette::create(&catalog);
+    let tie_fighter = Structure::read_from_file(&catalog, "structures/tie.vors").unwrap();
+    let bytes = tie_fighter.serialize_vox(&palette).unwrap();
+    let imported = Structure::deserialize_vox(&catalog, &palette, &bytes).unwrap();
+    assert!(imported == tie_fighter);
+    assert!(Structure::deserialize(&catalog, &imported.serialize()).unwrap() == imported);
+    assert!(imported.serialize_vox(&palette).unwrap() == bytes);
+
+    // Not centered on the origin.
+    let offset_structure = Structure::new(&catalog, 2, 5, -7, -6, 0, 0, catalog.create_voxel(VoxelID::LIGHT_HULL));
+    let imported = Structure::deserialize_vox(&catalog, &palette, &offset_structure.serialize_vox(&palette).unwrap()).unwrap();
+    assert!(imported == offset_structure);
+
+    let too_big = Structure::new(&catalog, 0, 200, 0, 0, 0, 0, catalog.create_voxel(VoxelID::LIGHT_HULL));
+    assert!(matches!(too_big.serialize_vox(&palette), Err(SaveError::TooBig { axis: 'x', size: 401, .. })));
+    let other_catalog = VoxelCatalog::deserialize("42;Unmapped;1;Core;1;0;0;0;0;None;0;0\n").unwrap();
+    let unknown_voxel = Structure::new(&other_catalog, 0, 0, 0, 0, 0, 0, other_catalog.create_voxel(VoxelID(42)));
+    assert!(matches!(unknown_voxel.serialize_vox(&palette), Err(SaveError::UnmappedVoxelID { id: 42 })));
+}
+
+#[test]
+fn vox_palette_deserialization() {
+    use super::vox::VoxPalette;
+
+    let catalog = VoxelCatalog::create();
+    let palette = VoxPalette::deserialize(&catalog, "# Comment\n\n10;1;255;0;0\n11;1;0;255;0\n12;0;0;0;255\n").unwrap();
+    assert!(palette.voxel_id(10) == Some(VoxelID::LIGHT_HULL));
+    assert!(palette.voxel_id(11) == Some(VoxelID::LIGHT_HULL));
+    assert!(palette.voxel_id(1).is_none());
+    // The first index is used on export.
+    assert!(palette.palette_index(VoxelID::LIGHT_HULL) == Some(10));
+    assert!(palette.palette_index(VoxelID::SHIP_CORE) == Some(12));
+    assert!(palette.color(11) == [0, 255, 0, 255]);
+
+    assert!(matches!(VoxPalette::deserialize(&catalog, "10;1;255;0;0\n10;0;0;0;0"), Err(LoadError::DuplicatePaletteIndex { line: 2, index: 10 })));
+    assert!(matches!(VoxPalette::deserialize(&catalog, "10;42;255;0;0"), Err(LoadError::InvalidVoxelID { line: 1, id: 42 })));
+    assert!(matches!(VoxPalette::deserialize(&catalog, "0;1;255;0;0"), Err(LoadError::InvalidDefinition { line: 1, .. })));
+    assert!(matches!(VoxPalette::deserialize(&catalog, "10;1;256;0;0"), Err(LoadError::InvalidNumber { line: 1, .. })));
+    assert!(matches!(VoxPalette::deserialize(&catalog, "10;1;255;0"), Err(LoadError::InvalidDefinition { line: 1, .. })));
+}
+
+#[test]
+fn surface_mesh_faces() {
+    use super::mesh::SurfaceMesh;
+
+    let catalog = VoxelCatalog::create();
+
+    assert!(SurfaceMesh::from_structure(&Structure::new(&catalog, 0, 0, 0, 0, 0, 0, TEST_VOXEL), &catalog).quads().len() == 6);
+    assert!(SurfaceMesh::from_structure(&Structure::new(&catalog, 0, 1, 0, 0, 0, 0, TEST_VOXEL), &catalog).quads().len() == 10);
+    let mut cube = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    let mesh = SurfaceMesh::from_structure(&cube, &catalog);
+    assert!(mesh.quads().len() == 54);
+    for quad in mesh.quads() {
+        // Outside the cube, counterclockwise seen from outside.
+        let center = quad.corners.iter().sum::<Vect3f>() / 4.0;
+        let normal = Vect3f::new([quad.normal[0] as f32, quad.normal[1] as f32, quad.normal[2] as f32]);
+        assert!(Vect3f::dot(center, normal) == 1.5);
+        let winding = Vect3f::cross(quad.corners[1] - quad.corners[0], quad.corners[2] - quad.corners[1]);
+        assert!(winding == normal);
+    }
+
+    // A hidden cavity has its own faces.
+    cube.remove_voxel(Vect3i::zero());
+    assert!(SurfaceMesh::from_structure(&cube, &catalog).quads().len() == 60);
+}
+
+#[test]
+fn surface_mesh_export() {
+    use super::mesh::SurfaceMesh;
+
+    let catalog = VoxelCatalog::create();
+    let mut structure = Structure::new(&catalog, 0, 1, 0, 0, 0, 0, catalog.create_voxel(VoxelID::LIGHT_HULL));
+    structure.add_voxel(Vect3i::new([2, 0, 0]), catalog.create_voxel(VoxelID::SHIP_CORE));
+    let mesh = SurfaceMesh::from_structure(&structure, &catalog);
+    assert!(mesh.quads().len() == 14);
+
+    let obj = mesh.serialize_obj(&catalog, "bar.mtl");
+    let lines: Vec<&str> = obj.lines().collect();
+    assert!(lines.contains(&"mtllib bar.mtl"));
+    // Corners are shared between faces.
+    assert!(lines.iter().filter(|line| line.starts_with("v ")).count() == 16);
+    assert!(lines.iter().filter(|line| line.starts_with("vn ")).count() == 6);
+    assert!(lines.iter().filter(|line| line.starts_with("f ")).count() == 14);
+    let core_group = lines.iter().position(|line| *line == "usemtl ShipCore").unwrap();
+    let hull_group = lines.iter().position(|line| *line == "usemtl LightHull").unwrap();
+    assert!(hull_group - core_group == 6);
+    assert!(lines[core_group + 1].starts_with("f ") && lines[core_group + 1].split_whitespace().all(|field| field == "f" || field.contains("//")));
+
+    let mtl = mesh.serialize_mtl(&catalog);
+    assert!(mtl.lines().filter(|line| line.starts_with("newmtl ")).count() == 2);
+    assert!(mtl.contains("newmtl ShipCore\nKd "));
+
+    let ply = mesh.serialize_ply(&catalog);
+    let header_end = b"end_header\n";
+    let header_size = ply.windows(header_end.len()).position(|window| window == header_end).unwrap() + header_end.len();
+    let header = String::from_utf8_lossy(&ply[..header_size]);
+    assert!(header.starts_with("ply\nformat binary_little_endian 1.0\n"));
+    assert!(header.contains("element vertex 56\n") && header.contains("element face 14\n"));
+    assert!(ply.len() == header_size + 56 * (6 * 4 + 3) + 14 * (1 + 4 * 4));
+    let first_x = f32::from_le_bytes(ply[header_size..header_size + 4].try_into().unwrap());
+    assert!(first_x == 0.5 || first_x == -0.5);
+
+    // Merged quads are exported the same way.
+    let greedy_obj = SurfaceMesh::greedy_from_structure(&structure, &catalog).serialize_obj(&catalog, "bar.mtl");
+    assert!(greedy_obj.lines().filter(|line| line.starts_with("f ")).count() == 10);
+    assert!(greedy_obj.lines().filter(|line| line.starts_with("usemtl ")).count() == 2);
+}
+
+#[test]
+fn greedy_mesh_faces() {
+    use super::mesh::SurfaceMesh;
+
+    let catalog = VoxelCatalog::create();
+    let hull_voxel = catalog.create_voxel(VoxelID::LIGHT_HULL);
+    let face_count = |structure: &Structure| SurfaceMesh::greedy_from_structure(structure, &catalog).quads().len();
+
+    assert!(face_count(&Structure::new(&catalog, 0, 0, 0, 0, 0, 0, hull_voxel)) == 6);
+    let mut cube = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, hull_voxel);
+    let mesh = SurfaceMesh::greedy_from_structure(&cube, &catalog);
+    assert!(mesh.quads().len() == 6);
+    for quad in mesh.quads() {
+        // Each side of the cube in a single quad, counterclockwise seen from outside.
+        let center = quad.corners.iter().sum::<Vect3f>() / 4.0;
+        let normal = Vect3f::new([quad.normal[0] as f32, quad.normal[1] as f32, quad.normal[2] as f32]);
+        assert!(Vect3f::dot(center, normal) == 1.5);
+        let winding = Vect3f::cross(quad.corners[1] - quad.corners[0], quad.corners[2] - quad.corners[1]);
+        assert!(winding == normal * 9.0);
+        assert!(quad.corners.iter().all(|corner| corner[0] >= quad.corners[0][0] && corner[1] >= quad.corners[0][1] && corner[2] >= quad.corners[0][2]));
+        assert!(quad.damage == 0.0);
+    }
+
+    // A hidden cavity has its own faces.
+    cube.remove_voxel(Vect3i::zero());
+    assert!(face_count(&cube) == 12);
+
+    // L shape: the sides in the L plane stay whole, the inner corner splits the others.
+    let mut l_shape = Structure::new(&catalog, 0, 2, 0, 0, 0, 0, hull_voxel);
+    l_shape.add_voxel(Vect3i::new([0, 1, 0]), hull_voxel);
+    l_shape.add_voxel(Vect3i::new([0, 2, 0]), hull_voxel);
+    assert!(face_count(&l_shape) == 10);
+
+    // A bar with a different voxel type in its middle.
+    let mut bar = Structure::new(&catalog, 0, 2, 0, 0, 0, 0, hull_voxel);
+    bar.add_voxel(Vect3i::new([1, 0, 0]), catalog.create_voxel(VoxelID::SHIP_CORE));
+    let mesh = SurfaceMesh::greedy_from_structure(&bar, &catalog);
+    assert!(mesh.quads().len() == 14);
+    assert!(mesh.quads().iter().filter(|quad| quad.voxel_id == VoxelID::SHIP_CORE).count() == 4);
+
+    // Damaged voxels do not merge with intact ones.
+    let mut plate = Structure::new(&catalog, 0, 3, 0, 3, 0, 0, hull_voxel);
+    assert!(face_count(&plate) == 6);
+    plate.for_first_voxel_in_segment(Segm3f::new(Vect3f::new([0.0, 0.0, 5.0]), Vect3f::new([0.0, 0.0, -5.0])), |voxel, _| voxel.life /= 2.0);
+    let mesh = SurfaceMesh::greedy_from_structure(&plate, &catalog);
+    assert!(mesh.quads().len() == 12);
+    assert!(mesh.quads().iter().filter(|quad| quad.damage == 0.5).count() == 4);
+}
+
+#[test]
+fn structure_surface_mesh_cache() {
+    let catalog = VoxelCatalog::create();
+    let mut structure = Structure::new(&catalog, 0, 3, 0, 0, 0, 0, catalog.create_voxel(VoxelID::LIGHT_HULL));
+    let first_mesh: *const _ = structure.surface_mesh(&catalog);
+    assert!(std::ptr::eq(first_mesh, structure.surface_mesh(&catalog)));
+    assert!(structure.surface_mesh(&catalog).quads().len() == 6);
+
+    structure.add_voxel(Vect3i::new([0, 1, 0]), catalog.create_voxel(VoxelID::LIGHT_HULL));
+    assert!(structure.surface_mesh(&catalog).quads().len() == 10);
+    structure.remove_voxel(Vect3i::new([0, 1, 0]));
+    assert!(structure.surface_mesh(&catalog).quads().len() == 6);
+
+    let segment = Segm3f::new(Vect3f::new([3.0, 0.0, 5.0]), Vect3f::new([3.0, 0.0, -5.0]));
+    structure.for_first_voxel_in_segment(segment, |voxel, _| voxel.life = 0.0);
+    assert!(structure.surface_mesh(&catalog).quads().len() == 10);
+    structure.erase_dead_voxels();
+    assert!(structure.surface_mesh(&catalog).quads().len() == 6);
+    assert!(structure.surface_mesh(&catalog).quads().iter().all(|quad| quad.damage == 0.0));
+}
+
+#[test]
+fn asteroid_generation() {
+    use super::connectivity::Neighbourhood;
+    use super::generation::AsteroidParameters;
+    use super::generation::generate_asteroid;
+
+    let catalog = VoxelCatalog::create();
+    let parameters = AsteroidParameters {
+        radius: 6.0,
+        roughness: 0.3,
+        layers: vec![VoxelID::LIGHT_HULL, VoxelID::HEAVY_HULL],
+    };
+    let asteroid = generate_asteroid(&catalog, &parameters, 12);
+    assert!(asteroid == generate_asteroid(&catalog, &parameters, 12));
+    assert!(asteroid != generate_asteroid(&catalog, &parameters, 13));
+
+    assert!(asteroid.components(Neighbourhood::Faces).len() == 1);
+    assert!(asteroid.has_voxel_on_coords(Vect3i::zero()));
+    let mut voxel_counts = [0; 2];
+    asteroid.for_each_voxel(|coords, voxel| {
+        let distance = Vect3f::new([coords[0] as f32, coords[1] as f32, coords[2] as f32]).length();
+        assert!(distance <= 6.0 * 1.3);
+        voxel_counts[if voxel.id == VoxelID::LIGHT_HULL { 0 } else { 1 }] += 1;
+    });
+    // Mixed voxel types, the surface one being the only one at the center.
+    assert!(voxel_counts[0] > 0 && voxel_counts[1] > 0);
+    assert!(asteroid.get_voxel(Vect3i::zero()).unwrap().id == VoxelID::HEAVY_HULL);
+    // Rough, yet about the volume of the ball.
+    let ball_volume = 4.0 / 3.0 * std::f32::consts::PI * 6.0_f32.powi(3);
+    let voxel_count = (voxel_counts[0] + voxel_counts[1]) as f32;
+    assert!(voxel_count > ball_volume * 0.4 && voxel_count < ball_volume * 2.0);
+
+    let ball = generate_asteroid(&catalog, &AsteroidParameters { radius: 2.0, roughness: 0.0, layers: vec![VoxelID::LIGHT_HULL] }, 1);
+    assert!(ball == generate_asteroid(&catalog, &AsteroidParameters { radius: 2.0, roughness: 0.0, layers: vec![VoxelID::LIGHT_HULL] }, 2));
+    assert!(ball.get_box() == Box3f::from_min_max(Vect3f::all(-2.5), Vect3f::all(2.5)));
+}
+
+#[test]
+fn derelict_generation() {
+    use super::connectivity::Neighbourhood;
+    use super::generation::DerelictParameters;
+    use super::generation::generate_derelict;
+
+    let catalog = VoxelCatalog::create();
+    let hull_voxel = catalog.create_voxel(VoxelID::LIGHT_HULL);
+    let mut parts = vec![Structure::new(&catalog, 0, 4, 0, 0, 0, 0, hull_voxel), Structure::new(&catalog, -1, 1, -1, 1, 0, 0, hull_voxel)];
+    parts[1].add_voxel(Vect3i::zero(), catalog.create_voxel(VoxelID::SHIP_CORE));
+
+    let intact = DerelictParameters {
+        part_count: 4,
+        missing_voxel_ratio: 0.0,
+        damaged_voxel_ratio: 0.0,
+    };
+    for seed in 0..20 {
+        let derelict = generate_derelict(&parts, &intact, seed);
+        assert!(derelict == generate_derelict(&parts, &intact, seed));
+        assert!(derelict.components(Neighbourhood::Faces).len() == 1);
+        let mut voxel_count = 0;
+        derelict.for_each_voxel(|_, voxel| {
+            voxel_count += 1;
+            assert!(voxel.life == catalog.get_descriptor(voxel.id).max_life);
+        });
+        // Every part found a place, each one having 5 or 9 voxels.
+        assert!(voxel_count >= 4 * 5 && voxel_count <= 4 * 9);
+        assert!((voxel_count - 4 * 5) % 4 == 0);
+    }
+
+    let wrecked = DerelictParameters {
+        part_count: 6,
+        missing_voxel_ratio: 0.2,
+        damaged_voxel_ratio: 0.5,
+    };
+    let derelict = generate_derelict(&parts, &wrecked, 3);
+    assert!(derelict == generate_derelict(&parts, &wrecked, 3));
+    assert!(derelict != generate_derelict(&parts, &wrecked, 4));
+    assert!(derelict.components(Neighbourhood::Faces).len() == 1);
+    let mut damaged_count = 0;
+    let mut voxel_count = 0;
+    derelict.for_each_voxel(|_, voxel| {
+        voxel_count += 1;
+        if voxel.life < catalog.get_descriptor(voxel.id).max_life {
+            damaged_count += 1;
+        }
+    });
+    assert!(voxel_count < 6 * 9 && damaged_count > 0);
+}
+
+#[test]
+fn structure_area_damage() {
+    use super::damage::AreaDamage;
+
+    let catalog = VoxelCatalog::create();
+    let sturdy_voxel = Voxel { id: VoxelID::LIGHT_HULL, life: 100.0};
+    let life = |structure: &Structure, x: i32| structure.get_voxel(Vect3i::new([x, 0, 0])).unwrap().life;
+
+    // Falloff with the distance, each voxel in the way halving the damage.
+    let mut bar = Structure::new(&catalog, 0, 6, 0, 0, 0, 0, sturdy_voxel);
+    let area_damage = AreaDamage { center: Vect3f::new([-1.0, 0.0, 0.0]), radius: 5.0, damage: 10.0, damage_type: DamageType::Kinetic, transmission: 0.5 };
+    let damaged_coords = bar.apply_area_damage(&catalog, &area_damage);
+    assert!(damaged_coords == (0..4).map(|x| Vect3i::new([x, 0, 0])).collect::<Vec<Vect3i>>());
+    assert!(testing::equals_with_delta(life(&bar, 0), 100.0 - 8.0, 0.0001));
+    assert!(testing::equals_with_delta(life(&bar, 1), 100.0 - 6.0 * 0.5, 0.0001));
+    assert!(testing::equals_with_delta(life(&bar, 2), 100.0 - 4.0 * 0.25, 0.0001));
+    assert!(testing::equals_with_delta(life(&bar, 3), 100.0 - 2.0 * 0.125, 0.0001));
+    assert!(life(&bar, 4) == 100.0);
+
+    // Full occlusion.
+    let mut bar = Structure::new(&catalog, 0, 6, 0, 0, 0, 0, sturdy_voxel);
+    assert!(bar.apply_area_damage(&catalog, &AreaDamage { transmission: 0.0, ..area_damage }) == vec![Vect3i::zero()]);
+
+    // The voxel holding the center does not shield the others.
+    let mut bar = Structure::new(&catalog, 0, 6, 0, 0, 0, 0, sturdy_voxel);
+    let area_damage = AreaDamage { center: Vect3f::new([3.0, 0.0, 0.0]), radius: 5.0, damage: 10.0, damage_type: DamageType::Kinetic, transmission: 0.0 };
+    assert!(bar.apply_area_damage(&catalog, &area_damage).len() == 3);
+    assert!(life(&bar, 3) == 90.0);
+    assert!(testing::equals_with_delta(life(&bar, 2), 92.0, 0.0001));
+    assert!(testing::equals_with_delta(life(&bar, 4), 92.0, 0.0001));
+
+    // Damage is lowered by the resistance of each voxel type.
+    let mut bar = Structure::new(&catalog, 0, 6, 0, 0, 0, 0, sturdy_voxel);
+    bar.add_voxel(Vect3i::new([4, 0, 0]), Voxel { id: VoxelID::HEAVY_HULL, ..sturdy_voxel });
+    let heavy_hull = catalog.get_descriptor(VoxelID::HEAVY_HULL);
+    bar.apply_area_damage(&catalog, &AreaDamage { damage_type: DamageType::Explosive, ..area_damage });
+    assert!(testing::equals_with_delta(life(&bar, 4), 100.0 - heavy_hull.effective_damage(8.0, DamageType::Explosive), 0.0001));
+    assert!(heavy_hull.effective_damage(8.0, DamageType::Explosive) < 8.0);
+
+    // Dead voxels stay until erased, and the mesh sees the damage.
+    let mut cube = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, catalog.create_voxel(VoxelID::LIGHT_HULL));
+    assert!(cube.surface_mesh(&catalog).quads().len() == 6);
+    let area_damage = AreaDamage { center: Vect3f::new([1.0, 1.0, 1.0]), radius: 1.5, damage: 100.0, damage_type: DamageType::Kinetic, transmission: 1.0 };
+    assert!(cube.apply_area_damage(&catalog, &area_damage).len() == 7);
+    assert!(cube.surface_mesh(&catalog).quads().iter().any(|quad| quad.damage > 0.0));
+    assert!(cube.erase_dead_voxels().len() == 7);
+    assert!(!cube.has_voxel_on_coords(Vect3i::new([1, 1, 1])) && !cube.has_voxel_on_coords(Vect3i::new([0, 1, 1])));
+    assert!(cube.has_voxel_on_coords(Vect3i::new([-1, 1, 1])));
+
+    // Only the structure box is visited, whatever the radius, and nothing is visited when the sphere is out of it.
+    let mut cube = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, catalog.create_voxel(VoxelID::LIGHT_HULL));
+    let area_damage = AreaDamage { center: Vect3f::zero(), radius: 1e6, damage: 1e6, damage_type: DamageType::Kinetic, transmission: 1.0 };
+    assert!(cube.apply_area_damage(&catalog, &area_damage).len() == 27);
+    let area_damage = AreaDamage { center: Vect3f::all(1e6), radius: 10.0, ..area_damage };
+    assert!(cube.apply_area_damage(&catalog, &area_damage).is_empty());
+}
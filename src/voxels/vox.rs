@@ -0,0 +1,231 @@
+use std::collections::HashMap;
+
+use crate::maths::vector::Vect3i;
+use super::catalog::VoxelCatalog;
+use super::error::LoadError;
+use super::error::SaveError;
+use super::structure::ByteReader;
+use super::structure::Structure;
+use super::voxel::VoxelID;
+
+// Maps MagicaVoxel palette indices to voxel types, and voxel types back to palette indices and colors.
+pub struct VoxPalette {
+    voxel_ids: HashMap<u8, VoxelID>,
+    palette_indices: HashMap<VoxelID, u8>,
+    colors: HashMap<u8, [u8; 4]>,
+}
+
+impl VoxPalette {
+    pub const DEFAULT_FILE_NAME: &'static str = "catalog/vox_palette.def";
+    const SEPARATOR: &'static str = ";";
+    const COMMENT: &'static str = "#";
+    const UNMAPPED_COLOR: [u8; 4] = [128, 128, 128, 255];
+
+    pub fn create(catalog: &VoxelCatalog) -> Self {
+        Self::read_from_file(catalog, Self::DEFAULT_FILE_NAME).unwrap_or_else(|error| panic!("Unable to load MagicaVoxel palette: {error}"))
+    }
+
+    pub fn read_from_file(catalog: &VoxelCatalog, file_name: &str) -> Result<Self, LoadError> {
+        let palette_file_content = std::fs::read_to_string(file_name).map_err(|error| LoadError::Io { file_name: file_name.to_string(), error: error })?;
+        Self::deserialize(catalog, &palette_file_content)
+    }
+
+    // One palette index per line: palette_index;voxel_id;red;green;blue
+    // Empty lines and lines starting with '#' are ignored.
+    pub fn deserialize(catalog: &VoxelCatalog, str: &str) -> Result<Self, LoadError> {
+        fn parse_number<T: std::str::FromStr>(line: usize, value: &str) -> Result<T, LoadError> {
+            value.trim().parse().map_err(|_| LoadError::InvalidNumber { line: line, value: value.to_string() })
+        }
+
+        let mut result = Self {
+            voxel_ids: Default::default(),
+            palette_indices: Default::default(),
+            colors: Default::default(),
+        };
+        for (index, line) in str.lines().enumerate() {
+            let line_number = index + 1;
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(Self::COMMENT) {
+                continue;
+            }
+
+            let fields: Vec<&str> = line.split(Self::SEPARATOR).map(|field| field.trim()).collect();
+            if fields.len() != 5 {
+                return Err(LoadError::InvalidDefinition { line: line_number, content: line.to_string() });
+            }
+            let palette_index: u8 = parse_number(line_number, fields[0])?;
+            if palette_index == 0 {
+                return Err(LoadError::InvalidDefinition { line: line_number, content: line.to_string() });
+            }
+            let id = VoxelID(parse_number(line_number, fields[1])?);
+            if !catalog.contains(id) {
+                return Err(LoadError::InvalidVoxelID { line: line_number, id: id.0 });
+            }
+            let color = [parse_number(line_number, fields[2])?, parse_number(line_number, fields[3])?, parse_number(line_number, fields[4])?, 255];
+            if result.voxel_ids.contains_key(&palette_index) {
+                return Err(LoadError::DuplicatePaletteIndex { line: line_number, index: palette_index });
+            }
+
+            result.voxel_ids.insert(palette_index, id);
+            result.palette_indices.entry(id).or_insert(palette_index);
+            result.colors.insert(palette_index, color);
+        }
+        Ok(result)
+    }
+
+    pub fn voxel_id(&self, palette_index: u8) -> Option<VoxelID> {
+        self.voxel_ids.get(&palette_index).copied()
+    }
+
+    pub fn palette_index(&self, id: VoxelID) -> Option<u8> {
+        self.palette_indices.get(&id).copied()
+    }
+
+    pub fn color(&self, palette_index: u8) -> [u8; 4] {
+        self.colors.get(&palette_index).copied().unwrap_or(Self::UNMAPPED_COLOR)
+    }
+}
+
+// MagicaVoxel files: a MAIN chunk whose children are a SIZE chunk, a XYZI chunk and optionally a RGBA chunk,
+// see https://github.com/ephtracy/voxel-model/blob/master/MagicaVoxel-file-format-vox.txt
+// Each chunk is its ID, its content size, its children size, its content and its children.
+// Only the first model of a file is read, other chunks are skipped.
+//
+// Model coordinates go from 0 to the size, they are moved so that the model center is on the structure origin.
+// Axes are kept as they are.
+impl Structure {
+    const VOX_MAGIC: &'static [u8; 4] = b"VOX ";
+    const VOX_VERSION: i32 = 150;
+    const VOX_MAX_SIZE: i32 = 256;
+    pub const VOX_EXTENSION: &'static str = "vox";
+
+    pub fn serialize_vox(&self, palette: &VoxPalette) -> Result<Vec<u8>, SaveError> {
+        // Smallest size holding the box once centered.
+        let mut size = Vect3i::zero();
+        for axis in 0..3 {
+            size[axis] = (-2 * self.voxel_box().min()[axis]).max(2 * self.voxel_box().max()[axis] + 1).max(1);
+            if size[axis] > Self::VOX_MAX_SIZE {
+                return Err(SaveError::TooBig { axis: ['x', 'y', 'z'][axis], size: size[axis], max_size: Self::VOX_MAX_SIZE });
+            }
+        }
+
+        let mut voxels = vec![];
+        let mut unmapped_id = None;
+        self.for_each_voxel(|coords, voxel| {
+            match palette.palette_index(voxel.id) {
+                Some(palette_index) => {
+                    let vox_coords = coords + Vect3i::new([size[0] / 2, size[1] / 2, size[2] / 2]);
+                    voxels.extend_from_slice(&[vox_coords[0] as u8, vox_coords[1] as u8, vox_coords[2] as u8, palette_index]);
+                },
+                None => unmapped_id = Some(voxel.id),
+            }
+        });
+        if let Some(id) = unmapped_id {
+            return Err(SaveError::UnmappedVoxelID { id: id.0 });
+        }
+
+        let mut size_content = vec![];
+        for axis in 0..3 {
+            size_content.extend_from_slice(&size[axis].to_le_bytes());
+        }
+        let mut xyzi_content = ((voxels.len() / 4) as u32).to_le_bytes().to_vec();
+        xyzi_content.extend(voxels);
+        // Color of palette index i is the entry i - 1.
+        let rgba_content: Vec<u8> = (1..=255).chain([0]).flat_map(|palette_index| palette.color(palette_index)).collect();
+
+        let mut children = vec![];
+        Self::write_vox_chunk(&mut children, b"SIZE", &size_content, &[]);
+        Self::write_vox_chunk(&mut children, b"XYZI", &xyzi_content, &[]);
+        Self::write_vox_chunk(&mut children, b"RGBA", &rgba_content, &[]);
+        let mut result = Self::VOX_MAGIC.to_vec();
+        result.extend_from_slice(&Self::VOX_VERSION.to_le_bytes());
+        Self::write_vox_chunk(&mut result, b"MAIN", &[], &children);
+        Ok(result)
+    }
+
+    fn write_vox_chunk(result: &mut Vec<u8>, id: &[u8; 4], content: &[u8], children: &[u8]) {
+        result.extend_from_slice(id);
+        result.extend_from_slice(&(content.len() as u32).to_le_bytes());
+        result.extend_from_slice(&(children.len() as u32).to_le_bytes());
+        result.extend_from_slice(content);
+        result.extend_from_slice(children);
+    }
+
+    pub fn deserialize_vox(catalog: &VoxelCatalog, palette: &VoxPalette, bytes: &[u8]) -> Result<Self, LoadError> {
+        let mut reader = ByteReader::new(bytes);
+        if reader.read_bytes(Self::VOX_MAGIC.len())? != Self::VOX_MAGIC {
+            return Err(LoadError::CorruptedBinary { offset: 0, reason: "not a MagicaVoxel file" });
+        }
+        reader.read_i32()?;
+
+        let main_offset = reader.offset;
+        if reader.read_bytes(4)? != b"MAIN" {
+            return Err(LoadError::CorruptedBinary { offset: main_offset, reason: "missing MAIN chunk" });
+        }
+        let main_content_size = reader.read_u32()? as usize;
+        let main_children_size = reader.read_u32()? as usize;
+        reader.read_bytes(main_content_size)?;
+        let mut children = ByteReader::new(reader.read_bytes(main_children_size)?);
+        let children_offset = reader.offset - main_children_size;
+
+        let mut size = None;
+        let mut model = None;
+        while !children.is_at_end() && model.is_none() {
+            let chunk_offset = children_offset + children.offset;
+            let id = children.read_bytes(4)?;
+            let content_size = children.read_u32()? as usize;
+            let children_size = children.read_u32()? as usize;
+            let content = children.read_bytes(content_size)?;
+            children.read_bytes(children_size)?;
+            match id {
+                b"SIZE" if size.is_none() => {
+                    let mut content_reader = ByteReader::new(content);
+                    let mut model_size = Vect3i::zero();
+                    for axis in 0..3 {
+                        model_size[axis] = content_reader.read_i32().map_err(|_| LoadError::CorruptedBinary { offset: chunk_offset, reason: "truncated SIZE chunk" })?;
+                        if model_size[axis] <= 0 || model_size[axis] > Self::VOX_MAX_SIZE {
+                            return Err(LoadError::CorruptedBinary { offset: chunk_offset, reason: "model size out of range" });
+                        }
+                    }
+                    size = Some(model_size);
+                },
+                b"XYZI" => {
+                    if size.is_none() {
+                        return Err(LoadError::CorruptedBinary { offset: chunk_offset, reason: "XYZI chunk before SIZE chunk" });
+                    }
+                    model = Some((chunk_offset, content));
+                },
+                _ => (),
+            }
+        }
+        let (size, (model_offset, model)) = match (size, model) {
+            (Some(size), Some(model)) => (size, model),
+            _ => return Err(LoadError::CorruptedBinary { offset: bytes.len(), reason: "missing SIZE or XYZI chunk" }),
+        };
+
+        let mut model_reader = ByteReader::new(model);
+        let voxel_count = model_reader.read_u32().map_err(|_| LoadError::CorruptedBinary { offset: model_offset, reason: "truncated XYZI chunk" })?;
+        let mut result = Self::new_empty(catalog);
+        for _ in 0..voxel_count {
+            let voxel_bytes = model_reader.read_bytes(4).map_err(|_| LoadError::CorruptedBinary { offset: model_offset, reason: "truncated XYZI chunk" })?;
+            let vox_coords = Vect3i::new([voxel_bytes[0] as i32, voxel_bytes[1] as i32, voxel_bytes[2] as i32]);
+            if (0..3).any(|axis| vox_coords[axis] >= size[axis]) {
+                return Err(LoadError::CorruptedBinary { offset: model_offset, reason: "voxel out of model size" });
+            }
+            let id = palette.voxel_id(voxel_bytes[3]).ok_or(LoadError::UnmappedPaletteIndex { index: voxel_bytes[3] })?;
+            let coords = vox_coords - Vect3i::new([size[0] / 2, size[1] / 2, size[2] / 2]);
+            result.add_voxel(coords, catalog.create_voxel(id));
+        }
+        result.recalculate_box();
+        Ok(result)
+    }
+
+    pub fn read_vox_file(catalog: &VoxelCatalog, palette: &VoxPalette, file_name: &str) -> Result<Self, LoadError> {
+        let vox_file_content = std::fs::read(file_name).map_err(|error| LoadError::Io { file_name: file_name.to_string(), error: error })?;
+        Self::deserialize_vox(catalog, palette, &vox_file_content)
+    }
+
+    pub fn write_vox_file(&self, palette: &VoxPalette, file_name: &str) -> Result<(), SaveError> {
+        std::fs::write(file_name, self.serialize_vox(palette)?).map_err(|error| SaveError::Io { file_name: file_name.to_string(), error: error })
+    }
+}
@@ -1,4 +1,4 @@
-#[derive(Clone, Copy)]
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
 pub enum TextureType {
     LightHull = 0,
     DarkHull = 1,
@@ -6,27 +6,78 @@ pub enum TextureType {
     Canon = 3,
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash)]
-pub enum VoxelID {
-    ShipCore = 0,
-    LightHull = 1,
-    HeavyHull = 2,
-    Canon = 3,
-    COUNT = 4,
+impl TextureType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "LightHull" => Some(Self::LightHull),
+            "DarkHull" => Some(Self::DarkHull),
+            "Core" => Some(Self::Core),
+            "Canon" => Some(Self::Canon),
+            _ => None,
+        }
+    }
+
+    // Main color of the texture, as red, green, blue. Also used where voxels are drawn without textures.
+    pub fn fill_color(self) -> [u8; 3] {
+        match self {
+            Self::LightHull => [0x80, 0x80, 0x80],
+            Self::DarkHull => [0x30, 0x30, 0x30],
+            Self::Core => [0x80, 0x80, 0xFF],
+            Self::Canon => [0x20, 0x20, 0x20],
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum DamageType {
+    Kinetic,
+    Energy,
+    Explosive,
 }
 
-impl From<i32> for VoxelID {
-    fn from(value: i32) -> Self {
-        match value {
-            0 => Self::ShipCore,
-            1 => Self::LightHull,
-            2 => Self::HeavyHull,
-            3 => Self::Canon,
-            _ => todo!(),
+impl DamageType {
+    // Position of the damage type in the catalog resistances.
+    pub fn index(self) -> usize {
+        match self {
+            Self::Kinetic => 0,
+            Self::Energy => 1,
+            Self::Explosive => 2,
         }
     }
 }
 
+// Voxel types doing more than holding the ship together.
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum ModuleType {
+    Thruster,
+    Gyroscope,
+    Reactor,
+}
+
+impl ModuleType {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "Thruster" => Some(Self::Thruster),
+            "Gyroscope" => Some(Self::Gyroscope),
+            "Reactor" => Some(Self::Reactor),
+            _ => None,
+        }
+    }
+}
+
+// Voxel types are defined in the catalog definitions file, any ID can be used as long as the catalog knows it.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+pub struct VoxelID(pub u16);
+
+// Voxel types the code relies on, they must stay defined with these IDs in the catalog definitions file.
+impl VoxelID {
+    pub const SHIP_CORE: Self = Self(0);
+    pub const LIGHT_HULL: Self = Self(1);
+    pub const HEAVY_HULL: Self = Self(2);
+}
+
+pub const VOXEL_VOLUME: f32 = 1.0; // Expressed in cubic meters
+
 #[derive(Clone, Copy)]
 pub struct Voxel {
     pub id: VoxelID,
@@ -40,9 +91,21 @@ impl PartialEq for Voxel {
     }
 }
 
-
 #[derive(Clone)]
 pub struct VoxelDescriptor {
+    pub name: String,
     pub max_life: f32,
     pub texture_type: TextureType,
+    pub density: f32, // Expressed in tonnes per cubic meter
+    pub penetration_resistance: f32, // Energy a projectile loses going through a voxel of this type
+    pub damage_resistances: [f32; 3], // Share of the damage ignored, indexed by DamageType::index
+    pub module: Option<ModuleType>,
+    pub module_output: f32, // Thrust in kilonewtons, torque in kilonewton meters or power in megawatts, depending on the module
+    pub power_draw: f32, // Power in megawatts a thruster or a gyroscope takes from reactors at full throttle
+}
+
+impl VoxelDescriptor {
+    pub fn effective_damage(&self, damage: f32, damage_type: DamageType) -> f32 {
+        damage * (1.0 - self.damage_resistances[damage_type.index()])
+    }
 }
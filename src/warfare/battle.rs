@@ -1,39 +1,72 @@
 use super::projectile::Projectile;
 use super::ship::Ship;
+use crate::physics::broadphase;
 use crate::physics::collision;
 use crate::physics::body::Body;
+use crate::physics::body_arena::BodyArena;
+use crate::physics::body_arena::BodyHandle;
+use crate::maths::boxes::Box3f;
 use crate::maths::segment::Segm3f;
-use crate::voxels::voxel::Voxel;
+use crate::maths::vector::Vect3f;
+use crate::voxels::damage::AreaDamage;
+use crate::voxels::catalog::VoxelCatalog;
+use crate::voxels::structure::RayHit;
+
+enum BattleBody {
+    Inert(Body),
+    PlayerShip(Ship),
+}
+
+impl BattleBody {
+    fn body(&self) -> &Body {
+        match self {
+            Self::Inert(body) => body,
+            Self::PlayerShip(ship) => ship.body(),
+        }
+    }
+
+    fn body_mut(&mut self) -> &mut Body {
+        match self {
+            Self::Inert(body) => body,
+            Self::PlayerShip(ship) => ship.body_mut(),
+        }
+    }
+}
 
 struct BodyList {
-    pub inert_bodies: Vec<Body>,
-    pub player_ship: Option<Ship>,
+    pub arena: BodyArena<BattleBody>,
+    pub player_ship: Option<BodyHandle>,
 }
 
 impl BodyList {
     pub fn new() -> Self {
         Self {
-            inert_bodies: vec![],
+            arena: BodyArena::new(),
             player_ship: None,
         }
     }
 
     pub fn bodies(&self) -> Vec<&Body> {
-        let mut bodies: Vec<&Body> = self.inert_bodies.iter().collect();
-        if self.player_ship.is_some() {
-            bodies.push(self.player_ship.as_ref().unwrap().body());
-        }
-        bodies
+        self.arena.iter().map(|(_, battle_body)| battle_body.body()).collect()
     }
 
     pub fn bodies_mut(&mut self) -> Vec<&mut Body> {
-        let mut bodies: Vec<&mut Body> = self.inert_bodies.iter_mut().collect();
-        if self.player_ship.is_some() {
-            bodies.push(self.player_ship.as_mut().unwrap().body_mut());
+        self.arena.iter_mut().map(|(_, battle_body)| battle_body.body_mut()).collect()
+    }
+
+    pub fn player_ship(&self) -> Option<&Ship> {
+        match self.arena.get(self.player_ship?) {
+            Some(BattleBody::PlayerShip(ship)) => Some(ship),
+            _ => None,
         }
-        bodies
     }
 
+    pub fn player_ship_mut(&mut self) -> Option<&mut Ship> {
+        match self.arena.get_mut(self.player_ship?) {
+            Some(BattleBody::PlayerShip(ship)) => Some(ship),
+            _ => None,
+        }
+    }
 }
 
 pub struct Battle {
@@ -49,13 +82,15 @@ impl Battle {
         }
     }
 
-    pub fn add_inert_body(&mut self, body: Body) {
-        self.body_list.inert_bodies.push(body);
+    pub fn add_inert_body(&mut self, body: Body) -> BodyHandle {
+        self.body_list.arena.insert(BattleBody::Inert(body))
     }
 
-    pub fn set_player_ship(&mut self, ship: Ship) {
+    pub fn set_player_ship(&mut self, ship: Ship) -> BodyHandle {
         assert!(self.body_list.player_ship.is_none());
-        self.body_list.player_ship = Some(ship);
+        let handle = self.body_list.arena.insert(BattleBody::PlayerShip(ship));
+        self.body_list.player_ship = Some(handle);
+        handle
     }
 
     #[cfg(test)]
@@ -68,22 +103,54 @@ impl Battle {
     }
 
     pub fn player_ship(&self) -> Option<&Ship> {
-        self.body_list.player_ship.as_ref()
+        self.body_list.player_ship()
     }
 
     pub fn player_ship_mut(&mut self) -> Option<&mut Ship> {
-        self.body_list.player_ship.as_mut()
+        self.body_list.player_ship_mut()
     }
 
     pub fn bodies(&self) -> Vec<&Body> {
         self.body_list.bodies()
     }
 
+    #[cfg(test)]
+    pub fn body_handles(&self) -> Vec<BodyHandle> {
+        self.body_list.arena.handles()
+    }
+
+    // None once the body is destroyed.
+    #[cfg(test)]
+    pub fn body(&self, handle: BodyHandle) -> Option<&Body> {
+        self.body_list.arena.get(handle).map(|battle_body| battle_body.body())
+    }
+
+    #[cfg(test)]
+    pub fn parent_body(&self, handle: BodyHandle) -> Option<BodyHandle> {
+        self.body_list.arena.parent(handle)
+    }
+
+    #[cfg(test)]
+    pub fn child_bodies(&self, handle: BodyHandle) -> Vec<BodyHandle> {
+        self.body_list.arena.children(handle)
+    }
+
     pub fn projectiles(&self) -> &Vec<Projectile> {
         &self.projectiles
     }
 
-    pub fn update(&mut self, elapsed_time: f32) {
+    // Damages every body around right away, destroyed voxels are removed by the next update.
+    #[allow(dead_code)]
+    pub fn add_explosion(&mut self, catalog: &VoxelCatalog, area_damage: AreaDamage) {
+        let sphere_box = Box3f::from_min_max(area_damage.center - Vect3f::all(area_damage.radius), area_damage.center + Vect3f::all(area_damage.radius));
+        for body in self.body_list.bodies_mut() {
+            if body.get_box().intersects(&sphere_box) {
+                body.apply_area_damage(catalog, &area_damage);
+            }
+        }
+    }
+
+    pub fn update(&mut self, catalog: &VoxelCatalog, elapsed_time: f32) {
         for body in self.body_list.bodies_mut() {
             body.apply_velocity_and_rotation(elapsed_time);
         }
@@ -92,37 +159,59 @@ impl Battle {
             let segment_start = projectile.position();
             projectile.moove(elapsed_time);
             let segment_end = projectile.position();
-            let mut hit = false;
+            let mut stopped = false;
             if !projectile.is_out_of_max_range() {
                 let segment = Segm3f::new(segment_start, segment_end);
-                for body in self.body_list.bodies_mut() {
-                    hit |= body.for_first_voxel_in_segment(segment, |voxel: &mut Voxel, _coords| {
-                        voxel.life -= projectile.damage();
-                    });
+                // Voxels are hit from the closest one, whatever their body, until the projectile is stopped.
+                // The voxel gone through at the end of the previous segment is not hit again.
+                let last_hit = projectile.last_hit();
+                let mut bodies: Vec<(BodyHandle, &mut Body)> = self.body_list.arena.iter_mut()
+                    .map(|(handle, battle_body)| (handle, battle_body.body_mut()))
+                    .collect();
+                let mut hits: Vec<(usize, RayHit)> = bodies.iter()
+                    .enumerate()
+                    .flat_map(|(index, (_, body))| body.ray_cast_all(segment).into_iter().map(move |ray_hit| (index, ray_hit)))
+                    .filter(|(index, ray_hit)| last_hit != Some((bodies[*index].0, ray_hit.coords)))
+                    .collect();
+                hits.sort_by(|a, b| a.1.distance.total_cmp(&b.1.distance));
+                for (index, ray_hit) in hits {
+                    let (handle, body) = &mut bodies[index];
+                    let voxel_id = body.structure().get_voxel(ray_hit.coords).unwrap().id;
+                    let descriptor = catalog.get_descriptor(voxel_id);
+                    body.damage_voxel(ray_hit.coords, descriptor.effective_damage(projectile.damage(), projectile.damage_type()));
+                    projectile.set_last_hit(*handle, ray_hit.coords);
+                    stopped = !projectile.go_through(descriptor.penetration_resistance);
+                    if stopped {
+                        break;
+                    }
                 }
             }
-            !hit && !projectile.is_out_of_max_range()
+            !stopped && !projectile.is_out_of_max_range()
         });
 
         {
             const RESTITUTION: f32 = 1.0;
             let mut bodies = self.body_list.bodies_mut();
-            // TODO better than this O(n^2)...
-            for i1 in 0..bodies.len() -1 {
-                let split_bodies = bodies.split_at_mut(i1 + 1);
-                let body = &mut split_bodies.0[i1];
-                for other_body in split_bodies.1 {
-                    collision::apply_collision_if_any(body, other_body, RESTITUTION);
-                }
+            let boxes: Vec<Box3f> = bodies.iter().map(|body| body.get_box()).collect();
+            for (index_a, index_b) in broadphase::candidate_pairs(&boxes) {
+                let (first_bodies, last_bodies) = bodies.split_at_mut(index_b);
+                collision::apply_collision_if_any(first_bodies[index_a], last_bodies[0], RESTITUTION);
             }
         }
 
         let mut new_bodies = vec![];
-        for body in self.body_list.bodies_mut() {
-            new_bodies.extend(body.update_dead_voxels());
+        for (handle, battle_body) in self.body_list.arena.iter_mut() {
+            for new_body in battle_body.body_mut().update_dead_voxels() {
+                new_bodies.push((handle, new_body));
+            }
+        }
+        for (parent, new_body) in new_bodies {
+            self.body_list.arena.insert_split(parent, BattleBody::Inert(new_body));
         }
-        self.body_list.inert_bodies.extend(new_bodies);
 
-        self.body_list.inert_bodies.retain(|body| !body.structure().is_empty());
+        self.body_list.arena.retain(|battle_body| match battle_body {
+            BattleBody::Inert(body) => !body.structure().is_empty(),
+            BattleBody::PlayerShip(_) => true,
+        });
     }
 }
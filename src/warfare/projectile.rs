@@ -1,21 +1,28 @@
 use crate::maths::vector::Vect3f;
+use crate::maths::vector::Vect3i;
+use crate::physics::body_arena::BodyHandle;
+use crate::voxels::voxel::DamageType;
 
 pub struct Projectile {
     position: Vect3f,
     velocity: Vect3f,
     damage: f32,
+    damage_type: DamageType,
     max_range: f32,
     traveled_distance: f32,
+    last_hit: Option<(BodyHandle, Vect3i)>,
 }
 
 impl Projectile {
-    pub fn new(position: Vect3f, velocity: Vect3f, damage: f32, max_range: f32) -> Self {
+    pub fn new(position: Vect3f, velocity: Vect3f, damage: f32, damage_type: DamageType, max_range: f32) -> Self {
         Self {
             position: position,
             velocity: velocity,
             damage: damage,
+            damage_type: damage_type,
             max_range: max_range,
             traveled_distance: 0.0,
+            last_hit: None,
         }
     }
 
@@ -31,6 +38,25 @@ impl Projectile {
         self.damage
     }
 
+    pub fn damage_type(&self) -> DamageType {
+        self.damage_type
+    }
+
+    // The damage left is what goes on through the voxel. False once the projectile is stopped.
+    pub fn go_through(&mut self, penetration_resistance: f32) -> bool {
+        self.damage -= penetration_resistance;
+        self.damage > 0.0
+    }
+
+    // The body and coordinates of the last voxel gone through, so the next segment does not hit it again.
+    pub fn last_hit(&self) -> Option<(BodyHandle, Vect3i)> {
+        self.last_hit
+    }
+
+    pub fn set_last_hit(&mut self, handle: BodyHandle, coords: Vect3i) {
+        self.last_hit = Some((handle, coords));
+    }
+
     pub fn is_out_of_max_range(&self) -> bool {
         self.traveled_distance > self.max_range
     }
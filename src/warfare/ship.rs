@@ -3,6 +3,16 @@ use super::weapon::Weapon;
 use crate::physics::body::Body;
 use crate::maths::matrix::Mat4f;
 use crate::maths::vector::Vect3f;
+use crate::maths::vector::Vect3i;
+use crate::voxels::catalog::VoxelCatalog;
+use crate::voxels::mesh::FACE_NORMALS;
+use crate::voxels::voxel::ModuleType;
+
+// What the pilot asks for, in ship axes: x forward, y left and z up. Each throttle goes from -1 to 1.
+pub struct ShipControls {
+    pub thrust: Vect3f,
+    pub rotation: Vect3f, // Around x for roll, y for pitch and z for yaw.
+}
 
 pub struct Ship {
     body: Body,
@@ -50,4 +60,58 @@ impl Ship {
         result
     }
 
+    // Modules still on the ship move it, weakened by their damage.
+    // A thruster pushes towards any side whose opposite face is free for its exhaust. It pushes from where it is,
+    // so thrusters off the center of mass make the ship spin, unless gyroscopes have torque left to cancel it.
+    // When reactors do not produce all the power drawn, every thruster and gyroscope runs below its throttle.
+    pub fn steer(&mut self, catalog: &VoxelCatalog, controls: &ShipControls, elapsed_time: f32) {
+        let structure = self.body.structure();
+        let max_throttle = |throttles: Vect3f| (0..3).map(|axis| throttles[axis].abs()).fold(0.0, f32::max);
+        let gyroscope_throttle = max_throttle(controls.rotation).max(max_throttle(controls.thrust));
+        let mut power_produced = 0.0;
+        let mut power_drawn = 0.0;
+        let mut max_torque = 0.0;
+        let mut thrusts: Vec<(Vect3f, Vect3f)> = vec![]; // Position and force, in ship axes.
+        structure.for_each_voxel(|coords, voxel| {
+            let descriptor = catalog.get_descriptor(voxel.id);
+            let health = voxel.life / descriptor.max_life;
+            match descriptor.module {
+                Some(ModuleType::Thruster) => {
+                    for normal in FACE_NORMALS {
+                        let direction = Vect3f::new(normal.map(|value| value as f32));
+                        let throttle = Vect3f::dot(controls.thrust, direction);
+                        if throttle > 0.0 && !structure.has_voxel_on_coords(coords - Vect3i::new(normal)) {
+                            let position = Vect3f::new([coords[0] as f32, coords[1] as f32, coords[2] as f32]);
+                            thrusts.push((position, direction * (descriptor.module_output * health * throttle)));
+                            power_drawn += descriptor.power_draw * throttle;
+                        }
+                    }
+                },
+                Some(ModuleType::Gyroscope) => {
+                    max_torque += descriptor.module_output * health;
+                    power_drawn += descriptor.power_draw * gyroscope_throttle;
+                },
+                Some(ModuleType::Reactor) => power_produced += descriptor.module_output * health,
+                None => {},
+            }
+        });
+
+        let power_ratio = if power_drawn > power_produced { power_produced / power_drawn } else { 1.0 };
+        let center_of_mass = structure.center_of_mass();
+        let mut thrust_torque = Vect3f::zero();
+        for (position, force) in &mut thrusts {
+            *force *= power_ratio;
+            thrust_torque += Vect3f::cross(*position - center_of_mass, *force);
+        }
+        let max_torque = max_torque * power_ratio;
+        let wished_torque = controls.rotation * max_torque;
+        let gyroscope_torque = Vect3f::new([0, 1, 2].map(|axis| (wished_torque[axis] - thrust_torque[axis]).clamp(-max_torque, max_torque)));
+
+        let repere = self.body.repere().clone();
+        let rotation = repere.without_translation();
+        for (position, force) in thrusts {
+            self.body.apply_impulse(rotation.clone() * force * elapsed_time, repere.clone() * position);
+        }
+        self.body.apply_torque(rotation * gyroscope_torque, elapsed_time);
+    }
 }
@@ -1,28 +1,39 @@
 use crate::maths::matrix::Mat4f;
+use crate::maths::testing;
 use crate::maths::vector::Vect3f;
 use crate::maths::vector::Vect3i;
 use crate::physics::body::Body;
+use crate::physics::collision;
+use crate::voxels::catalog::VoxelCatalog;
+use crate::voxels::damage::AreaDamage;
 use crate::voxels::structure::Structure;
+use crate::voxels::voxel::DamageType;
 use crate::voxels::voxel::Voxel;
 use crate::voxels::voxel::VoxelID;
 use super::battle::Battle;
 use super::projectile::Projectile;
+use super::ship::Ship;
+use super::ship::ShipControls;
 
 const TEST_VOXEL: Voxel = Voxel{
     life: 2.0,
-    id: VoxelID::LightHull,
+    id: VoxelID::LIGHT_HULL,
 };
 
+const THRUSTER: VoxelID = VoxelID(4);
+const GYROSCOPE: VoxelID = VoxelID(5);
+const REACTOR: VoxelID = VoxelID(6);
+
 const TEST_DEAD_VOXEL: Voxel = Voxel{
     life: 0.0,
-    id: VoxelID::ShipCore,
+    id: VoxelID::SHIP_CORE,
 };
 
 #[test]
 fn projectile_velocity() {
     let initial_position = Vect3f::new([0.0, 0.0, 2.0]);
     let velocity = Vect3f::new([10.0, -20.0, 0.0]);
-    let mut projectile = Projectile::new(initial_position, velocity, 1.0, f32::MAX);
+    let mut projectile = Projectile::new(initial_position, velocity, 1.0, DamageType::Kinetic, f32::MAX);
     projectile.moove(2.0);
     projectile.moove(2.0);
     projectile.moove(2.0);
@@ -35,7 +46,7 @@ fn projectile_max_distance() {
     let initial_position = Vect3f::zero();
     let velocity = Vect3f::new([10.0, 0.0, 0.0]);
     let max_distance = 25.0;
-    let mut projectile = Projectile::new(initial_position, velocity, 1.0, max_distance);
+    let mut projectile = Projectile::new(initial_position, velocity, 1.0, DamageType::Kinetic, max_distance);
     projectile.moove(1.0);
     assert!(!projectile.is_out_of_max_range());
     projectile.moove(1.0);
@@ -46,49 +57,51 @@ fn projectile_max_distance() {
 
 #[test]
 fn projectile_damage() {
+    let catalog = VoxelCatalog::create();
     let mut battle = Battle::new();
-    let structure = Structure::new(-1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    let structure = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
     battle.add_inert_body(Body::new(structure, Mat4f::identity()));
 
     let initial_position = Vect3f::new([-10.0, 1.0, 1.0]);
     let velocity = Vect3f::new([10.0, 0.0, 0.0]);
-    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, f32::MAX));
-    battle.update(1.0);
+    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, DamageType::Kinetic, f32::MAX));
+    battle.update(&catalog, 1.0);
     assert!(battle.bodies()[0].structure().get_voxel(Vect3i::new([-1, 1, 1])).unwrap().life == 1.0);
 
-    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, f32::MAX));
-    battle.update(1.0);
+    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, DamageType::Kinetic, f32::MAX));
+    battle.update(&catalog, 1.0);
     assert!(battle.bodies()[0].structure().get_voxel(Vect3i::new([-1, 1, 1])).is_none());
 }
 
 #[test]
 fn projectile_damage_on_moving_body() {
+    let catalog = VoxelCatalog::create();
     let mut battle = Battle::new();
-    let structure = Structure::new(-1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    let structure = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
     let mut expected_structure = structure.clone();
     let mut body = Body::new(structure, Mat4f::identity());
-    body.add_to_velocity(Vect3f::new([0.0, 0.4, 0.0]));
+    body.set_velocity(Vect3f::new([0.0, 0.4, 0.0]));
     battle.add_inert_body(body);
 
     let initial_position = Vect3f::new([-10.0, 0.0, 0.0]);
     let velocity = Vect3f::new([10.0, 0.0, 0.0]);
-    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, f32::MAX));
-    battle.update(1.0);
-    expected_structure.set_voxel(-1, 0, 0, Some(Voxel{life: 1.0, id: VoxelID::LightHull}));
+    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, DamageType::Kinetic, f32::MAX));
+    battle.update(&catalog, 1.0);
+    expected_structure.set_voxel(-1, 0, 0, Some(Voxel{life: 1.0, id: VoxelID::LIGHT_HULL}));
     assert!(battle.bodies()[0].structure().clone() == expected_structure);
 
-    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, f32::MAX));
-    battle.update(1.0);
-    expected_structure.set_voxel(-1, -1, 0, Some(Voxel{life: 1.0, id: VoxelID::LightHull}));
+    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, DamageType::Kinetic, f32::MAX));
+    battle.update(&catalog, 1.0);
+    expected_structure.set_voxel(-1, -1, 0, Some(Voxel{life: 1.0, id: VoxelID::LIGHT_HULL}));
     assert!(battle.bodies()[0].structure().clone() == expected_structure);
 
-    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, f32::MAX));
-    battle.update(1.0);
+    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, DamageType::Kinetic, f32::MAX));
+    battle.update(&catalog, 1.0);
     expected_structure.set_voxel(-1, -1, 0, None);
     assert!(battle.bodies()[0].structure().clone() == expected_structure);
 
-    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, f32::MAX));
-    battle.update(1.0);
+    battle.add_projectile(Projectile::new(initial_position, velocity, 1.0, DamageType::Kinetic, f32::MAX));
+    battle.update(&catalog, 1.0);
     // Nothing new, the projectile should have missed.
     assert!(battle.bodies()[0].structure().clone() == expected_structure);
 }
@@ -96,11 +109,281 @@ fn projectile_damage_on_moving_body() {
 
 #[test]
 fn battle_forget_empty_bodies() {
-    let structure = Structure::new(-1, 1, 0, 0, 0, 0, TEST_DEAD_VOXEL);
+    let catalog = VoxelCatalog::create();
+    let structure = Structure::new(&catalog, -1, 1, 0, 0, 0, 0, TEST_DEAD_VOXEL);
     let body = Body::new(structure, Mat4f::identity());
     let mut battle = Battle::new();
     battle.add_inert_body(body);
 
-    battle.update(1.0);
+    battle.update(&catalog, 1.0);
+    assert!(battle.bodies().is_empty());
+}
+
+#[test]
+fn battle_separates_interpenetrating_bodies() {
+    let catalog = VoxelCatalog::create();
+    let structure = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    let mut battle = Battle::new();
+    battle.add_inert_body(Body::new(structure.clone(), Mat4f::identity()));
+    battle.add_inert_body(Body::new(structure.clone(), Mat4f::translation(Vect3f::new([0.0, 2.0, 0.0]))));
+
+    for _ in 0..10 {
+        battle.update(&catalog, 1.0 / 60.0);
+    }
+    let bodies = battle.bodies();
+    assert!(collision::intersection_low_precision(bodies[0], bodies[1]).is_empty());
+    assert!(bodies[1].repere().position()[1] - bodies[0].repere().position()[1] >= 3.0);
+}
+
+#[test]
+fn battle_body_handles_survive_splitting() {
+    let catalog = VoxelCatalog::create();
+    let mut battle = Battle::new();
+    let dying_handle = battle.add_inert_body(Body::new(Structure::new(&catalog, -1, 1, 0, 0, 0, 0, TEST_DEAD_VOXEL), Mat4f::translation(Vect3f::new([0.0, 0.0, 50.0]))));
+    let bar_handle = battle.add_inert_body(Body::new(Structure::new(&catalog, -1, 3, 0, 0, 0, 0, TEST_VOXEL), Mat4f::identity()));
+    let other_handle = battle.add_inert_body(Body::new(Structure::new(&catalog, 0, 0, 0, 0, 0, 0, TEST_VOXEL), Mat4f::translation(Vect3f::new([0.0, 0.0, -50.0]))));
+
+    // Cut the bar on x = 1, its part on the origin stays the original body.
+    battle.add_projectile(Projectile::new(Vect3f::new([1.0, -10.0, 0.0]), Vect3f::new([0.0, 10.0, 0.0]), 2.0, DamageType::Kinetic, f32::MAX));
+    battle.update(&catalog, 1.0);
+
+    assert!(battle.body(dying_handle).is_none());
+    assert!(battle.body(bar_handle).unwrap().structure().get_box() == Structure::new(&catalog, -1, 0, 0, 0, 0, 0, TEST_VOXEL).get_box());
+    assert!(battle.body(other_handle).unwrap().repere().position() == Vect3f::new([0.0, 0.0, -50.0]));
+
+    let children = battle.child_bodies(bar_handle);
+    assert!(children.len() == 1);
+    assert!(battle.parent_body(children[0]) == Some(bar_handle));
+    assert!(battle.parent_body(bar_handle).is_none());
+    assert!(battle.body(children[0]).unwrap().structure().mass() == 2.0 * battle.body(children[0]).unwrap().structure().voxel_mass(&TEST_VOXEL));
+    assert!(battle.body_handles().len() == 3);
+    assert!(!battle.body_handles().contains(&dying_handle));
+}
+
+#[test]
+fn projectile_stopped_by_closest_body() {
+    let catalog = VoxelCatalog::create();
+    let mut battle = Battle::new();
+    let structure = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    // Added first, but farther from the projectile, which is stopped before reaching it.
+    battle.add_inert_body(Body::new(structure.clone(), Mat4f::translation(Vect3f::new([5.0, 0.0, 0.0]))));
+    battle.add_inert_body(Body::new(structure.clone(), Mat4f::identity()));
+
+    battle.add_projectile(Projectile::new(Vect3f::new([-10.0, 0.0, 0.0]), Vect3f::new([20.0, 0.0, 0.0]), 1.0, DamageType::Kinetic, f32::MAX));
+    battle.update(&catalog, 1.0);
+    assert!(battle.bodies()[0].structure().get_voxel(Vect3i::new([-1, 0, 0])).unwrap().life == 2.0);
+    assert!(battle.bodies()[1].structure().get_voxel(Vect3i::new([-1, 0, 0])).unwrap().life == 1.0);
+}
+
+#[test]
+fn explosion_cuts_body() {
+    let catalog = VoxelCatalog::create();
+    let mut battle = Battle::new();
+    battle.add_inert_body(Body::new(Structure::new(&catalog, 0, 6, 0, 0, 0, 0, TEST_VOXEL), Mat4f::translation(Vect3f::new([-3.0, 0.0, 0.0]))));
+    let far_structure = Structure::new(&catalog, -1, 1, -1, 1, -1, 1, TEST_VOXEL);
+    battle.add_inert_body(Body::new(far_structure.clone(), Mat4f::translation(Vect3f::new([0.0, 20.0, 0.0]))));
+
+    // Only the middle voxel gets enough damage to be destroyed.
+    battle.add_explosion(&catalog, AreaDamage { center: Vect3f::zero(), radius: 1.5, damage: 4.0, damage_type: DamageType::Explosive, transmission: 0.0 });
+    assert!(battle.bodies().len() == 2);
+    battle.update(&catalog, 0.0);
+    assert!(battle.bodies().len() == 3);
+    assert!(battle.bodies().iter().filter(|body| body.structure().mass() == 3.0 * body.structure().voxel_mass(&TEST_VOXEL)).count() == 2);
+    assert!(*battle.bodies()[1].structure() == far_structure);
+}
+
+#[test]
+fn projectile_penetration() {
+    let catalog = VoxelCatalog::create();
+    let light_hull_life = catalog.get_descriptor(VoxelID::LIGHT_HULL).max_life;
+    let heavy_hull_life = catalog.get_descriptor(VoxelID::HEAVY_HULL).max_life;
+    let bar_battle = |voxel_id: VoxelID| {
+        let mut battle = Battle::new();
+        battle.add_inert_body(Body::new(Structure::new(&catalog, 0, 4, 0, 0, 0, 0, catalog.create_voxel(voxel_id)), Mat4f::identity()));
+        battle
+    };
+    let shoot = |battle: &mut Battle, damage: f32| {
+        battle.add_projectile(Projectile::new(Vect3f::new([-10.0, 0.0, 0.0]), Vect3f::new([20.0, 0.0, 0.0]), damage, DamageType::Kinetic, f32::MAX));
+        battle.update(&catalog, 1.0);
+    };
+    let life = |battle: &Battle, x: i32| battle.bodies()[0].structure().get_voxel(Vect3i::new([x, 0, 0])).map_or(0.0, |voxel| voxel.life);
+
+    // A heavy gun goes through light hull, each voxel absorbing part of its damage.
+    let mut battle = bar_battle(VoxelID::LIGHT_HULL);
+    shoot(&mut battle, 3.0);
+    assert!(battle.bodies().len() == 1);
+    assert!(life(&battle, 0) == 0.0 && life(&battle, 1) == 0.0);
+    assert!(life(&battle, 2) == light_hull_life - 1.0);
+    assert!(life(&battle, 3) == light_hull_life);
+    assert!(battle.projectiles().is_empty());
+
+    // Heavy hull stops it right away.
+    let mut battle = bar_battle(VoxelID::HEAVY_HULL);
+    shoot(&mut battle, 3.0);
+    assert!(life(&battle, 0) == heavy_hull_life - catalog.get_descriptor(VoxelID::HEAVY_HULL).effective_damage(3.0, DamageType::Kinetic));
+    assert!(life(&battle, 1) == heavy_hull_life);
+    assert!(battle.projectiles().is_empty());
+
+    // With damage left the projectile keeps flying.
+    let mut battle = bar_battle(VoxelID::LIGHT_HULL);
+    shoot(&mut battle, 10.0);
     assert!(battle.bodies().is_empty());
+    assert!(battle.projectiles().len() == 1 && battle.projectiles()[0].damage() == 5.0);
+}
+
+#[test]
+fn projectile_penetration_through_several_bodies() {
+    let catalog = VoxelCatalog::create();
+    let light_hull_life = catalog.get_descriptor(VoxelID::LIGHT_HULL).max_life;
+    let mut battle = Battle::new();
+    // Added farthest first, so that hits have to be sorted by distance.
+    for x in [6.0, 3.0, 0.0] {
+        battle.add_inert_body(Body::new(Structure::new(&catalog, 0, 0, 0, 0, 0, 0, catalog.create_voxel(VoxelID::LIGHT_HULL)), Mat4f::translation(Vect3f::new([x, 0.0, 0.0]))));
+    }
+    battle.add_projectile(Projectile::new(Vect3f::new([-10.0, 0.0, 0.0]), Vect3f::new([20.0, 0.0, 0.0]), 2.5, DamageType::Kinetic, f32::MAX));
+    battle.update(&catalog, 1.0);
+    // The closest one is destroyed, the next ones take what is left after the ones before.
+    let lives: Vec<f32> = battle.bodies().iter().map(|body| body.structure().get_voxel(Vect3i::zero()).unwrap().life).collect();
+    assert!(lives == vec![light_hull_life - 0.5, light_hull_life - 1.5]);
+    assert!(battle.projectiles().is_empty());
+}
+
+#[test]
+fn projectile_hits_voxel_once() {
+    let catalog = VoxelCatalog::create();
+    let light_hull_life = catalog.get_descriptor(VoxelID::LIGHT_HULL).max_life;
+    let mut battle = Battle::new();
+    battle.add_inert_body(Body::new(Structure::new(&catalog, 0, 0, 0, 0, 0, 0, catalog.create_voxel(VoxelID::LIGHT_HULL)), Mat4f::identity()));
+    let life = |battle: &Battle| battle.bodies()[0].structure().get_voxel(Vect3i::zero()).unwrap().life;
+
+    // The first segment ends inside the voxel, the next one starts there without hitting it again.
+    battle.add_projectile(Projectile::new(Vect3f::new([-1.0, 0.0, 0.0]), Vect3f::new([1.0, 0.0, 0.0]), 1.5, DamageType::Kinetic, f32::MAX));
+    battle.update(&catalog, 1.0);
+    assert!(life(&battle) == light_hull_life - 1.5);
+    battle.update(&catalog, 1.0);
+    assert!(life(&battle) == light_hull_life - 1.5);
+    assert!(battle.projectiles().len() == 1 && battle.projectiles()[0].damage() == 0.5);
+
+    // Another projectile starting inside the voxel still hits it.
+    battle.add_projectile(Projectile::new(Vect3f::zero(), Vect3f::new([0.0, 1.0, 0.0]), 0.25, DamageType::Kinetic, f32::MAX));
+    battle.update(&catalog, 1.0);
+    assert!(life(&battle) == light_hull_life - 1.75);
+}
+
+#[test]
+fn projectile_damage_types() {
+    let catalog = VoxelCatalog::create();
+    let heavy_hull = catalog.get_descriptor(VoxelID::HEAVY_HULL);
+    let light_hull = catalog.get_descriptor(VoxelID::LIGHT_HULL);
+    let damage_taken = |voxel_id: VoxelID, damage_type: DamageType| {
+        let mut battle = Battle::new();
+        battle.add_inert_body(Body::new(Structure::new(&catalog, 0, 0, 0, 0, 0, 0, catalog.create_voxel(voxel_id)), Mat4f::identity()));
+        battle.add_projectile(Projectile::new(Vect3f::new([-10.0, 0.0, 0.0]), Vect3f::new([20.0, 0.0, 0.0]), 1.0, damage_type, f32::MAX));
+        battle.update(&catalog, 1.0);
+        catalog.get_descriptor(voxel_id).max_life - battle.bodies()[0].structure().get_voxel(Vect3i::zero()).unwrap().life
+    };
+
+    for damage_type in [DamageType::Kinetic, DamageType::Energy, DamageType::Explosive] {
+        assert!(damage_taken(VoxelID::LIGHT_HULL, damage_type) == light_hull.effective_damage(1.0, damage_type));
+        assert!(damage_taken(VoxelID::HEAVY_HULL, damage_type) == heavy_hull.effective_damage(1.0, damage_type));
+        // Heavy hull is not only tougher, it also shrugs off part of every damage type.
+        assert!(heavy_hull.effective_damage(1.0, damage_type) < light_hull.effective_damage(1.0, damage_type));
+    }
+    assert!(damage_taken(VoxelID::HEAVY_HULL, DamageType::Kinetic) == 0.5);
+}
+
+fn create_module_ship(catalog: &VoxelCatalog, voxels: &[(Vect3i, VoxelID)]) -> Ship {
+    let mut structure = Structure::new_empty(&catalog);
+    for (coords, id) in voxels {
+        structure.add_voxel(*coords, catalog.create_voxel(*id));
+    }
+    Ship::new(Body::new(structure, Mat4f::identity()))
+}
+
+fn thrust_controls(thrust: [f32; 3]) -> ShipControls {
+    ShipControls { thrust: Vect3f::new(thrust), rotation: Vect3f::zero() }
+}
+
+#[test]
+fn ship_thrusters() {
+    let catalog = VoxelCatalog::create();
+    let thrust = catalog.get_descriptor(THRUSTER).module_output;
+    let voxels = [(Vect3i::new([0, 0, 0]), THRUSTER), (Vect3i::new([1, 0, 0]), REACTOR)];
+
+    let mut ship = create_module_ship(&catalog, &voxels);
+    ship.steer(&catalog, &thrust_controls([1.0, 0.0, 0.0]), 0.5);
+    let mass = ship.body().structure().mass();
+    assert!(testing::equals_with_delta(ship.body().velocity()[0], thrust * 0.5 / mass, 0.0001));
+    assert!(ship.body().velocity()[1] == 0.0 && ship.body().velocity()[2] == 0.0);
+    assert!(ship.body().angular_velocity() == Vect3f::zero());
+
+    // The reactor is in the way of the exhaust.
+    let mut ship = create_module_ship(&catalog, &voxels);
+    ship.steer(&catalog, &thrust_controls([-1.0, 0.0, 0.0]), 0.5);
+    assert!(ship.body().velocity() == Vect3f::zero());
+
+    let mut ship = create_module_ship(&catalog, &voxels);
+    ship.steer(&catalog, &thrust_controls([0.5, 0.0, 0.0]), 0.5);
+    assert!(testing::equals_with_delta(ship.body().velocity()[0], thrust * 0.25 / mass, 0.0001));
+
+    // Without reactor, thrusters have no power.
+    let mut ship = create_module_ship(&catalog, &voxels[0..1]);
+    ship.steer(&catalog, &thrust_controls([1.0, 0.0, 0.0]), 0.5);
+    assert!(ship.body().velocity() == Vect3f::zero());
+}
+
+#[test]
+fn ship_modules_damage() {
+    let catalog = VoxelCatalog::create();
+    let thruster = catalog.get_descriptor(THRUSTER);
+    let reactor = catalog.get_descriptor(REACTOR);
+    let thruster_coords = Vect3i::new([0, 0, 0]);
+    let reactor_coords = Vect3i::new([1, 0, 0]);
+    let voxels = [(thruster_coords, THRUSTER), (reactor_coords, REACTOR)];
+    let forward_velocity = |ship: &mut Ship| {
+        ship.steer(&catalog, &thrust_controls([1.0, 0.0, 0.0]), 1.0);
+        ship.body().velocity()[0] * ship.body().structure().mass()
+    };
+
+    let mut ship = create_module_ship(&catalog, &voxels);
+    assert!(testing::equals_with_delta(forward_velocity(&mut ship), thruster.module_output, 0.01));
+
+    let mut ship = create_module_ship(&catalog, &voxels);
+    ship.body_mut().damage_voxel(thruster_coords, thruster.max_life * 0.75);
+    assert!(testing::equals_with_delta(forward_velocity(&mut ship), thruster.module_output * 0.25, 0.01));
+
+    // The damaged reactor only produces half the power the thruster draws.
+    let mut ship = create_module_ship(&catalog, &voxels);
+    let reactor_health = thruster.power_draw * 0.5 / reactor.module_output;
+    ship.body_mut().damage_voxel(reactor_coords, reactor.max_life * (1.0 - reactor_health));
+    assert!(testing::equals_with_delta(forward_velocity(&mut ship), thruster.module_output * 0.5, 0.01));
+}
+
+#[test]
+fn ship_gyroscopes() {
+    let catalog = VoxelCatalog::create();
+    let torque = catalog.get_descriptor(GYROSCOPE).module_output;
+    let voxels = [(Vect3i::new([0, 0, 0]), THRUSTER), (Vect3i::new([1, 0, 0]), REACTOR), (Vect3i::new([2, 0, 0]), GYROSCOPE)];
+
+    let mut ship = create_module_ship(&catalog, &voxels);
+    ship.steer(&catalog, &ShipControls { thrust: Vect3f::zero(), rotation: Vect3f::new([0.0, 0.0, 1.0]) }, 0.5);
+    let expected_angular_velocity = ship.body().angular_velocity_from_momentum(Vect3f::new([0.0, 0.0, torque * 0.5]));
+    assert!((ship.body().angular_velocity() - expected_angular_velocity).length() < 0.0001);
+    assert!(ship.body().velocity() == Vect3f::zero());
+
+    // Pushing sideways from behind the center of mass makes the ship turn, unless a gyroscope holds it.
+    let mut ship = create_module_ship(&catalog, &voxels);
+    ship.steer(&catalog, &thrust_controls([0.0, 1.0, 0.0]), 0.5);
+    assert!(ship.body().velocity()[1] > 0.0);
+    assert!(ship.body().angular_velocity().length() < 0.0001);
+
+    let mut ship = create_module_ship(&catalog, &voxels[0..2]);
+    ship.steer(&catalog, &thrust_controls([0.0, 1.0, 0.0]), 0.5);
+    assert!(ship.body().velocity()[1] > 0.0);
+    assert!(ship.body().angular_velocity()[2] < 0.0);
+
+    // Without gyroscope, the pilot cannot turn the ship.
+    let mut ship = create_module_ship(&catalog, &voxels[0..2]);
+    ship.steer(&catalog, &ShipControls { thrust: Vect3f::zero(), rotation: Vect3f::new([0.0, 0.0, 1.0]) }, 0.5);
+    assert!(ship.body().angular_velocity() == Vect3f::zero());
 }
@@ -2,21 +2,24 @@ use std::time::Duration;
 use std::time::Instant;
 
 use crate::maths::vector::Vect3f;
+use crate::voxels::voxel::DamageType;
 use super::projectile::Projectile;
 
 pub struct Weapon {
     firerate: Duration,
     damage: f32,
+    damage_type: DamageType,
     projectile_speed: f32,
     max_range: f32,
     last_shot_time: Instant,
 }
 
 impl Weapon {
-    pub fn new(firerate: f32, damage: f32, projectile_speed: f32, max_range: f32) -> Self {
+    pub fn new(firerate: f32, damage: f32, damage_type: DamageType, projectile_speed: f32, max_range: f32) -> Self {
         Self {
             firerate: Duration::from_secs_f32(firerate),
             damage: damage,
+            damage_type: damage_type,
             projectile_speed: projectile_speed,
             max_range: max_range,
             last_shot_time: Instant::now(),
@@ -26,7 +29,7 @@ impl Weapon {
     pub fn shoot(&mut self, position: Vect3f, direction: Vect3f) -> Option<Projectile> {
         if self.last_shot_time.elapsed() > self.firerate {
             self.last_shot_time = Instant::now();
-            return Some(Projectile::new(position, direction * self.projectile_speed, self.damage, self.max_range));
+            return Some(Projectile::new(position, direction * self.projectile_speed, self.damage, self.damage_type, self.max_range));
         }
         None
     }